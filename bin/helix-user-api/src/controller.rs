@@ -1,2 +1,3 @@
 pub mod business_controller;
 pub mod internal_controller;
+pub mod problem;
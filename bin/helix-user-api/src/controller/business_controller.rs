@@ -1,214 +1,1690 @@
+use crate::controller::problem::*;
 use crate::state::AppState;
 use actix_web::web::Data;
 use actix_web::{web, HttpRequest, HttpResponse};
-use helix_auth_lib::HelixAuth;
-use helix_user_domain::core::app_user::AppUser;
-use helix_user_domain::core::person::Person;
-use std::sync::{Arc, Mutex};
+use helix_user_domain::business::authorization::Actor;
+use helix_user_domain::business::error::UserDomainError;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::login::{LoginChannel, LoginContext};
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::query::{
+    AuditQuery, DeletedQuery, Pagination, PersonQuery, UserQuery,
+};
+use helix_user_domain::core::role::Permission;
+use helix_user_domain::core::view::{
+    AuditEntryView, GroupView, LoginEventView, OrganizationView, PersonView, RoleView, SessionView,
+    StatusChangeView, UserView,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginData {
     login: String,
     password: String,
+    //Label of the device, shown in the session list.
+    #[serde(default)]
+    device: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct AccessToken {
-    access_token: String,
+pub struct RefreshToken {
     refresh_token: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RefreshToken {
-    refresh_token: String,
+fn get_login_context(req: &HttpRequest) -> LoginContext {
+    let ip = req
+        .connection_info()
+        .realip_remote_addr()
+        //Drop the port, attempts are counted per address.
+        .map(|addr| match addr.parse::<std::net::SocketAddr>() {
+            Ok(socket) => socket.ip().to_string(),
+            Err(_) => addr.to_string(),
+        });
+    let user_agent = req
+        .headers()
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    LoginContext::new(LoginChannel::Rest, ip, user_agent)
+}
+
+//Caller of the request, authenticated by the tenant resolver from the Authorization header.
+fn get_actor(req: &HttpRequest) -> Result<Actor, HttpResponse> {
+    match req.extensions().get::<Actor>() {
+        Some(actor) => Ok(actor.clone()),
+        None => Err(error_response(UserDomainError::InvalidToken)),
+    }
+}
+
+//Organization resolved by the tenant resolver, for the anonymous routes.
+fn get_tenant(req: &HttpRequest) -> Result<Organization, HttpResponse> {
+    match req.extensions().get::<Organization>() {
+        Some(organization) => Ok(organization.clone()),
+        None => Err(internal_error()),
+    }
+}
+
+//Author of a mutation with the id of its request, taken from the
+//X-Request-Id header so entries can be matched with the proxy logs.
+fn get_audit_context(req: &HttpRequest, actor: Option<&Actor>) -> AuditContext {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    AuditContext::new(actor.map(|actor| actor.user_uuid), Some(request_id))
+}
+
+fn get_uuid_param(req: &HttpRequest) -> Result<uuid::Uuid, HttpResponse> {
+    get_named_uuid_param(req, "uuid")
+}
+
+fn get_named_uuid_param(req: &HttpRequest, name: &str) -> Result<uuid::Uuid, HttpResponse> {
+    match req.match_info().get(name).map(uuid::Uuid::parse_str) {
+        Some(Ok(uuid)) => Ok(uuid),
+        _ => Err(bad_request("Invalid uuid.")),
+    }
 }
 
 pub async fn login(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
+    state: Data<AppState>,
+    req: HttpRequest,
     login_data: web::Json<LoginData>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
+    let context = get_login_context(&req);
+
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
 
-    match domain.login(&login_data.login, &login_data.password).await {
-        Ok(app_user) => {
-            let generated_keys = HelixAuth::generate_tokens(
-                &login_data.login,
-                &app_user.uuid.unwrap(),
-                &app_user.person.uuid.unwrap(),
-            );
+    match domain
+        .login(
+            &organization,
+            &login_data.login,
+            &login_data.password,
+            &context,
+        )
+        .await
+    {
+        Ok(app_user) => match domain
+            .start_session(
+                &organization,
+                &app_user,
+                &context,
+                login_data.device.clone(),
+            )
+            .await
+        {
+            //Tokens, or a challenge when the user has a second factor.
+            Ok(step) => HttpResponse::Ok().json(step),
+            Err(error) => error_response(error),
+        },
+        Err(error) => error_response(error),
+    }
+}
 
-            match generated_keys {
-                Ok(generated_keys) => {
-                    let atoken: AccessToken = AccessToken {
-                        access_token: generated_keys.0,
-                        refresh_token: generated_keys.1,
-                    };
+pub async fn verify_login(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<VerifyLoginCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+    let context = get_login_context(&req);
 
-                    HttpResponse::Ok().json(atoken)
-                }
-                Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-            }
-        }
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
 
-        Err(_) => HttpResponse::Unauthorized().body("{'message':'invalid credentials'}"),
+    match domain
+        .verify_login(&organization, json.into_inner(), &context)
+        .await
+    {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(error) => error_response(error),
     }
 }
 
-pub async fn refresh(refresh_token: web::Json<RefreshToken>) -> HttpResponse {
-    let generated_keys = HelixAuth::refresh_tokens(&refresh_token.refresh_token);
+//Accepted whether the account exists or not.
+pub async fn request_password_reset(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<RequestPasswordResetCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
 
-    match generated_keys {
-        Ok(generated_keys) => {
-            let atoken: AccessToken = AccessToken {
-                access_token: generated_keys.0,
-                refresh_token: generated_keys.1,
-            };
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
 
-            HttpResponse::Ok().json(atoken)
-        }
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
+    match domain
+        .request_password_reset(&organization, json.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::Accepted().body("Password reset requested."),
+    }
+}
+
+pub async fn reset_password(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<ResetPasswordCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
+
+    let context = get_audit_context(&req, None);
+    match domain
+        .reset_password(&organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Password reset."),
+    }
+}
+
+pub async fn verify_email(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<VerifyEmailCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
+
+    let context = get_audit_context(&req, None);
+    match domain
+        .verify_email(&organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Email verified."),
+    }
+}
+
+pub async fn refresh(
+    state: Data<AppState>,
+    req: HttpRequest,
+    refresh_token: web::Json<RefreshToken>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
+
+    match domain
+        .refresh(&organization, &refresh_token.refresh_token)
+        .await
+    {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(error) => error_response(error),
+    }
+}
+
+pub async fn logout(
+    state: Data<AppState>,
+    req: HttpRequest,
+    refresh_token: web::Json<RefreshToken>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let organization = match get_tenant(&req) {
+        Ok(organization) => organization,
+        Err(response) => return response,
+    };
+
+    match domain
+        .logout(&organization, &refresh_token.refresh_token)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Logged out."),
     }
 }
 
 pub async fn get_all_persons(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    _req: HttpRequest,
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<PersonQuery>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
-    match domain.get_all_persons().await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(persons) => HttpResponse::Ok().json(persons),
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::PersonsRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .get_all_persons(&actor.organization, query.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(persons) => HttpResponse::Ok().json(persons.map(PersonView::from)),
     }
 }
 
-pub async fn get_person(wrap_state: Data<Arc<Mutex<AppState>>>, req: HttpRequest) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
+pub async fn get_person(
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<DeletedQuery>,
+) -> HttpResponse {
     let domain = state.get_domain();
 
-    let uuid: uuid::Uuid = uuid::Uuid::parse_str(req.match_info().get("uuid").unwrap()).unwrap();
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_person_or(&uuid, Permission::PersonsRead) {
+        return error_response(error);
+    }
 
-    match domain.get_person(&uuid).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
+    match domain
+        .get_person(&actor.organization, &uuid, query.include_deleted)
+        .await
+    {
+        Err(error) => error_response(error),
         Ok(wrap_person) => match wrap_person {
-            None => HttpResponse::NotFound().body("Person not found."),
-            Some(person) => HttpResponse::Ok().json(person),
+            None => error_response(UserDomainError::not_found("Person")),
+            Some(person) => HttpResponse::Ok().json(PersonView::from(person)),
         },
     }
 }
 
+pub async fn send_email_verification(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_person_or(&uuid, Permission::PersonsWrite) {
+        return error_response(error);
+    }
+
+    match domain
+        .send_email_verification(&actor.organization, &uuid)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::Accepted().body("Email verification sent."),
+    }
+}
+
 pub async fn create_person(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<Person>,
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<CreatePersonCommand>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    let person: Person = json.into_inner();
-    match domain.create_person(person).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(created_person) => HttpResponse::Created().json(created_person),
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::PersonsWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .create_person(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_person) => HttpResponse::Created().json(PersonView::from(created_person)),
     }
 }
 
 pub async fn update_person(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<Person>,
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<UpdatePersonCommand>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    let person: Person = json.into_inner();
-    match domain.update_person(person).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(updated_person) => HttpResponse::Created().json(updated_person),
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_person_or(&json.uuid, Permission::PersonsWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .update_person(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(updated_person) => HttpResponse::Created().json(PersonView::from(updated_person)),
     }
 }
 
-pub async fn delete_person(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<Person>,
-) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
+pub async fn delete_person(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
     let domain = state.get_domain();
 
-    let person: Person = json.into_inner();
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::PersonsDelete) {
+        return error_response(error);
+    }
+
+    let person = match domain.get_person(&actor.organization, &uuid, false).await {
+        Err(error) => return error_response(error),
+        Ok(None) => return error_response(UserDomainError::not_found("Person")),
+        Ok(Some(person)) => person,
+    };
 
-    match domain.delete_person(person).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .delete_person(&actor.organization, person, &context)
+        .await
+    {
+        Err(error) => error_response(error),
         Ok(_) => HttpResponse::NoContent().body("Person deleted."),
     }
 }
 
+pub async fn restore_person(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::PersonsDelete) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .restore_person(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(person) => HttpResponse::Ok().json(PersonView::from(person)),
+    }
+}
+
 pub async fn get_all_users(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    _req: HttpRequest,
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<UserQuery>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    match domain.get_all_users().await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(users) => HttpResponse::Ok().json(users),
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .get_all_users(&actor.organization, query.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(users) => HttpResponse::Ok().json(users.map(UserView::from)),
     }
 }
 
-pub async fn get_user(wrap_state: Data<Arc<Mutex<AppState>>>, req: HttpRequest) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
+pub async fn get_user(
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<DeletedQuery>,
+) -> HttpResponse {
     let domain = state.get_domain();
 
-    //TODO: Control parse
-    let uuid: uuid::Uuid = uuid::Uuid::parse_str(req.match_info().get("uuid").unwrap()).unwrap();
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
 
-    match domain.get_user(&uuid).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .get_user(&actor.organization, &uuid, query.include_deleted)
+        .await
+    {
+        Err(error) => error_response(error),
         Ok(wrap_user) => match wrap_user {
-            None => HttpResponse::NotFound().body("User not found."),
-            Some(user) => HttpResponse::Ok().json(user),
+            None => error_response(UserDomainError::not_found("User")),
+            //Groups are not resolved for deleted users.
+            Some(user) if user.is_deleted() => HttpResponse::Ok().json(UserView::from(user)),
+            Some(user) => match domain.get_user_groups(&actor.organization, &uuid).await {
+                Err(error) => error_response(error),
+                Ok(groups) => HttpResponse::Ok().json(UserView::from(user).with_groups(groups)),
+            },
+        },
+    }
+}
+
+pub async fn get_user_photo(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain.get_user(&actor.organization, &uuid, false).await {
+        Err(error) => error_response(error),
+        Ok(wrap_user) => match wrap_user.and_then(|user| user.photo) {
+            None => error_response(UserDomainError::not_found("Photo")),
+            Some(photo) => HttpResponse::Ok()
+                .content_type("application/octet-stream")
+                .body(photo),
         },
     }
 }
 
 pub async fn create_user(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<AppUser>,
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<CreateUserCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .create_user(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_user) => HttpResponse::Created().json(UserView::from(created_user)),
+    }
+}
+
+pub async fn register_account(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<RegisterAccountCommand>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    let user: AppUser = json.into_inner();
-    match domain.create_user(user).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(created_user) => HttpResponse::Created().json(created_user),
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor
+        .require(Permission::PersonsWrite)
+        .and_then(|_| actor.require(Permission::UsersWrite))
+    {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .register_account(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_user) => HttpResponse::Created().json(UserView::from(created_user)),
     }
 }
 
 pub async fn update_user(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<AppUser>,
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<UpdateUserCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&json.uuid, Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .update_user(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(updated_user) => HttpResponse::Created().json(UserView::from(updated_user)),
+    }
+}
+
+pub async fn change_password(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<ChangePasswordCommand>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    let user: AppUser = json.into_inner();
-    match domain.update_user(user).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
-        Ok(updated_user) => HttpResponse::Created().json(updated_user),
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .change_password(&actor.organization, &uuid, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Password changed."),
+    }
+}
+
+pub async fn unlock_user(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .unlock_user(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("User unlocked."),
     }
 }
 
-pub async fn delete_user(
-    wrap_state: Data<Arc<Mutex<AppState>>>,
-    json: web::Json<AppUser>,
+//The acting user is recorded as the author of the change.
+pub async fn change_user_status(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<ChangeStatusCommand>,
 ) -> HttpResponse {
-    let state = wrap_state.lock().unwrap();
     let domain = state.get_domain();
 
-    let user: AppUser = json.into_inner();
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .change_user_status(&actor.organization, &uuid, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(user) => HttpResponse::Ok().json(UserView::from(user)),
+    }
+}
+
+pub async fn get_user_status_history(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain.get_status_history(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(changes) => HttpResponse::Ok().json(
+            changes
+                .into_iter()
+                .map(StatusChangeView::from)
+                .collect::<Vec<StatusChangeView>>(),
+        ),
+    }
+}
+
+pub async fn get_two_factor(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain.get_two_factor(&actor.organization, &uuid).await {
+        Ok(status) => HttpResponse::Ok().json(status),
+        Err(error) => error_response(error),
+    }
+}
+
+//The secret is only ever shown to the user itself.
+pub async fn enroll_two_factor(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self(&uuid) {
+        return error_response(error);
+    }
+
+    match domain.enroll_two_factor(&actor.organization, &uuid).await {
+        Ok(setup) => HttpResponse::Created().json(setup),
+        Err(error) => error_response(error),
+    }
+}
+
+pub async fn confirm_two_factor(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<ConfirmTwoFactorCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
 
-    match domain.delete_user(user).await {
-        Err(_) => HttpResponse::InternalServerError().body("Internal Server Error."),
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self(&uuid) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .confirm_two_factor(&actor.organization, &uuid, json.into_inner(), &context)
+        .await
+    {
+        Ok(recovery_codes) => HttpResponse::Ok().json(recovery_codes),
+        Err(error) => error_response(error),
+    }
+}
+
+pub async fn reset_two_factor(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersWrite) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .reset_two_factor(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Second factor reset."),
+    }
+}
+
+pub async fn get_user_sessions(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::SessionsManage) {
+        return error_response(error);
+    }
+
+    match domain.get_user_sessions(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(sessions) => HttpResponse::Ok().json(
+            sessions
+                .into_iter()
+                .map(SessionView::from)
+                .collect::<Vec<SessionView>>(),
+        ),
+    }
+}
+
+pub async fn delete_user_session(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let session_uuid = match get_named_uuid_param(&req, "session_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::SessionsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .delete_user_session(&actor.organization, &uuid, &session_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Session deleted."),
+    }
+}
+
+pub async fn revoke_user_sessions(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::SessionsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .revoke_user_sessions(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Sessions revoked."),
+    }
+}
+
+pub async fn get_user_logins(
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<Pagination>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::UsersRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .get_login_history(&actor.organization, &uuid, query.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(events) => HttpResponse::Ok().json(events.map(LoginEventView::from)),
+    }
+}
+
+pub async fn delete_user(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersDelete) {
+        return error_response(error);
+    }
+
+    let user = match domain.get_user(&actor.organization, &uuid, false).await {
+        Err(error) => return error_response(error),
+        Ok(None) => return error_response(UserDomainError::not_found("User")),
+        Ok(Some(user)) => user,
+    };
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .delete_user(&actor.organization, user, &context)
+        .await
+    {
+        Err(error) => error_response(error),
         Ok(_) => HttpResponse::NoContent().body("User deleted."),
     }
 }
+
+pub async fn restore_user(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::UsersDelete) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .restore_user(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(user) => HttpResponse::Ok().json(UserView::from(user)),
+    }
+}
+
+pub async fn get_all_organizations(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_default_organization(Permission::OrganizationsManage) {
+        return error_response(error);
+    }
+
+    match domain.get_all_organizations().await {
+        Err(error) => error_response(error),
+        Ok(organizations) => HttpResponse::Ok().json(
+            organizations
+                .into_iter()
+                .map(OrganizationView::from)
+                .collect::<Vec<OrganizationView>>(),
+        ),
+    }
+}
+
+pub async fn get_organization(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_default_organization(Permission::OrganizationsManage) {
+        return error_response(error);
+    }
+
+    match domain.get_organization(&uuid).await {
+        Err(error) => error_response(error),
+        Ok(None) => error_response(UserDomainError::not_found("Organization")),
+        Ok(Some(organization)) => HttpResponse::Ok().json(OrganizationView::from(organization)),
+    }
+}
+
+//Creates the organization with its first admin, who can log in right away.
+pub async fn create_organization(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<CreateOrganizationCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_default_organization(Permission::OrganizationsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .create_organization(json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_organization) => {
+            HttpResponse::Created().json(OrganizationView::from(created_organization))
+        }
+    }
+}
+
+pub async fn update_organization(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<UpdateOrganizationCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_default_organization(Permission::OrganizationsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .update_organization(json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(updated_organization) => {
+            HttpResponse::Ok().json(OrganizationView::from(updated_organization))
+        }
+    }
+}
+
+pub async fn get_all_roles(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    match domain.get_all_roles(&actor.organization).await {
+        Err(error) => error_response(error),
+        Ok(roles) => HttpResponse::Ok().json(
+            roles
+                .into_iter()
+                .map(RoleView::from)
+                .collect::<Vec<RoleView>>(),
+        ),
+    }
+}
+
+pub async fn get_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    match domain.get_role(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(None) => error_response(UserDomainError::not_found("Role")),
+        Ok(Some(role)) => HttpResponse::Ok().json(RoleView::from(role)),
+    }
+}
+
+pub async fn create_role(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<CreateRoleCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .create_role(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_role) => HttpResponse::Created().json(RoleView::from(created_role)),
+    }
+}
+
+pub async fn update_role(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<UpdateRoleCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .update_role(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(updated_role) => HttpResponse::Created().json(RoleView::from(updated_role)),
+    }
+}
+
+pub async fn delete_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .delete_role(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Role deleted."),
+    }
+}
+
+pub async fn get_user_roles(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    match domain.get_user_roles(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(roles) => HttpResponse::Ok().json(
+            roles
+                .into_iter()
+                .map(RoleView::from)
+                .collect::<Vec<RoleView>>(),
+        ),
+    }
+}
+
+pub async fn assign_user_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let role_uuid = match get_named_uuid_param(&req, "role_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .assign_role(&actor.organization, &uuid, &role_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Role assigned."),
+    }
+}
+
+pub async fn unassign_user_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let role_uuid = match get_named_uuid_param(&req, "role_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .unassign_role(&actor.organization, &uuid, &role_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Role unassigned."),
+    }
+}
+
+pub async fn get_all_groups(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsRead) {
+        return error_response(error);
+    }
+
+    match domain.get_all_groups(&actor.organization).await {
+        Err(error) => error_response(error),
+        Ok(groups) => HttpResponse::Ok().json(
+            groups
+                .into_iter()
+                .map(GroupView::from)
+                .collect::<Vec<GroupView>>(),
+        ),
+    }
+}
+
+pub async fn get_group(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_member_or(&uuid, Permission::GroupsRead) {
+        return error_response(error);
+    }
+
+    match domain.get_group(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(None) => error_response(UserDomainError::not_found("Group")),
+        Ok(Some(group)) => HttpResponse::Ok().json(GroupView::from(group)),
+    }
+}
+
+pub async fn create_group(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<CreateGroupCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .create_group(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(created_group) => HttpResponse::Created().json(GroupView::from(created_group)),
+    }
+}
+
+pub async fn update_group(
+    state: Data<AppState>,
+    req: HttpRequest,
+    json: web::Json<UpdateGroupCommand>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .update_group(&actor.organization, json.into_inner(), &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(updated_group) => HttpResponse::Created().json(GroupView::from(updated_group)),
+    }
+}
+
+pub async fn delete_group(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .delete_group(&actor.organization, &uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Group deleted."),
+    }
+}
+
+pub async fn get_group_members(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_member_or(&uuid, Permission::GroupsRead) {
+        return error_response(error);
+    }
+
+    match domain.get_group_members(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(members) => HttpResponse::Ok().json(
+            members
+                .into_iter()
+                .map(UserView::from)
+                .collect::<Vec<UserView>>(),
+        ),
+    }
+}
+
+pub async fn add_group_member(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let user_uuid = match get_named_uuid_param(&req, "user_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .add_group_member(&actor.organization, &uuid, &user_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Member added."),
+    }
+}
+
+pub async fn remove_group_member(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let user_uuid = match get_named_uuid_param(&req, "user_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::GroupsManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .remove_group_member(&actor.organization, &uuid, &user_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Member removed."),
+    }
+}
+
+pub async fn get_group_roles(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_member_or(&uuid, Permission::GroupsRead) {
+        return error_response(error);
+    }
+
+    match domain.get_group_roles(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(roles) => HttpResponse::Ok().json(
+            roles
+                .into_iter()
+                .map(RoleView::from)
+                .collect::<Vec<RoleView>>(),
+        ),
+    }
+}
+
+pub async fn assign_group_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let role_uuid = match get_named_uuid_param(&req, "role_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .assign_group_role(&actor.organization, &uuid, &role_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Role assigned."),
+    }
+}
+
+pub async fn unassign_group_role(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+    let role_uuid = match get_named_uuid_param(&req, "role_uuid") {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::RolesManage) {
+        return error_response(error);
+    }
+
+    let context = get_audit_context(&req, Some(&actor));
+    match domain
+        .unassign_group_role(&actor.organization, &uuid, &role_uuid, &context)
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(_) => HttpResponse::NoContent().body("Role unassigned."),
+    }
+}
+
+pub async fn get_user_groups(state: Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let uuid = match get_uuid_param(&req) {
+        Ok(uuid) => uuid,
+        Err(response) => return response,
+    };
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require_self_or(&uuid, Permission::GroupsRead) {
+        return error_response(error);
+    }
+
+    match domain.get_user_groups(&actor.organization, &uuid).await {
+        Err(error) => error_response(error),
+        Ok(groups) => HttpResponse::Ok().json(
+            groups
+                .into_iter()
+                .map(GroupView::from)
+                .collect::<Vec<GroupView>>(),
+        ),
+    }
+}
+
+pub async fn get_audit_entries(
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::AuditRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .get_audit_entries(&actor.organization, query.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(entries) => HttpResponse::Ok().json(entries.map(AuditEntryView::from)),
+    }
+}
+
+//JSON Lines, one entry per line, with the filters of the list.
+pub async fn export_audit_entries(
+    state: Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+) -> HttpResponse {
+    let domain = state.get_domain();
+
+    let actor = match get_actor(&req) {
+        Ok(actor) => actor,
+        Err(response) => return response,
+    };
+    if let Err(error) = actor.require(Permission::AuditRead) {
+        return error_response(error);
+    }
+
+    match domain
+        .export_audit_entries(&actor.organization, query.into_inner())
+        .await
+    {
+        Err(error) => error_response(error),
+        Ok(entries) => {
+            let mut body = String::new();
+            for entry in entries {
+                match serde_json::to_string(&AuditEntryView::from(entry)) {
+                    Ok(line) => body.push_str(&line),
+                    Err(_) => return internal_error(),
+                }
+                body.push('\n');
+            }
+            HttpResponse::Ok()
+                .content_type("application/x-ndjson")
+                .body(body)
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use actix_web::error::{InternalError, JsonPayloadError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use chrono::prelude::*;
+use helix_user_domain::business::error::{FieldError, UserDomainError};
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+//Problem details body (RFC 7807) returned on every error.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub errors: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &str, detail: String) -> Problem {
+        Problem {
+            problem_type: format!("/problems/{}", code),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            detail,
+            errors: Vec::new(),
+            locked_until: None,
+        }
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        let mut response = HttpResponse::build(StatusCode::from_u16(self.status).unwrap());
+        if let Some(locked_until) = self.locked_until {
+            let seconds = (locked_until - Utc::now()).num_seconds().max(1);
+            response.header("Retry-After", seconds.to_string());
+        }
+        response.content_type(PROBLEM_CONTENT_TYPE).json(self)
+    }
+}
+
+impl From<&UserDomainError> for Problem {
+    fn from(error: &UserDomainError) -> Problem {
+        let status = get_status_code(error);
+
+        //Do not leak internal details to clients.
+        let detail = match status {
+            StatusCode::INTERNAL_SERVER_ERROR => "Internal Server Error.".to_string(),
+            _ => error.to_string(),
+        };
+
+        let mut problem = Problem::new(status, error.code(), detail);
+        problem.errors = error.field_errors();
+        problem.locked_until = error.get_retry_after();
+        problem
+    }
+}
+
+pub fn get_status_code(error: &UserDomainError) -> StatusCode {
+    match error {
+        UserDomainError::NotImplemented => StatusCode::NOT_IMPLEMENTED,
+        UserDomainError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+        UserDomainError::NotFound { .. } => StatusCode::NOT_FOUND,
+        UserDomainError::Conflict { .. } => StatusCode::CONFLICT,
+        UserDomainError::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        UserDomainError::InvalidToken => StatusCode::UNAUTHORIZED,
+        UserDomainError::Forbidden => StatusCode::FORBIDDEN,
+        UserDomainError::EmailNotVerified => StatusCode::FORBIDDEN,
+        UserDomainError::AccountInactive { .. } => StatusCode::FORBIDDEN,
+        UserDomainError::AccountLocked { .. } => StatusCode::LOCKED,
+        UserDomainError::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+        UserDomainError::BackendUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        UserDomainError::PasswordHashError => StatusCode::INTERNAL_SERVER_ERROR,
+        UserDomainError::TokenGenerationError => StatusCode::INTERNAL_SERVER_ERROR,
+        UserDomainError::NotificationError => StatusCode::INTERNAL_SERVER_ERROR,
+        UserDomainError::MissingUuid { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        UserDomainError::Storage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+pub fn error_response(error: UserDomainError) -> HttpResponse {
+    if get_status_code(&error) == StatusCode::INTERNAL_SERVER_ERROR {
+        println!("Internal error: {}", error);
+    }
+    Problem::from(&error).into_response()
+}
+
+pub fn bad_request(detail: &str) -> HttpResponse {
+    Problem::new(StatusCode::BAD_REQUEST, "bad_request", detail.to_string()).into_response()
+}
+
+pub fn internal_error() -> HttpResponse {
+    Problem::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal_error",
+        "Internal Server Error.".to_string(),
+    )
+    .into_response()
+}
+
+//Answer malformed query strings with a problem instead of plain text.
+pub fn query_error_handler(error: QueryPayloadError, _req: &HttpRequest) -> Error {
+    let response = bad_request(&error.to_string());
+    InternalError::from_response(error, response).into()
+}
+
+//Same for bodies that do not match the expected command.
+pub fn json_error_handler(error: JsonPayloadError, _req: &HttpRequest) -> Error {
+    let response = bad_request(&error.to_string());
+    InternalError::from_response(error, response).into()
+}
@@ -0,0 +1,141 @@
+#[macro_use]
+extern crate serde_derive;
+
+pub mod controller;
+pub mod tenant;
+
+pub use helix_user_runtime::{configuration, state};
+
+use crate::controller::business_controller::*;
+use crate::tenant::TenantResolver;
+use actix_web::web;
+
+pub const APP_NAME: &str = "USER_APP";
+
+pub fn get_routes_configuration(cfg: &mut web::ServiceConfig) {
+    //----------------------------------------------------------
+    //___DOMAIN___
+    //----------------------------------------------------------
+    //----------------------------------------------------------
+    //___USERSTORE___
+    //----------------------------------------------------------
+    cfg.service(
+        web::scope("")
+            .wrap(TenantResolver)
+            .route("/login", web::post().to(login))
+            .route("/login", web::put().to(refresh))
+            .route("/login", web::delete().to(logout))
+            .route("/login/verify", web::post().to(verify_login))
+            .route("/password/reset", web::post().to(request_password_reset))
+            .route("/password/reset", web::put().to(reset_password))
+            .route("/email/verify", web::post().to(verify_email))
+            .service(
+                web::scope("/persons")
+                    .route("", web::get().to(get_all_persons))
+                    .route("", web::post().to(create_person))
+                    .route("", web::put().to(update_person))
+                    //.route("", web::delete().to(delete_person))
+                    .service(
+                        web::scope("/{uuid}")
+                            .route("", web::get().to(get_person))
+                            .route("", web::delete().to(delete_person))
+                            .route("/restore", web::put().to(restore_person))
+                            .route(
+                                "/email/verification",
+                                web::post().to(send_email_verification),
+                            ),
+                    ),
+            )
+            .service(
+                web::scope("/users")
+                    .route("", web::get().to(get_all_users))
+                    .route("", web::post().to(create_user))
+                    .route("", web::put().to(update_user))
+                    //.route("", web::delete().to(delete_user))
+                    .service(
+                        web::scope("/{uuid}")
+                            .route("", web::get().to(get_user))
+                            .route("", web::delete().to(delete_user))
+                            .route("/restore", web::put().to(restore_user))
+                            .route("/password", web::put().to(change_password))
+                            .route("/lock", web::delete().to(unlock_user))
+                            .route("/status", web::put().to(change_user_status))
+                            .route("/status", web::get().to(get_user_status_history))
+                            .route("/two-factor", web::get().to(get_two_factor))
+                            .route("/two-factor", web::post().to(enroll_two_factor))
+                            .route("/two-factor", web::put().to(confirm_two_factor))
+                            .route("/two-factor", web::delete().to(reset_two_factor))
+                            .route("/logins", web::get().to(get_user_logins))
+                            .route("/sessions", web::get().to(get_user_sessions))
+                            .route("/sessions", web::delete().to(revoke_user_sessions))
+                            .route(
+                                "/sessions/{session_uuid}",
+                                web::delete().to(delete_user_session),
+                            )
+                            .route("/roles", web::get().to(get_user_roles))
+                            .route("/roles/{role_uuid}", web::put().to(assign_user_role))
+                            .route("/roles/{role_uuid}", web::delete().to(unassign_user_role))
+                            .route("/groups", web::get().to(get_user_groups))
+                            .route("/photo", web::get().to(get_user_photo)),
+                    ),
+            )
+            .service(
+                web::scope("/organizations")
+                    .route("", web::get().to(get_all_organizations))
+                    .route("", web::post().to(create_organization))
+                    .route("", web::put().to(update_organization))
+                    .service(web::scope("/{uuid}").route("", web::get().to(get_organization))),
+            )
+            .service(
+                web::scope("/roles")
+                    .route("", web::get().to(get_all_roles))
+                    .route("", web::post().to(create_role))
+                    .route("", web::put().to(update_role))
+                    .service(
+                        web::scope("/{uuid}")
+                            .route("", web::get().to(get_role))
+                            .route("", web::delete().to(delete_role)),
+                    ),
+            )
+            .service(
+                web::scope("/groups")
+                    .route("", web::get().to(get_all_groups))
+                    .route("", web::post().to(create_group))
+                    .route("", web::put().to(update_group))
+                    .service(
+                        web::scope("/{uuid}")
+                            .route("", web::get().to(get_group))
+                            .route("", web::delete().to(delete_group))
+                            .route("/members", web::get().to(get_group_members))
+                            .route("/members/{user_uuid}", web::put().to(add_group_member))
+                            .route(
+                                "/members/{user_uuid}",
+                                web::delete().to(remove_group_member),
+                            )
+                            .route("/roles", web::get().to(get_group_roles))
+                            .route("/roles/{role_uuid}", web::put().to(assign_group_role))
+                            .route("/roles/{role_uuid}", web::delete().to(unassign_group_role)),
+                    ),
+            )
+            .service(web::scope("/accounts").route("", web::post().to(register_account)))
+            .service(
+                web::scope("/audit")
+                    .route("", web::get().to(get_audit_entries))
+                    .route("/export", web::get().to(export_audit_entries)),
+            ),
+    );
+}
+
+pub fn get_exception_uri() -> Vec<String> {
+    vec![
+        "/api/_".to_string(),
+        "/api/version".to_string(),
+        "/api/login".to_string(),
+        //Second login step, the caller only holds a challenge token.
+        "/api/login/verify".to_string(),
+        //Forgotten passwords, the caller has no token at all.
+        "/api/password/reset".to_string(),
+        //Unverified users may not log in, the token they received is all they have.
+        "/api/email/verify".to_string(),
+    ]
+}
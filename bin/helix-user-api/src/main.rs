@@ -1,20 +1,11 @@
-#[macro_use]
-extern crate serde_derive;
-
-pub mod configuration;
-pub mod controller;
-pub mod state;
-
-use crate::controller::{business_controller::*, internal_controller::*};
-use crate::state::AppState;
 use actix_web::{middleware, web, App, HttpServer};
 use helix_auth_lib::middleware::AuthValidator;
 use helix_config_lib::Configuration as GlobalConfiguration;
-use std::sync::{Arc, Mutex};
+use helix_user_api::controller::{internal_controller::*, problem::*};
+use helix_user_api::state::AppState;
+use helix_user_api::{get_exception_uri, get_routes_configuration, APP_NAME};
 use std::{env, io};
 
-const APP_NAME: &str = "USER_APP";
-
 #[actix_rt::main]
 async fn main() -> io::Result<()> {
     println!("[HELIX {} {}]", APP_NAME, env!("CARGO_PKG_VERSION"));
@@ -31,8 +22,15 @@ async fn main() -> io::Result<()> {
     std::env::set_var("RUST_LOG", "actix_web=info");
     env_logger::init();
 
-    //Define a global state for all the Actix-Worker
-    let app_state = Arc::new(Mutex::new(AppState::new()));
+    //Database schema
+    AppState::migrate_database().await;
+
+    //Shared by all the Actix-Worker, the domain needs no lock.
+    let app_state = web::Data::new(AppState::new());
+    app_state.bootstrap_admin().await;
+
+    //Deleted records are purged in the background once their retention is over.
+    actix_rt::spawn(app_state.get_ref().clone().purge_periodically());
 
     //Start server
     HttpServer::new(move || {
@@ -40,7 +38,9 @@ async fn main() -> io::Result<()> {
             .wrap(middleware::Logger::default())
             .wrap(middleware::Compress::default())
             .wrap(AuthValidator::new(get_exception_uri()))
-            .data(app_state.clone())
+            .app_data(app_state.clone())
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
             .service(
                 web::scope("/api")
                     .route("/_", web::get().to(healthcheck))
@@ -56,49 +56,3 @@ async fn main() -> io::Result<()> {
     .run()
     .await
 }
-
-fn get_routes_configuration(cfg: &mut web::ServiceConfig) {
-    //----------------------------------------------------------
-    //___DOMAIN___
-    //----------------------------------------------------------
-    //----------------------------------------------------------
-    //___USERSTORE___
-    //----------------------------------------------------------
-    cfg.service(
-        web::scope("")
-            .route("/login", web::post().to(login))
-            .route("/login", web::put().to(refresh))
-            .service(
-                web::scope("/persons")
-                    .route("", web::get().to(get_all_persons))
-                    .route("", web::post().to(create_person))
-                    .route("", web::put().to(update_person))
-                    //.route("", web::delete().to(delete_person))
-                    .service(
-                        web::scope("/{uuid}")
-                            .route("", web::get().to(get_person))
-                            .route("", web::delete().to(delete_person)),
-                    ),
-            )
-            .service(
-                web::scope("/users")
-                    .route("", web::get().to(get_all_users))
-                    .route("", web::post().to(create_user))
-                    .route("", web::put().to(update_user))
-                    //.route("", web::delete().to(delete_user))
-                    .service(
-                        web::scope("/{uuid}")
-                            .route("", web::get().to(get_user))
-                            .route("", web::delete().to(delete_user)),
-                    ),
-            ),
-    );
-}
-
-fn get_exception_uri() -> Vec<String> {
-    let mut exception_uri = Vec::new();
-    exception_uri.push("/api/_".to_string());
-    exception_uri.push("/api/version".to_string());
-    exception_uri.push("/api/login".to_string());
-    exception_uri
-}
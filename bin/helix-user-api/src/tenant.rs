@@ -0,0 +1,123 @@
+use crate::controller::problem::*;
+use crate::state::AppState;
+use actix_service::{Service, Transform};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use helix_user_domain::business::authorization::Actor;
+use helix_user_domain::business::error::UserDomainError;
+use helix_user_domain::core::organization::Organization;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+//Names the organization of anonymous requests, the default one when missing.
+pub const ORGANIZATION_HEADER: &str = "x-helix-organization";
+
+//Resolve the organization of every request before it reaches a handler.
+//Authenticated requests act in the organization of their access token,
+//a header naming another one is refused.
+//The organization, and the actor when the token is valid, are stored in the request extensions.
+pub struct TenantResolver;
+
+impl<S> Transform<S> for TenantResolver
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TenantResolverMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TenantResolverMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct TenantResolverMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for TenantResolverMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            match resolve_tenant(&req).await {
+                Ok((organization, actor)) => {
+                    req.extensions_mut().insert(organization);
+                    if let Some(actor) = actor {
+                        req.extensions_mut().insert(actor);
+                    }
+                    let response = service.borrow_mut().call(req);
+                    response.await
+                }
+                Err(response) => Ok(req.into_response(response)),
+            }
+        })
+    }
+}
+
+async fn resolve_tenant(
+    req: &ServiceRequest,
+) -> Result<(Organization, Option<Actor>), HttpResponse> {
+    let state = match req.app_data::<Data<AppState>>() {
+        Some(state) => state.clone(),
+        None => return Err(internal_error()),
+    };
+    let domain = state.get_domain();
+
+    let organization_uuid = match req.headers().get(ORGANIZATION_HEADER) {
+        Some(value) => match value.to_str().ok().map(uuid::Uuid::parse_str) {
+            Some(Ok(uuid)) => Some(uuid),
+            _ => return Err(bad_request("Invalid organization.")),
+        },
+        None => None,
+    };
+    let access_token = req
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|value| value.trim().to_string());
+
+    //Invalid tokens are left to the handlers, anonymous routes still accept them.
+    if let Some(access_token) = access_token {
+        match domain.authenticate(&access_token).await {
+            Ok(actor) => {
+                return match organization_uuid {
+                    Some(uuid) if Some(uuid) != actor.organization.uuid => {
+                        Err(error_response(UserDomainError::Forbidden))
+                    }
+                    _ => Ok((actor.organization.clone(), Some(actor))),
+                }
+            }
+            Err(UserDomainError::InvalidToken) => (),
+            Err(error) => return Err(error_response(error)),
+        }
+    }
+
+    match domain.resolve_organization(organization_uuid).await {
+        Ok(organization) => Ok((organization, None)),
+        Err(error) => Err(error_response(error)),
+    }
+}
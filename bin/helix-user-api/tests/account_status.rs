@@ -0,0 +1,139 @@
+//Only active users log in, status changes are recorded with their author.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use common::PASSWORD;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::core::app_user::AccountStatus;
+use serde_json::json;
+
+mod common;
+
+const ADMIN_LOGIN: &str = "status.admin";
+const LOGIN: &str = "status.user";
+
+#[actix_rt::test]
+async fn status_lifecycle() {
+    let settings = UserDomainSettings {
+        require_activation: true,
+        ..UserDomainSettings::default()
+    };
+    let common::Fixture {
+        domain,
+        organization,
+        admin,
+        admin_token,
+        user: app_user,
+        ..
+    } = common::with_admin(settings, ADMIN_LOGIN, LOGIN).await;
+    assert_eq!(admin.status, AccountStatus::Pending);
+    assert_eq!(app_user.status, AccountStatus::Pending);
+    let (admin_uuid, uuid) = (admin.uuid.unwrap(), app_user.uuid.unwrap());
+    let mut app = test::init_service(common::app(domain.clone())).await;
+
+    let login = |login: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": login, "password": PASSWORD }))
+            .to_request()
+    };
+
+    //The appointed admin is activated, other users wait for it.
+    let response = test::call_service(&mut app, login(ADMIN_LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&mut app, login(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], json!("account_inactive"));
+
+    let status_uri = format!("/api/users/{}/status", uuid);
+    let change_status = |body: serde_json::Value| {
+        test::TestRequest::put()
+            .uri(&status_uri)
+            .header("Authorization", format!("Bearer {}", admin_token))
+            .set_json(&body)
+            .to_request()
+    };
+
+    let response = test::call_service(
+        &mut app,
+        change_status(json!({ "status": "suspended", "reason": "Abuse" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let user: serde_json::Value =
+        test::read_response_json(&mut app, change_status(json!({ "status": "active" }))).await;
+    assert_eq!(user["status"], json!("active"));
+
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login(LOGIN)).await;
+    let refresh_token = tokens["refresh_token"].as_str().unwrap().to_string();
+    let access_token = tokens["access_token"].as_str().unwrap().to_string();
+    let get_user = |access_token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/users/{}", uuid))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request()
+    };
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    //Suspensions need a reason, they end the sessions of the user.
+    let response =
+        test::call_service(&mut app, change_status(json!({ "status": "suspended" }))).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let user: serde_json::Value = test::read_response_json(
+        &mut app,
+        change_status(json!({
+            "status": "suspended",
+            "reason": "Abuse",
+            "suspended_until": chrono::Utc::now() + chrono::Duration::days(1)
+        })),
+    )
+    .await;
+    assert_eq!(user["status"], json!("suspended"));
+    assert_eq!(user["status_reason"], json!("Abuse"));
+
+    let refresh = test::TestRequest::put()
+        .uri("/api/login")
+        .set_json(&json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = test::call_service(&mut app, refresh).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    //Nor do sessions opened while suspended, the status is checked on each request.
+    let access_token = common::open_session(&domain, &organization, &app_user).await;
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, login(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.headers().contains_key("Retry-After"));
+
+    let user: serde_json::Value =
+        test::read_response_json(&mut app, change_status(json!({ "status": "disabled" }))).await;
+    assert_eq!(user["status"], json!("disabled"));
+    let response = test::call_service(&mut app, login(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let access_token = common::open_session(&domain, &organization, &app_user).await;
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(
+        &mut app,
+        change_status(json!({ "status": "suspended", "reason": "Abuse" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let history = test::TestRequest::get()
+        .uri(&status_uri)
+        .header("Authorization", format!("Bearer {}", admin_token))
+        .to_request();
+    let changes: serde_json::Value = test::read_response_json(&mut app, history).await;
+    let changes = changes.as_array().unwrap();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0]["previous_status"], json!("suspended"));
+    assert_eq!(changes[0]["status"], json!("disabled"));
+    assert_eq!(changes[2]["previous_status"], json!("pending"));
+    assert!(changes
+        .iter()
+        .all(|change| change["changed_by"] == json!(admin_uuid)));
+}
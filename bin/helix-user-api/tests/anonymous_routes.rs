@@ -0,0 +1,129 @@
+//Routes used without an access token go through the authentication middleware.
+use actix_web::http::{Method, StatusCode};
+use actix_web::{test, web, App};
+use helix_auth_lib::middleware::AuthValidator;
+use helix_user_api::state::AppState;
+use helix_user_api::{get_exception_uri, get_routes_configuration};
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::{FileNotifier, LogNotifier, Notification};
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const ANONYMOUS_ROUTES: [(&str, &str); 4] = [
+    ("POST", "/api/login/verify"),
+    ("POST", "/api/password/reset"),
+    ("PUT", "/api/password/reset"),
+    ("POST", "/api/email/verify"),
+];
+const LOGIN: &str = "anonymous.user";
+const EMAIL: &str = "anonymous.user@helix.test";
+const PASSWORD: &str = "Correct-Horse-42";
+
+#[actix_rt::test]
+async fn anonymous_routes_skip_authentication() {
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    let mut app = test::init_service(
+        App::new()
+            .wrap(AuthValidator::new(get_exception_uri()))
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    //An empty body reaches the handler, which refuses it as malformed.
+    for (method, uri) in ANONYMOUS_ROUTES.iter() {
+        let request = test::TestRequest::with_uri(uri)
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .set_json(&json!({}))
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "{} {}",
+            method,
+            uri
+        );
+    }
+
+    let request = test::TestRequest::get().uri("/api/users").to_request();
+    let response = test::call_service(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn unverified_user_verifies_without_token() {
+    let path = std::env::temp_dir()
+        .join(format!("helix-anonymous-{}.jsonl", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .to_string();
+    let settings = UserDomainSettings {
+        require_verified_email: true,
+        ..UserDomainSettings::default()
+    };
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(FileNotifier::new(path.clone())),
+        settings,
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+    domain
+        .register_account(
+            &organization,
+            RegisterAccountCommand {
+                firstname: "Anonymous".to_string(),
+                lastname: "User".to_string(),
+                email: EMAIL.to_string(),
+                phone: None,
+                login: LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .wrap(AuthValidator::new(get_exception_uri()))
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+    let login = || {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": LOGIN, "password": PASSWORD }))
+            .to_request()
+    };
+
+    let response = test::call_service(&mut app, login()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let notification: Notification =
+        serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+    let request = test::TestRequest::post()
+        .uri("/api/email/verify")
+        .set_json(&json!({ "token": notification.token }))
+        .to_request();
+    let response = test::call_service(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, login()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    std::fs::remove_file(&path).ok();
+}
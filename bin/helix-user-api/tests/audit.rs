@@ -0,0 +1,128 @@
+//Mutations are recorded with their author and request, secrets stay out of the log.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use common::PASSWORD;
+use helix_user_domain::business::settings::UserDomainSettings;
+use serde_json::json;
+
+mod common;
+
+const ADMIN_LOGIN: &str = "audit.admin";
+const LOGIN: &str = "audit.user";
+const REQUEST_ID: &str = "audit-request-1";
+
+#[actix_rt::test]
+async fn mutations_are_audited() {
+    let common::Fixture {
+        domain,
+        admin,
+        admin_token,
+        token: user_token,
+        ..
+    } = common::with_admin(UserDomainSettings::default(), ADMIN_LOGIN, LOGIN).await;
+    let admin_uuid = admin.uuid.unwrap();
+    let mut app = test::init_service(common::app(domain)).await;
+
+    let get = |uri: &str, token: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .to_request()
+    };
+
+    let create_person = test::TestRequest::post()
+        .uri("/api/persons")
+        .header("Authorization", format!("Bearer {}", admin_token))
+        .header("X-Request-Id", REQUEST_ID)
+        .set_json(&json!({
+            "firstname": "Audited",
+            "lastname": "Person",
+            "email": "audited.person@helix.test",
+            "phone": null
+        }))
+        .to_request();
+    let person: serde_json::Value = test::read_response_json(&mut app, create_person).await;
+    let create_user = test::TestRequest::post()
+        .uri("/api/users")
+        .header("Authorization", format!("Bearer {}", admin_token))
+        .header("X-Request-Id", REQUEST_ID)
+        .set_json(&json!({
+            "login": "audited.user",
+            "password": PASSWORD,
+            "person_uuid": person["uuid"]
+        }))
+        .to_request();
+    let user: serde_json::Value = test::read_response_json(&mut app, create_user).await;
+
+    //Both creations carry the actor and the request id.
+    let entries: serde_json::Value = test::read_response_json(
+        &mut app,
+        get(
+            &format!("/api/audit?request_id={}", REQUEST_ID),
+            &admin_token,
+        ),
+    )
+    .await;
+    assert_eq!(entries["total"], json!(2));
+    let entries = entries["items"].as_array().unwrap();
+    assert!(entries
+        .iter()
+        .all(|entry| entry["actor"] == json!(admin_uuid) && entry["action"] == json!("create")));
+    assert_eq!(entries[0]["target"], json!("user"));
+    assert_eq!(entries[1]["target"], json!("person"));
+
+    //The password hash never reaches the log.
+    let entries: serde_json::Value = test::read_response_json(
+        &mut app,
+        get(
+            &format!(
+                "/api/audit?target=user&target_uuid={}",
+                user["uuid"].as_str().unwrap()
+            ),
+            &admin_token,
+        ),
+    )
+    .await;
+    assert_eq!(entries["total"], json!(1));
+    let changes = entries["items"][0]["changes"].as_array().unwrap();
+    let password = changes
+        .iter()
+        .find(|change| change["field"] == json!("password"))
+        .unwrap();
+    assert_eq!(password["before"], json!(null));
+    assert_eq!(password["after"], json!("[redacted]"));
+    assert!(changes.iter().any(
+        |change| change["field"] == json!("login") && change["after"] == json!("audited.user")
+    ));
+
+    let response = test::call_service(&mut app, get("/api/audit", &user_token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response =
+        test::call_service(&mut app, get("/api/audit?action=unknown", &admin_token)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    //Windows out of reach are refused instead of overflowing.
+    let response = test::call_service(
+        &mut app,
+        get(&format!("/api/audit?page={}", u32::MAX), &admin_token),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    //The export holds every entry, one JSON document per line.
+    let entries: serde_json::Value =
+        test::read_response_json(&mut app, get("/api/audit?limit=1", &admin_token)).await;
+    let response = test::call_service(&mut app, get("/api/audit/export", &admin_token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/x-ndjson"
+    );
+    let body = test::read_body(response).await;
+    let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(json!(lines.len()), entries["total"]);
+    assert_eq!(lines[0], entries["items"][0]);
+}
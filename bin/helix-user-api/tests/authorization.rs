@@ -0,0 +1,90 @@
+//Routes check the permissions granted by the roles of the caller.
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::audit::AuditContext;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+#[actix_rt::test]
+async fn roles_grant_permissions() {
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+    let admin = common::add_user(&domain, &organization, "role.admin").await;
+    let admin_token = common::open_session(&domain, &organization, &admin).await;
+    let user = common::add_user(&domain, &organization, "role.user").await;
+    let token = common::open_session(&domain, &organization, &user).await;
+    let (admin_uuid, uuid) = (admin.uuid.unwrap(), user.uuid.unwrap());
+    domain
+        .ensure_admin(&organization, "role.admin", &AuditContext::default())
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    let get = |uri: String, access_token: &str| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request()
+    };
+
+    let anonymous = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", uuid))
+        .to_request();
+    let response = test::call_service(&mut app, anonymous).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    //Without any role, users only see their own account.
+    let response = test::call_service(&mut app, get(format!("/api/users/{}", uuid), &token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response =
+        test::call_service(&mut app, get(format!("/api/users/{}", admin_uuid), &token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = test::call_service(&mut app, get("/api/users".to_string(), &token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let create_role = |access_token: &str| {
+        test::TestRequest::post()
+            .uri("/api/roles")
+            .header("Authorization", format!("Bearer {}", access_token))
+            .set_json(&json!({ "name": "support", "permissions": ["users_read"] }))
+            .to_request()
+    };
+    let response = test::call_service(&mut app, create_role(&token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let role: serde_json::Value =
+        test::read_response_json(&mut app, create_role(&admin_token)).await;
+    let role_uuid = role["uuid"].as_str().unwrap();
+
+    let assign = test::TestRequest::put()
+        .uri(&format!("/api/users/{}/roles/{}", uuid, role_uuid))
+        .header("Authorization", format!("Bearer {}", admin_token))
+        .to_request();
+    let response = test::call_service(&mut app, assign).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    //Permissions are resolved on each request, the same token now lists users.
+    let response = test::call_service(&mut app, get("/api/users".to_string(), &token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&mut app, get("/api/roles".to_string(), &token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,132 @@
+//Shared by the test binaries, each one uses a part of it.
+#![allow(dead_code)]
+
+use actix_service::ServiceFactory;
+use actix_web::dev::{Body, ServiceRequest, ServiceResponse};
+use actix_web::{web, App, Error};
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::error::UserDomainResult;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::token::AccessTokenIssuer;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::app_user::AppUser;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use in_memory_storage::InMemoryUserStorage;
+use std::sync::Arc;
+
+pub const PASSWORD: &str = "Correct-Horse-42";
+
+//Opaque access tokens, the signed ones need the authentication service keys.
+pub struct FakeTokenIssuer;
+
+impl AccessTokenIssuer for FakeTokenIssuer {
+    fn issue(&self, user: &AppUser) -> UserDomainResult<String> {
+        Ok(format!("{}.{}", user.login, uuid::Uuid::new_v4()))
+    }
+}
+
+//Default organization of a new storage, with an admin appointed by ensure_admin
+//and a user without any role. Both are added with add_user and have a session.
+pub struct Fixture {
+    pub domain: Arc<UserDomain>,
+    pub organization: Organization,
+    pub admin: AppUser,
+    pub admin_token: String,
+    pub user: AppUser,
+    pub token: String,
+}
+
+pub async fn with_admin(settings: UserDomainSettings, admin_login: &str, login: &str) -> Fixture {
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(FakeTokenIssuer),
+        Box::new(LogNotifier),
+        settings,
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+    let admin = add_user(&domain, &organization, admin_login).await;
+    let user = add_user(&domain, &organization, login).await;
+    domain
+        .ensure_admin(&organization, admin_login, &AuditContext::default())
+        .await
+        .unwrap();
+    let admin_token = open_session(&domain, &organization, &admin).await;
+    let token = open_session(&domain, &organization, &user).await;
+
+    Fixture {
+        domain: Arc::new(domain),
+        organization,
+        admin,
+        admin_token,
+        user,
+        token,
+    }
+}
+
+//Routes of the service over the domain, without the authentication middleware.
+pub fn app(
+    domain: Arc<UserDomain>,
+) -> App<
+    impl ServiceFactory<
+        Config = (),
+        Request = ServiceRequest,
+        Response = ServiceResponse<Body>,
+        Error = Error,
+        InitError = (),
+    >,
+    Body,
+> {
+    App::new()
+        .app_data(web::Data::new(AppState::from_domain(domain)))
+        .service(web::scope("/api").configure(get_routes_configuration))
+}
+
+//Person and user named after the login, with PASSWORD.
+pub async fn add_user(domain: &UserDomain, organization: &Organization, login: &str) -> AppUser {
+    let person = domain
+        .create_person(
+            organization,
+            CreatePersonCommand {
+                firstname: login.to_string(),
+                lastname: "User".to_string(),
+                email: format!("{}@helix.test", login),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    domain
+        .create_user(
+            organization,
+            CreateUserCommand {
+                login: login.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid: person.uuid.unwrap(),
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap()
+}
+
+//Access token of a session opened without going through the login.
+pub async fn open_session(
+    domain: &UserDomain,
+    organization: &Organization,
+    user: &AppUser,
+) -> String {
+    let context = LoginContext::new(LoginChannel::Rest, None, None);
+    domain
+        .open_session(organization, user, &context, None)
+        .await
+        .unwrap()
+        .access_token
+}
@@ -0,0 +1,182 @@
+//Emails are verified with a token sent to them, logins may require it.
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::{FileNotifier, Notification, NotificationKind};
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::person::EmailStatus;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const LOGIN: &str = "unverified.user";
+const EMAIL: &str = "unverified.user@helix.test";
+const NEW_EMAIL: &str = "moved.user@helix.test";
+const PASSWORD: &str = "Correct-Horse-42";
+
+fn read_notifications(path: &str) -> Vec<Notification> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[actix_rt::test]
+async fn verified_email_required_to_login() {
+    let path = std::env::temp_dir()
+        .join(format!(
+            "helix-verifications-{}.jsonl",
+            uuid::Uuid::new_v4()
+        ))
+        .to_string_lossy()
+        .to_string();
+    let settings = UserDomainSettings {
+        require_verified_email: true,
+        ..UserDomainSettings::default()
+    };
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(FileNotifier::new(path.clone())),
+        settings,
+    );
+
+    let organization = domain.resolve_organization(None).await.unwrap();
+    let person = domain
+        .create_person(
+            &organization,
+            CreatePersonCommand {
+                firstname: "Unverified".to_string(),
+                lastname: "User".to_string(),
+                email: EMAIL.to_string(),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(person.email_status, EmailStatus::Pending);
+    let person_uuid = person.uuid.unwrap();
+    domain
+        .create_user(
+            &organization,
+            CreateUserCommand {
+                login: LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    let login = |password: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": LOGIN, "password": password }))
+            .to_request()
+    };
+    let verify = |token: &str| {
+        test::TestRequest::post()
+            .uri("/api/email/verify")
+            .set_json(&json!({ "token": token }))
+            .to_request()
+    };
+
+    let notifications = read_notifications(&path);
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].kind, NotificationKind::EmailVerification);
+    assert_eq!(notifications[0].email, EMAIL);
+    let token = notifications[0].token.clone();
+
+    //The password is checked first, the state of the email is only told to its owner.
+    let response = test::call_service(&mut app, login("Wrong-Horse-42")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let problem: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], json!("email_not_verified"));
+
+    let response = test::call_service(&mut app, verify("not-a-token")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, verify(&token)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, verify(&token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login(PASSWORD)).await;
+    let access_token = tokens["access_token"].as_str().unwrap().to_string();
+    let with_token = |request: test::TestRequest, uri: &str| {
+        request
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", access_token))
+    };
+    let person_uri = format!("/api/persons/{}", person_uuid);
+    let person: serde_json::Value = test::read_response_json(
+        &mut app,
+        with_token(test::TestRequest::get(), &person_uri).to_request(),
+    )
+    .await;
+    assert_eq!(person["email_status"], json!("verified"));
+    assert!(person["email_verified_on"].is_string());
+
+    //A new address is pending again, until the token sent to it comes back.
+    let person: serde_json::Value = test::read_response_json(
+        &mut app,
+        with_token(test::TestRequest::put(), "/api/persons")
+            .set_json(&json!({
+                "uuid": person_uuid,
+                "firstname": "Unverified",
+                "lastname": "User",
+                "email": NEW_EMAIL,
+                "phone": null
+            }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(person["email_status"], json!("pending"));
+    assert!(person["email_verified_on"].is_null());
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    //Asking again replaces the token.
+    let response = test::call_service(
+        &mut app,
+        with_token(
+            test::TestRequest::post(),
+            &format!("{}/email/verification", person_uri),
+        )
+        .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let notifications = read_notifications(&path);
+    assert_eq!(notifications.len(), 3);
+    assert!(notifications[1..]
+        .iter()
+        .all(|notification| notification.email == NEW_EMAIL));
+    let response = test::call_service(&mut app, verify(&notifications[1].token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, verify(&notifications[2].token)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    std::fs::remove_file(&path).ok();
+}
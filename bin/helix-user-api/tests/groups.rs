@@ -0,0 +1,118 @@
+//Members of a group get the roles of the group and of its parents.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use helix_user_domain::business::settings::UserDomainSettings;
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn nested_groups_grant_roles() {
+    let common::Fixture {
+        domain,
+        admin_token,
+        user,
+        token,
+        ..
+    } = common::with_admin(UserDomainSettings::default(), "group.admin", "group.user").await;
+    let uuid = user.uuid.unwrap();
+    let mut app = test::init_service(common::app(domain)).await;
+
+    let admin_json = |method: test::TestRequest, uri: &str, body: serde_json::Value| {
+        method
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", admin_token))
+            .set_json(&body)
+            .to_request()
+    };
+    let admin_put = |uri: String| {
+        test::TestRequest::put()
+            .uri(&uri)
+            .header("Authorization", format!("Bearer {}", admin_token))
+            .to_request()
+    };
+    let get = |uri: String| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .header("Authorization", format!("Bearer {}", token))
+            .to_request()
+    };
+
+    let staff: serde_json::Value = test::read_response_json(
+        &mut app,
+        admin_json(
+            test::TestRequest::post(),
+            "/api/groups",
+            json!({ "name": "staff" }),
+        ),
+    )
+    .await;
+    let staff_uuid = staff["uuid"].as_str().unwrap();
+    let support: serde_json::Value = test::read_response_json(
+        &mut app,
+        admin_json(
+            test::TestRequest::post(),
+            "/api/groups",
+            json!({ "name": "support", "parent_uuid": staff_uuid }),
+        ),
+    )
+    .await;
+    let support_uuid = support["uuid"].as_str().unwrap();
+    assert_eq!(support["parent_uuid"].as_str(), Some(staff_uuid));
+
+    //A group cannot end up below one of its own subgroups.
+    let response = test::call_service(
+        &mut app,
+        admin_json(
+            test::TestRequest::put(),
+            "/api/groups",
+            json!({ "uuid": staff_uuid, "name": "staff", "parent_uuid": support_uuid }),
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let role: serde_json::Value = test::read_response_json(
+        &mut app,
+        admin_json(
+            test::TestRequest::post(),
+            "/api/roles",
+            json!({ "name": "readers", "permissions": ["users_read"] }),
+        ),
+    )
+    .await;
+    let role_uuid = role["uuid"].as_str().unwrap();
+    let response = test::call_service(
+        &mut app,
+        admin_put(format!("/api/groups/{}/roles/{}", staff_uuid, role_uuid)),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = test::call_service(&mut app, get("/api/users".to_string())).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = test::call_service(&mut app, get(format!("/api/groups/{}", support_uuid))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = test::call_service(
+        &mut app,
+        admin_put(format!("/api/groups/{}/members/{}", support_uuid, uuid)),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    //The role of the parent group reaches the members of its subgroups.
+    let response = test::call_service(&mut app, get("/api/users".to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&mut app, get(format!("/api/groups/{}", support_uuid))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&mut app, get("/api/groups".to_string())).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let user: serde_json::Value =
+        test::read_response_json(&mut app, get(format!("/api/users/{}", uuid))).await;
+    assert_eq!(user["groups"], json!(["staff", "support"]));
+    let groups: serde_json::Value =
+        test::read_response_json(&mut app, get(format!("/api/users/{}/groups", uuid))).await;
+    assert_eq!(groups.as_array().unwrap().len(), 2);
+}
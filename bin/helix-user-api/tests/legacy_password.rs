@@ -0,0 +1,114 @@
+//Keys stored before Argon2 still log in, and are replaced by a hash on the way.
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::app_user::AppUser;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::{ChangePasswordCommand, UpdateUserCommand};
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::person::Person;
+use helix_user_domain::storage::traits::StorageTrait;
+use in_memory_storage::InMemoryUserStorage;
+
+mod common;
+
+const LOGIN: &str = "legacy.user";
+const PASSWORD: &str = "Correct-Horse-42";
+//login:sha256(login+password+salt), as written by the previous versions.
+const LEGACY_KEY: &str =
+    "legacy.user:7cbf2cbf85cc09ed16388fa85271171973b95cc4b6ebee1c87eda0effd9d41a3";
+
+async fn log_in(domain: &UserDomain, organization: &Organization, login: &str) -> bool {
+    let context = LoginContext::new(LoginChannel::Rest, None, None);
+    domain
+        .login(organization, login, PASSWORD, &context)
+        .await
+        .is_ok()
+}
+
+#[actix_rt::test]
+async fn legacy_key_is_rehashed_on_login() {
+    let storage = InMemoryUserStorage::new();
+    let organization = storage
+        .get_organization_by_uuid(&Organization::DEFAULT_UUID)
+        .await
+        .unwrap()
+        .unwrap();
+    let person = Person::new(
+        0,
+        None,
+        "Legacy".to_string(),
+        "User".to_string(),
+        "legacy.user@helix.test".to_string(),
+        None,
+        None,
+        None,
+    );
+    let person = storage
+        .create_person(organization.id, person)
+        .await
+        .unwrap();
+    let user = AppUser::new(
+        0,
+        None,
+        LOGIN.to_string(),
+        LEGACY_KEY.to_string(),
+        None,
+        None,
+        None,
+        None,
+        person,
+    );
+    let uuid = storage
+        .create_user(organization.id, user)
+        .await
+        .unwrap()
+        .uuid
+        .unwrap();
+
+    let domain = UserDomain::new(
+        Box::new(storage),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    assert!(log_in(&domain, &organization, LOGIN).await);
+
+    //The legacy key is bound to the login, only a new hash survives a login change.
+    domain
+        .update_user(
+            &organization,
+            UpdateUserCommand {
+                uuid,
+                login: "renamed.user".to_string(),
+                photo: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    assert!(log_in(&domain, &organization, "renamed.user").await);
+
+    //The current password is checked against the stored hash.
+    let change_password = |current_password: &str| ChangePasswordCommand {
+        current_password: current_password.to_string(),
+        new_password: "Battery-Staple-43".to_string(),
+    };
+    let context = AuditContext::default();
+    assert!(domain
+        .change_password(
+            &organization,
+            &uuid,
+            change_password("Wrong-Horse-42"),
+            &context
+        )
+        .await
+        .is_err());
+    domain
+        .change_password(&organization, &uuid, change_password(PASSWORD), &context)
+        .await
+        .unwrap();
+    assert!(!log_in(&domain, &organization, "renamed.user").await);
+}
@@ -0,0 +1,119 @@
+//Concurrent requests against the in-process server, each one held in the storage
+//until all of them are in flight. Requests holding a global lock never get there.
+use actix_web::{test, web, App};
+use futures::future::join_all;
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::app_user::*;
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::person::*;
+use helix_user_domain::storage::traits::StorageTrait;
+use in_memory_storage::InMemoryUserStorage;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Barrier;
+
+mod common;
+
+const CONCURRENT_REQUESTS: usize = 20;
+//Below the 5 seconds after which the client gives up on a request.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+async fn get_seeded_storage() -> (InMemoryUserStorage, Organization, AppUser) {
+    let storage = InMemoryUserStorage::new();
+    let organization = storage
+        .get_organization_by_uuid(&Organization::DEFAULT_UUID)
+        .await
+        .unwrap()
+        .unwrap();
+    let person = storage
+        .create_person(
+            organization.id,
+            Person::new(
+                0,
+                None,
+                "Load".to_string(),
+                "Test".to_string(),
+                "load.test@helix.test".to_string(),
+                None,
+                None,
+                None,
+            ),
+        )
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(
+            organization.id,
+            AppUser::new(
+                0,
+                None,
+                "load.test".to_string(),
+                "".to_string(),
+                None,
+                None,
+                None,
+                None,
+                person,
+            ),
+        )
+        .await
+        .unwrap();
+
+    (storage, organization, user)
+}
+
+#[actix_rt::test]
+async fn concurrent_requests_run_in_parallel() {
+    let (storage, organization, user) = get_seeded_storage().await;
+    //User reads wait for each other, they only go on once every request reached one.
+    let barrier = Arc::new(Barrier::new(CONCURRENT_REQUESTS));
+    let storage = storage.with_read_hook(Arc::new(move || {
+        let barrier = barrier.clone();
+        Box::pin(async move {
+            barrier.wait().await;
+        })
+    }));
+    let domain = UserDomain::new(
+        Box::new(storage),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    let context = LoginContext::new(LoginChannel::Rest, None, None);
+    let tokens = domain
+        .open_session(&organization, &user, &context, None)
+        .await
+        .unwrap();
+    let authorization = format!("Bearer {}", tokens.access_token);
+    let state = web::Data::new(AppState::from_domain(Arc::new(domain)));
+
+    let server = test::start(move || {
+        App::new()
+            .app_data(state.clone())
+            .service(web::scope("/api").configure(get_routes_configuration))
+    });
+
+    //Users read their own account without any role.
+    let path = format!("/api/users/{}", user.uuid.unwrap());
+    let responses = actix_rt::time::timeout(
+        TIMEOUT,
+        join_all((0..CONCURRENT_REQUESTS).map(|_| {
+            server
+                .get(&path)
+                .header("Authorization", authorization.as_str())
+                .send()
+        })),
+    )
+    .await
+    .expect("requests were serialized, they never were all in flight");
+
+    for response in responses {
+        assert!(response.unwrap().status().is_success());
+    }
+}
@@ -0,0 +1,160 @@
+//Failed logins lock the account until an admin unlocks it.
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::lockout::LockoutSettings;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::login::*;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const LOGIN: &str = "locked.user";
+const ADMIN_LOGIN: &str = "lock.admin";
+const PASSWORD: &str = "Correct-Horse-42";
+
+//State, uuid of the user to lock and access token of an admin.
+async fn get_state() -> (AppState, uuid::Uuid, String) {
+    let settings = UserDomainSettings {
+        lockout: LockoutSettings {
+            max_attempts: 3,
+            ..LockoutSettings::default()
+        },
+        ..UserDomainSettings::default()
+    };
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        settings,
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+
+    let person = domain
+        .create_person(
+            &organization,
+            CreatePersonCommand {
+                firstname: "Locked".to_string(),
+                lastname: "User".to_string(),
+                email: "locked.user@helix.test".to_string(),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    let user = domain
+        .create_user(
+            &organization,
+            CreateUserCommand {
+                login: LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid: person.uuid.unwrap(),
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+
+    let admin_person = domain
+        .create_person(
+            &organization,
+            CreatePersonCommand {
+                firstname: "Lock".to_string(),
+                lastname: "Admin".to_string(),
+                email: "lock.admin@helix.test".to_string(),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    domain
+        .create_user(
+            &organization,
+            CreateUserCommand {
+                login: ADMIN_LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid: admin_person.uuid.unwrap(),
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    domain
+        .ensure_admin(&organization, ADMIN_LOGIN, &AuditContext::default())
+        .await
+        .unwrap();
+
+    let context = LoginContext::new(LoginChannel::Rest, None, None);
+    let admin = domain
+        .login(&organization, ADMIN_LOGIN, PASSWORD, &context)
+        .await
+        .unwrap();
+    let tokens = domain
+        .open_session(&organization, &admin, &context, None)
+        .await
+        .unwrap();
+
+    (
+        AppState::from_domain(Arc::new(domain)),
+        user.uuid.unwrap(),
+        tokens.access_token,
+    )
+}
+
+#[actix_rt::test]
+async fn account_is_locked_after_failed_attempts() {
+    let (state, uuid, access_token) = get_state().await;
+    let mut app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    let login = |password: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": LOGIN, "password": password }))
+            .to_request()
+    };
+
+    for _ in 0..2 {
+        let response = test::call_service(&mut app, login("wrong")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = test::call_service(&mut app, login("wrong")).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    assert!(response.headers().contains_key("Retry-After"));
+
+    //Even the right password is refused while locked.
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+
+    //Unlocking is an admin task.
+    let unlock = |access_token: &str| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/users/{}/lock", uuid))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request()
+    };
+    let response = test::call_service(&mut app, unlock("unknown")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, unlock(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
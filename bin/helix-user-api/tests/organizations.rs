@@ -0,0 +1,95 @@
+//Organizations are isolated, logins and emails are only unique within one.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use common::PASSWORD;
+use helix_user_domain::business::settings::UserDomainSettings;
+use serde_json::json;
+
+mod common;
+
+const LOGIN: &str = "tenant.admin";
+
+#[actix_rt::test]
+async fn organizations_are_isolated() {
+    let common::Fixture {
+        domain,
+        admin: user,
+        admin_token: host_token,
+        ..
+    } = common::with_admin(UserDomainSettings::default(), LOGIN, "tenant.user").await;
+    let mut app = test::init_service(common::app(domain)).await;
+
+    //The same login and email are used by the admin of the new organization.
+    let acme: serde_json::Value = test::read_response_json(
+        &mut app,
+        test::TestRequest::post()
+            .uri("/api/organizations")
+            .header("Authorization", format!("Bearer {}", host_token))
+            .set_json(&json!({
+                "name": "acme",
+                "admin": {
+                    "firstname": "Acme",
+                    "lastname": "Admin",
+                    "email": "tenant.admin@helix.test",
+                    "login": LOGIN,
+                    "password": PASSWORD
+                }
+            }))
+            .to_request(),
+    )
+    .await;
+    let acme_uuid = acme["uuid"].as_str().unwrap();
+
+    let login = |organization: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .header("X-Helix-Organization", organization)
+            .set_json(&json!({ "login": LOGIN, "password": PASSWORD }))
+            .to_request()
+    };
+    let response = test::call_service(&mut app, login("not-a-uuid")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = test::call_service(&mut app, login(&uuid::Uuid::new_v4().to_string())).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login(acme_uuid)).await;
+    let acme_token = tokens["access_token"].as_str().unwrap().to_string();
+    let get = |uri: String, access_token: &str| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request()
+    };
+
+    //The token names its organization, listings and lookups stay within it.
+    let users: serde_json::Value =
+        test::read_response_json(&mut app, get("/api/users".to_string(), &acme_token)).await;
+    assert_eq!(users["total"], json!(1));
+    assert_ne!(users["items"][0]["uuid"], json!(user.uuid.unwrap()));
+    let response = test::call_service(
+        &mut app,
+        get(format!("/api/users/{}", user.uuid.unwrap()), &acme_token),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = test::call_service(
+        &mut app,
+        test::TestRequest::get()
+            .uri("/api/users")
+            .header("Authorization", format!("Bearer {}", acme_token))
+            .header("X-Helix-Organization", uuid::Uuid::nil().to_string())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    //Only the default organization manages the others.
+    let response =
+        test::call_service(&mut app, get("/api/organizations".to_string(), &acme_token)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let organizations: serde_json::Value =
+        test::read_response_json(&mut app, get("/api/organizations".to_string(), &host_token))
+            .await;
+    assert_eq!(organizations.as_array().unwrap().len(), 2);
+}
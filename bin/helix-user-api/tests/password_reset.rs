@@ -0,0 +1,125 @@
+//Forgotten passwords are reset with single use tokens received through the notifier.
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use common::PASSWORD;
+use helix_auth_lib::middleware::AuthValidator;
+use helix_user_api::state::AppState;
+use helix_user_api::{get_exception_uri, get_routes_configuration};
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::{FileNotifier, Notification, NotificationKind};
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const LOGIN: &str = "forgetful.user";
+//The address given by common::add_user.
+const EMAIL: &str = "forgetful.user@helix.test";
+const NEW_PASSWORD: &str = "Battery-Staple-43";
+
+//Reset notifications only, creating the person sends a verification too.
+fn read_notifications(path: &str) -> Vec<Notification> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str::<Notification>(line).unwrap())
+        .filter(|notification| notification.kind == NotificationKind::PasswordReset)
+        .collect()
+}
+
+#[actix_rt::test]
+async fn reset_forgotten_password() {
+    let path = std::env::temp_dir()
+        .join(format!(
+            "helix-notifications-{}.jsonl",
+            uuid::Uuid::new_v4()
+        ))
+        .to_string_lossy()
+        .to_string();
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(FileNotifier::new(path.clone())),
+        UserDomainSettings::default(),
+    );
+
+    let organization = domain.resolve_organization(None).await.unwrap();
+    let user = common::add_user(&domain, &organization, LOGIN).await;
+    let access_token = common::open_session(&domain, &organization, &user).await;
+
+    let mut app = test::init_service(
+        App::new()
+            .wrap(AuthValidator::new(get_exception_uri()))
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    let request_reset = |login: &str| {
+        test::TestRequest::post()
+            .uri("/api/password/reset")
+            .set_json(&json!({ "login": login }))
+            .to_request()
+    };
+    let reset = |token: &str, new_password: &str| {
+        test::TestRequest::put()
+            .uri("/api/password/reset")
+            .set_json(&json!({ "token": token, "new_password": new_password }))
+            .to_request()
+    };
+    let login = |password: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": LOGIN, "password": password }))
+            .to_request()
+    };
+
+    //Unknown accounts get the same answer, and nothing is sent.
+    let response = test::call_service(&mut app, request_reset("nobody")).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(read_notifications(&path).is_empty());
+
+    //By email or login, a new token replaces the previous one.
+    let response = test::call_service(&mut app, request_reset(&EMAIL.to_uppercase())).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let response = test::call_service(&mut app, request_reset(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let notifications = read_notifications(&path);
+    assert_eq!(notifications.len(), 2);
+    assert!(notifications.iter().all(|notification| {
+        notification.login.as_deref() == Some(LOGIN) && notification.email == EMAIL
+    }));
+    let replaced_token = notifications[0].token.clone();
+    let token = notifications[1].token.clone();
+
+    let response = test::call_service(&mut app, reset(&replaced_token, NEW_PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    //A rejected password does not use the token.
+    let response = test::call_service(&mut app, reset(&token, "short")).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = test::call_service(&mut app, reset(&token, NEW_PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, reset(&token, PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    //Sessions opened with the old password are over.
+    let response = test::call_service(
+        &mut app,
+        test::TestRequest::get()
+            .uri(&format!("/api/users/{}", user.uuid.unwrap()))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = test::call_service(&mut app, login(PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, login(NEW_PASSWORD)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    std::fs::remove_file(&path).ok();
+}
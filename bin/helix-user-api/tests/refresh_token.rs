@@ -0,0 +1,249 @@
+//Refresh tokens are single use, replaying a rotated one ends the whole session.
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::error::UserDomainError;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::app_user::AppUser;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use in_memory_storage::InMemoryUserStorage;
+
+mod common;
+
+const LOGIN: &str = "token.user";
+const PASSWORD: &str = "Correct-Horse-42";
+
+async fn get_logged_user() -> (UserDomain, Organization, AppUser) {
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+
+    let person = domain
+        .create_person(
+            &organization,
+            CreatePersonCommand {
+                firstname: "Token".to_string(),
+                lastname: "User".to_string(),
+                email: "token.user@helix.test".to_string(),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    domain
+        .create_user(
+            &organization,
+            CreateUserCommand {
+                login: LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid: person.uuid.unwrap(),
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+
+    let user = domain
+        .login(&organization, LOGIN, PASSWORD, &get_context())
+        .await
+        .unwrap();
+    (domain, organization, user)
+}
+
+fn get_context() -> LoginContext {
+    LoginContext::new(LoginChannel::Rest, Some("127.0.0.1".to_string()), None)
+}
+
+fn is_invalid_token<T>(result: Result<T, UserDomainError>) -> bool {
+    matches!(result, Err(UserDomainError::InvalidToken))
+}
+
+#[actix_rt::test]
+async fn refresh_rotates_and_detects_reuse() {
+    let (domain, organization, user) = get_logged_user().await;
+    let first = domain
+        .open_session(&organization, &user, &get_context(), None)
+        .await
+        .unwrap()
+        .refresh_token;
+
+    let second = domain
+        .refresh(&organization, &first)
+        .await
+        .unwrap()
+        .refresh_token;
+    assert_ne!(first, second);
+
+    //Replaying the first token ends the session, the second one included.
+    assert!(is_invalid_token(
+        domain.refresh(&organization, &first).await
+    ));
+    assert!(is_invalid_token(
+        domain.refresh(&organization, &second).await
+    ));
+    let sessions = domain
+        .get_user_sessions(&organization, &user.uuid.unwrap())
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+}
+
+#[actix_rt::test]
+async fn session_is_deleted_by_its_user_only() {
+    let (domain, organization, user) = get_logged_user().await;
+    let token = domain
+        .open_session(&organization, &user, &get_context(), None)
+        .await
+        .unwrap()
+        .refresh_token;
+    let sessions = domain
+        .get_user_sessions(&organization, &user.uuid.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(sessions[0].ip, Some("127.0.0.1".to_string()));
+    let session_uuid = sessions[0].uuid.unwrap();
+
+    let person = domain
+        .create_person(
+            &organization,
+            CreatePersonCommand {
+                firstname: "Other".to_string(),
+                lastname: "User".to_string(),
+                email: "other.user@helix.test".to_string(),
+                phone: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    let other_user = domain
+        .create_user(
+            &organization,
+            CreateUserCommand {
+                login: "other.user".to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+                person_uuid: person.uuid.unwrap(),
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    let result = domain
+        .delete_user_session(
+            &organization,
+            &other_user.uuid.unwrap(),
+            &session_uuid,
+            &AuditContext::default(),
+        )
+        .await;
+    assert!(matches!(result, Err(UserDomainError::NotFound { .. })));
+
+    domain
+        .delete_user_session(
+            &organization,
+            &user.uuid.unwrap(),
+            &session_uuid,
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    assert!(is_invalid_token(
+        domain.refresh(&organization, &token).await
+    ));
+}
+
+#[actix_rt::test]
+async fn logout_and_revocation() {
+    let (domain, organization, user) = get_logged_user().await;
+    let token = domain
+        .open_session(
+            &organization,
+            &user,
+            &get_context(),
+            Some("laptop".to_string()),
+        )
+        .await
+        .unwrap()
+        .refresh_token;
+    let other_device = domain
+        .open_session(
+            &organization,
+            &user,
+            &get_context(),
+            Some("phone".to_string()),
+        )
+        .await
+        .unwrap()
+        .refresh_token;
+
+    domain.logout(&organization, &token).await.unwrap();
+    assert!(is_invalid_token(
+        domain.refresh(&organization, &token).await
+    ));
+
+    //Other sessions are left alone by a logout.
+    let other_device = domain
+        .refresh(&organization, &other_device)
+        .await
+        .unwrap()
+        .refresh_token;
+    let sessions = domain
+        .get_user_sessions(&organization, &user.uuid.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].device, Some("phone".to_string()));
+    assert!(sessions[0].refreshed_on.is_some());
+
+    domain
+        .revoke_user_sessions(&organization, &user.uuid.unwrap(), &AuditContext::default())
+        .await
+        .unwrap();
+    assert!(is_invalid_token(
+        domain.refresh(&organization, &other_device).await
+    ));
+    assert!(is_invalid_token(
+        domain.refresh(&organization, "unknown").await
+    ));
+}
+
+#[actix_rt::test]
+async fn access_token_follows_its_session() {
+    let (domain, organization, user) = get_logged_user().await;
+    let tokens = domain
+        .open_session(&organization, &user, &get_context(), None)
+        .await
+        .unwrap();
+
+    let actor = domain.authenticate(&tokens.access_token).await.unwrap();
+    assert_eq!(actor.user_uuid, user.uuid.unwrap());
+    assert!(actor.permissions.is_empty());
+
+    //A refresh replaces the access token of the session.
+    let refreshed = domain
+        .refresh(&organization, &tokens.refresh_token)
+        .await
+        .unwrap();
+    assert!(is_invalid_token(
+        domain.authenticate(&tokens.access_token).await
+    ));
+    domain.authenticate(&refreshed.access_token).await.unwrap();
+
+    domain
+        .logout(&organization, &refreshed.refresh_token)
+        .await
+        .unwrap();
+    assert!(is_invalid_token(
+        domain.authenticate(&refreshed.access_token).await
+    ));
+}
@@ -0,0 +1,114 @@
+//A person and its user are registered together or not at all.
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use helix_user_api::get_routes_configuration;
+use helix_user_api::state::AppState;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::notifier::LogNotifier;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use in_memory_storage::InMemoryUserStorage;
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const ADMIN_LOGIN: &str = "register.admin";
+const PASSWORD: &str = "Correct-Horse-42";
+
+#[actix_rt::test]
+async fn register_account() {
+    let domain = UserDomain::new(
+        Box::new(InMemoryUserStorage::new()),
+        Box::new(common::FakeTokenIssuer),
+        Box::new(LogNotifier),
+        UserDomainSettings::default(),
+    );
+    let organization = domain.resolve_organization(None).await.unwrap();
+    domain
+        .register_account(
+            &organization,
+            RegisterAccountCommand {
+                firstname: "Register".to_string(),
+                lastname: "Admin".to_string(),
+                email: "register.admin@helix.test".to_string(),
+                phone: None,
+                login: ADMIN_LOGIN.to_string(),
+                password: PASSWORD.to_string(),
+                photo: None,
+            },
+            &AuditContext::default(),
+        )
+        .await
+        .unwrap();
+    domain
+        .ensure_admin(&organization, ADMIN_LOGIN, &AuditContext::default())
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::from_domain(Arc::new(domain))))
+            .service(web::scope("/api").configure(get_routes_configuration)),
+    )
+    .await;
+
+    let login = |login: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": login, "password": PASSWORD }))
+            .to_request()
+    };
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login(ADMIN_LOGIN)).await;
+    let authorization = format!("Bearer {}", tokens["access_token"].as_str().unwrap());
+    let register = |login: &str, email: &str| {
+        test::TestRequest::post()
+            .uri("/api/accounts")
+            .header("Authorization", authorization.clone())
+            .set_json(&json!({
+                "firstname": "Registered",
+                "lastname": "User",
+                "email": email,
+                "phone": null,
+                "login": login,
+                "password": PASSWORD,
+                "photo": null
+            }))
+            .to_request()
+    };
+    let find_persons = |email: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/persons?email={}", email))
+            .header("Authorization", authorization.clone())
+            .to_request()
+    };
+
+    let response = test::call_service(
+        &mut app,
+        register("registered.user", "registered.user@helix.test"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let user: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(user["login"], json!("registered.user"));
+    assert_eq!(user["person"]["email"], json!("registered.user@helix.test"));
+    assert!(user["person"]["uuid"].is_string());
+    let response = test::call_service(&mut app, login("registered.user")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    //A taken login is refused before anything is written.
+    let response = test::call_service(
+        &mut app,
+        register("registered.user", "other.user@helix.test"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let persons: serde_json::Value =
+        test::read_response_json(&mut app, find_persons("other.user@helix.test")).await;
+    assert_eq!(persons["total"], json!(0));
+
+    let response = test::call_service(&mut app, register("bad login!", "bad@helix.test")).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
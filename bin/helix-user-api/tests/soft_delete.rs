@@ -0,0 +1,141 @@
+//Deleted users and persons are hidden until restored or purged.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use common::PASSWORD;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::traits::UserDomainTrait;
+use serde_json::json;
+
+mod common;
+
+const ADMIN_LOGIN: &str = "delete.admin";
+const LOGIN: &str = "delete.user";
+
+#[actix_rt::test]
+async fn delete_restore_and_purge() {
+    let common::Fixture {
+        domain,
+        organization,
+        admin,
+        admin_token,
+        user: app_user,
+        token: access_token,
+    } = common::with_admin(UserDomainSettings::default(), ADMIN_LOGIN, LOGIN).await;
+    let admin_uuid = admin.uuid.unwrap();
+    let (person, uuid) = (app_user.person.clone(), app_user.uuid.unwrap());
+    let mut app = test::init_service(common::app(domain.clone())).await;
+
+    let login = |login: &str| {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": login, "password": PASSWORD }))
+            .to_request()
+    };
+    let authorization = format!("Bearer {}", admin_token);
+
+    let user_uri = format!("/api/users/{}", uuid);
+    let person_uri = format!("/api/persons/{}", person.uuid.unwrap());
+    let request = |method: &str, uri: &str| {
+        let request = match method {
+            "delete" => test::TestRequest::delete(),
+            "put" => test::TestRequest::put(),
+            _ => test::TestRequest::get(),
+        };
+        request
+            .uri(uri)
+            .header("Authorization", authorization.clone())
+            .to_request()
+    };
+
+    let get_user = |access_token: &str| {
+        test::TestRequest::get()
+            .uri(&user_uri)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .to_request()
+    };
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    //Deleted users are left out of reads and cannot log in.
+    let response = test::call_service(&mut app, request("delete", &user_uri)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let access_token = common::open_session(&domain, &organization, &app_user).await;
+    let response = test::call_service(&mut app, get_user(&access_token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = test::call_service(&mut app, request("get", &user_uri)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = test::call_service(&mut app, login(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let users: serde_json::Value = test::read_response_json(
+        &mut app,
+        request("get", &format!("/api/users?login={}", LOGIN)),
+    )
+    .await;
+    assert_eq!(users["total"], json!(0));
+    let users: serde_json::Value = test::read_response_json(
+        &mut app,
+        request(
+            "get",
+            &format!("/api/users?login={}&include_deleted=true", LOGIN),
+        ),
+    )
+    .await;
+    assert_eq!(users["total"], json!(1));
+
+    let user: serde_json::Value = test::read_response_json(
+        &mut app,
+        request("get", &format!("{}?include_deleted=true", user_uri)),
+    )
+    .await;
+    assert!(user["deleted_on"].is_string());
+    assert_eq!(user["deleted_by"], json!(admin_uuid));
+
+    //A restored user logs in again.
+    let user: serde_json::Value =
+        test::read_response_json(&mut app, request("put", &format!("{}/restore", user_uri))).await;
+    assert!(user.get("deleted_on").is_none());
+    let response = test::call_service(&mut app, login(LOGIN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    //Deleting a person deletes its users, restoring it brings them back.
+    let response = test::call_service(&mut app, request("delete", &person_uri)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = test::call_service(&mut app, request("get", &user_uri)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let person: serde_json::Value =
+        test::read_response_json(&mut app, request("put", &format!("{}/restore", person_uri)))
+            .await;
+    assert!(person.get("deleted_on").is_none());
+    let response = test::call_service(&mut app, request("get", &user_uri)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    //Only records deleted before the retention date are purged.
+    let response = test::call_service(&mut app, request("delete", &person_uri)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let purged = domain
+        .purge_deleted(
+            &organization,
+            chrono::Utc::now() - chrono::Duration::days(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(purged, 0);
+    let purged = domain
+        .purge_deleted(&organization, chrono::Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(purged, 2);
+
+    let response = test::call_service(
+        &mut app,
+        request("get", &format!("{}?include_deleted=true", user_uri)),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response =
+        test::call_service(&mut app, request("put", &format!("{}/restore", person_uri))).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,143 @@
+//Users with a second factor log in in two steps, codes are only accepted once.
+use actix_web::http::StatusCode;
+use actix_web::test;
+use chrono::prelude::*;
+use chrono::Duration;
+use common::PASSWORD;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::two_factor::{TwoFactorManager, TwoFactorSettings};
+use serde_json::json;
+
+mod common;
+
+const LOGIN: &str = "second.factor";
+
+#[actix_rt::test]
+async fn two_step_login() {
+    let common::Fixture {
+        domain,
+        admin_token,
+        user,
+        token,
+        ..
+    } = common::with_admin(UserDomainSettings::default(), "factor.admin", LOGIN).await;
+    let uuid = user.uuid.unwrap();
+    let authenticator = TwoFactorManager::new(TwoFactorSettings::default());
+    let mut app = test::init_service(common::app(domain)).await;
+
+    let uri = format!("/api/users/{}/two-factor", uuid);
+    let with_token = |request: test::TestRequest, access_token: &str| {
+        request
+            .uri(&uri)
+            .header("Authorization", format!("Bearer {}", access_token))
+    };
+    let login = || {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(&json!({ "login": LOGIN, "password": PASSWORD }))
+            .to_request()
+    };
+    let verify = |challenge_token: &serde_json::Value, code: &str| {
+        test::TestRequest::post()
+            .uri("/api/login/verify")
+            .set_json(&json!({ "challenge_token": challenge_token, "code": code }))
+            .to_request()
+    };
+
+    //Only the user itself sees its secret.
+    let response = test::call_service(
+        &mut app,
+        with_token(test::TestRequest::post(), &admin_token).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = test::call_service(
+        &mut app,
+        with_token(test::TestRequest::post(), &token).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let setup: serde_json::Value = test::read_body_json(response).await;
+    let secret = setup["secret"].as_str().unwrap().to_string();
+    assert!(setup["otpauth_uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/"));
+    assert!(setup["qr_code"].as_str().unwrap().contains("<svg"));
+
+    //Enrollment is pending until a valid code confirms it.
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login()).await;
+    assert!(tokens["access_token"].is_string());
+    let response = test::call_service(
+        &mut app,
+        with_token(test::TestRequest::put(), &token)
+            .set_json(&json!({ "code": "not-a-code" }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let now = Utc::now();
+    let code = authenticator.get_code(&secret, now).unwrap();
+    let recovery_codes: serde_json::Value = test::read_response_json(
+        &mut app,
+        with_token(test::TestRequest::put(), &token)
+            .set_json(&json!({ "code": code }))
+            .to_request(),
+    )
+    .await;
+    let recovery_codes = recovery_codes["recovery_codes"].as_array().unwrap().clone();
+    assert_eq!(recovery_codes.len(), 10);
+
+    //The password alone now gives a challenge, a code already used is refused.
+    let challenge: serde_json::Value = test::read_response_json(&mut app, login()).await;
+    assert!(challenge["access_token"].is_null());
+    let challenge_token = challenge["challenge_token"].clone();
+    let response = test::call_service(&mut app, verify(&challenge_token, &code)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let next_code = authenticator
+        .get_code(&secret, now + Duration::seconds(30))
+        .unwrap();
+    let tokens: serde_json::Value =
+        test::read_response_json(&mut app, verify(&challenge_token, &next_code)).await;
+    assert!(tokens["access_token"].is_string());
+    let response = test::call_service(&mut app, verify(&challenge_token, &next_code)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    //Recovery codes replace a code once, whatever their case and separators.
+    let recovery_code = recovery_codes[0].as_str().unwrap().to_uppercase();
+    let challenge: serde_json::Value = test::read_response_json(&mut app, login()).await;
+    let challenge_token = challenge["challenge_token"].clone();
+    let tokens: serde_json::Value =
+        test::read_response_json(&mut app, verify(&challenge_token, &recovery_code)).await;
+    assert!(tokens["access_token"].is_string());
+    let challenge: serde_json::Value = test::read_response_json(&mut app, login()).await;
+    let challenge_token = challenge["challenge_token"].clone();
+    let response = test::call_service(&mut app, verify(&challenge_token, &recovery_code)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let status: serde_json::Value = test::read_response_json(
+        &mut app,
+        with_token(test::TestRequest::get(), &token).to_request(),
+    )
+    .await;
+    assert_eq!(status["enabled"], json!(true));
+    assert_eq!(status["recovery_codes_left"], json!(9));
+
+    //Admins reset a lost second factor, the password is enough again.
+    let response = test::call_service(
+        &mut app,
+        with_token(test::TestRequest::delete(), &token).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = test::call_service(
+        &mut app,
+        with_token(test::TestRequest::delete(), &admin_token).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let tokens: serde_json::Value = test::read_response_json(&mut app, login()).await;
+    assert!(tokens["access_token"].is_string());
+}
@@ -1,3 +1,8 @@
+//Helpers return the Status of tonic as is, handlers answer with it anyway.
+#![allow(clippy::result_large_err)]
+
 tonic::include_proto!("helix_user_v1");
 
 pub mod business_controller;
+pub mod convert;
+pub mod status;
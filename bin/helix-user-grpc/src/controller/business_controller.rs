@@ -1,20 +1,1288 @@
+use crate::controller;
+use crate::controller::convert::*;
+use crate::controller::status::to_status;
 use crate::controller::user_service_server::UserService;
-use crate::controller::{AuthRequest, AuthResponse};
-use tonic::Response;
+use crate::controller::*;
+use helix_user_runtime::state::AppState;
+use helix_user_domain::business::authorization::Actor;
+use helix_user_domain::business::error::UserDomainError;
+use helix_user_domain::core::app_user::AppUser as DomainAppUser;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::login::{LoginChannel, LoginContext};
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::person::Person as DomainPerson;
+use helix_user_domain::core::query::{AuditQuery, Pagination, PersonQuery, UserQuery};
+use helix_user_domain::core::role::Permission;
+use helix_user_domain::core::view::{
+    AuditEntryView, GroupView, LoginEventView, OrganizationView, RoleView, SessionView,
+    StatusChangeView, UserView,
+};
+use std::convert::TryFrom;
+use tonic::{Request, Response, Status};
 
-#[derive(Default)]
-pub struct ImplUserService {}
+//Names the organization of anonymous requests, the default one when missing.
+pub const ORGANIZATION_METADATA: &str = "x-helix-organization";
+//Recorded with the audit entries of the request, generated when missing.
+pub const REQUEST_ID_METADATA: &str = "x-request-id";
+
+pub struct ImplUserService {
+    state: AppState,
+}
+
+impl ImplUserService {
+    pub fn new(state: AppState) -> Self {
+        ImplUserService { state }
+    }
+
+    async fn find_person(
+        &self,
+        organization: &Organization,
+        uuid: &str,
+        include_deleted: bool,
+    ) -> Result<DomainPerson, Status> {
+        let uuid = parse_uuid(uuid)?;
+        match self
+            .state
+            .get_domain()
+            .get_person(organization, &uuid, include_deleted)
+            .await
+        {
+            Ok(Some(person)) => Ok(person),
+            Ok(None) => Err(to_status(UserDomainError::not_found("Person"))),
+            Err(error) => Err(to_status(error)),
+        }
+    }
+
+    async fn find_user(
+        &self,
+        organization: &Organization,
+        uuid: &str,
+        include_deleted: bool,
+    ) -> Result<DomainAppUser, Status> {
+        let uuid = parse_uuid(uuid)?;
+        match self
+            .state
+            .get_domain()
+            .get_user(organization, &uuid, include_deleted)
+            .await
+        {
+            Ok(Some(user)) => Ok(user),
+            Ok(None) => Err(to_status(UserDomainError::not_found("User"))),
+            Err(error) => Err(to_status(error)),
+        }
+    }
+
+    //Caller of the request, from the access token of the authorization metadata.
+    //It acts in the organization of its token, metadata naming another one is refused.
+    async fn get_actor<T>(&self, request: &Request<T>) -> Result<Actor, Status> {
+        let access_token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|value| value.trim().to_string())
+            .ok_or_else(|| to_status(UserDomainError::InvalidToken))?;
+
+        let actor = self
+            .state
+            .get_domain()
+            .authenticate(&access_token)
+            .await
+            .map_err(to_status)?;
+        match get_organization_uuid(request)? {
+            Some(uuid) if Some(uuid) != actor.organization.uuid => {
+                Err(to_status(UserDomainError::Forbidden))
+            }
+            _ => Ok(actor),
+        }
+    }
+
+    //Organization named by the metadata of an anonymous request, the default one otherwise.
+    async fn get_tenant<T>(&self, request: &Request<T>) -> Result<Organization, Status> {
+        self.state
+            .get_domain()
+            .resolve_organization(get_organization_uuid(request)?)
+            .await
+            .map_err(to_status)
+    }
+}
+
+fn get_organization_uuid<T>(request: &Request<T>) -> Result<Option<uuid::Uuid>, Status> {
+    match request.metadata().get(ORGANIZATION_METADATA) {
+        Some(value) => match value.to_str().ok().map(uuid::Uuid::parse_str) {
+            Some(Ok(uuid)) => Ok(Some(uuid)),
+            _ => Err(Status::invalid_argument("Invalid organization.")),
+        },
+        None => Ok(None),
+    }
+}
+
+fn get_login_context<T>(request: &Request<T>) -> LoginContext {
+    let ip = request.remote_addr().map(|addr| addr.ip().to_string());
+    let user_agent = request
+        .metadata()
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    LoginContext::new(LoginChannel::Grpc, ip, user_agent)
+}
+
+//Author of a mutation with the id of its request, from the x-request-id metadata.
+fn get_audit_context<T>(request: &Request<T>, actor: Option<&Actor>) -> AuditContext {
+    let request_id = request
+        .metadata()
+        .get(REQUEST_ID_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    AuditContext::new(actor.map(|actor| actor.user_uuid), Some(request_id))
+}
 
 #[tonic::async_trait]
 impl UserService for ImplUserService {
     async fn authenticate(
         &self,
-        request: tonic::Request<AuthRequest>,
-    ) -> Result<tonic::Response<AuthResponse>, tonic::Status> {
-        println!("Authenticate request : {:?}", request);
+        request: Request<AuthRequest>,
+    ) -> Result<Response<AuthResponse>, Status> {
+        let context = get_login_context(&request);
+        let organization = self.get_tenant(&request).await?;
+        let auth_request = request.into_inner();
+        let domain = self.state.get_domain();
+
+        let app_user = domain
+            .login(
+                &organization,
+                &auth_request.login,
+                &auth_request.password,
+                &context,
+            )
+            .await
+            .map_err(to_status)?;
+        let device = match auth_request.device.is_empty() {
+            true => None,
+            false => Some(auth_request.device),
+        };
+        let step = domain
+            .start_session(&organization, &app_user, &context, device)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(step.into()))
+    }
+
+    async fn verify_login(
+        &self,
+        request: Request<VerifyLoginRequest>,
+    ) -> Result<Response<AuthResponse>, Status> {
+        let context = get_login_context(&request);
+        let organization = self.get_tenant(&request).await?;
+        let request = request.into_inner();
+        let command = VerifyLoginCommand {
+            challenge_token: request.challenge_token,
+            code: request.code,
+        };
+        let tokens = self
+            .state
+            .get_domain()
+            .verify_login(&organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(tokens.into()))
+    }
+
+    async fn request_password_reset(
+        &self,
+        request: Request<RequestPasswordResetRequest>,
+    ) -> Result<Response<RequestPasswordResetResponse>, Status> {
+        let organization = self.get_tenant(&request).await?;
+        let command = RequestPasswordResetCommand {
+            login: request.into_inner().login,
+        };
+        self.state
+            .get_domain()
+            .request_password_reset(&organization, command)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(RequestPasswordResetResponse {}))
+    }
+
+    async fn reset_password(
+        &self,
+        request: Request<ResetPasswordRequest>,
+    ) -> Result<Response<ResetPasswordResponse>, Status> {
+        let organization = self.get_tenant(&request).await?;
+        let context = get_audit_context(&request, None);
+        let request = request.into_inner();
+        let command = ResetPasswordCommand {
+            token: request.token,
+            new_password: request.new_password,
+        };
+        self.state
+            .get_domain()
+            .reset_password(&organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ResetPasswordResponse {}))
+    }
+
+    async fn refresh(
+        &self,
+        request: Request<RefreshRequest>,
+    ) -> Result<Response<AuthResponse>, Status> {
+        let organization = self.get_tenant(&request).await?;
+        let tokens = self
+            .state
+            .get_domain()
+            .refresh(&organization, &request.get_ref().refresh_token)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(tokens.into()))
+    }
+
+    async fn logout(
+        &self,
+        request: Request<LogoutRequest>,
+    ) -> Result<Response<LogoutResponse>, Status> {
+        let organization = self.get_tenant(&request).await?;
+        self.state
+            .get_domain()
+            .logout(&organization, &request.get_ref().refresh_token)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(LogoutResponse {}))
+    }
+
+    async fn get_person(
+        &self,
+        request: Request<GetPersonRequest>,
+    ) -> Result<Response<controller::Person>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_person_or(&uuid, Permission::PersonsRead)
+            .map_err(to_status)?;
+        let request = request.into_inner();
+        let person = self
+            .find_person(&actor.organization, &request.uuid, request.include_deleted)
+            .await?;
+        Ok(Response::new(person.into()))
+    }
+
+    async fn list_persons(
+        &self,
+        request: Request<ListPersonsRequest>,
+    ) -> Result<Response<ListPersonsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor.require(Permission::PersonsRead).map_err(to_status)?;
+        let query = PersonQuery::try_from(request.into_inner())?;
+        let page = self
+            .state
+            .get_domain()
+            .get_all_persons(&actor.organization, query)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListPersonsResponse {
+            persons: page.items.into_iter().map(|person| person.into()).collect(),
+            total: page.total,
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        }))
+    }
+
+    async fn create_person(
+        &self,
+        request: Request<CreatePersonRequest>,
+    ) -> Result<Response<controller::Person>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::PersonsWrite).map_err(to_status)?;
+        let command = CreatePersonCommand::from(request.into_inner());
+        let created_person = self
+            .state
+            .get_domain()
+            .create_person(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(created_person.into()))
+    }
+
+    async fn update_person(
+        &self,
+        request: Request<UpdatePersonRequest>,
+    ) -> Result<Response<controller::Person>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let command = UpdatePersonCommand::try_from(request.into_inner())?;
+        actor
+            .require_person_or(&command.uuid, Permission::PersonsWrite)
+            .map_err(to_status)?;
+        let updated_person = self
+            .state
+            .get_domain()
+            .update_person(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(updated_person.into()))
+    }
+
+    async fn delete_person(
+        &self,
+        request: Request<DeletePersonRequest>,
+    ) -> Result<Response<DeletePersonResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor
+            .require(Permission::PersonsDelete)
+            .map_err(to_status)?;
+        let person = self
+            .find_person(&actor.organization, &request.get_ref().uuid, false)
+            .await?;
+        self.state
+            .get_domain()
+            .delete_person(&actor.organization, person, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(DeletePersonResponse {}))
+    }
+
+    async fn restore_person(
+        &self,
+        request: Request<RestorePersonRequest>,
+    ) -> Result<Response<controller::Person>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor
+            .require(Permission::PersonsDelete)
+            .map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let person = self
+            .state
+            .get_domain()
+            .restore_person(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(person.into()))
+    }
+
+    async fn send_email_verification(
+        &self,
+        request: Request<SendEmailVerificationRequest>,
+    ) -> Result<Response<SendEmailVerificationResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_person_or(&uuid, Permission::PersonsWrite)
+            .map_err(to_status)?;
+        self.state
+            .get_domain()
+            .send_email_verification(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(SendEmailVerificationResponse {}))
+    }
+
+    async fn verify_email(
+        &self,
+        request: Request<VerifyEmailRequest>,
+    ) -> Result<Response<VerifyEmailResponse>, Status> {
+        let organization = self.get_tenant(&request).await?;
+        let context = get_audit_context(&request, None);
+        let command = VerifyEmailCommand {
+            token: request.into_inner().token,
+        };
+        self.state
+            .get_domain()
+            .verify_email(&organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(VerifyEmailResponse {}))
+    }
+
+    async fn get_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::UsersRead)
+            .map_err(to_status)?;
+        let request = request.into_inner();
+        let user = self
+            .find_user(&actor.organization, &request.uuid, request.include_deleted)
+            .await?;
+        //Groups are not resolved for deleted users.
+        if user.is_deleted() {
+            return Ok(Response::new(user.into()));
+        }
+        let groups = self
+            .state
+            .get_domain()
+            .get_user_groups(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+        let user = UserView::from(user).with_groups(groups);
+        Ok(Response::new(user.into()))
+    }
+
+    async fn list_users(
+        &self,
+        request: Request<ListUsersRequest>,
+    ) -> Result<Response<ListUsersResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor.require(Permission::UsersRead).map_err(to_status)?;
+        let query = UserQuery::try_from(request.into_inner())?;
+        let page = self
+            .state
+            .get_domain()
+            .get_all_users(&actor.organization, query)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListUsersResponse {
+            users: page.items.into_iter().map(|user| user.into()).collect(),
+            total: page.total,
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        }))
+    }
+
+    async fn create_user(
+        &self,
+        request: Request<CreateUserRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::UsersWrite).map_err(to_status)?;
+        let command = CreateUserCommand::try_from(request.into_inner())?;
+        let created_user = self
+            .state
+            .get_domain()
+            .create_user(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(created_user.into()))
+    }
+
+    async fn register_account(
+        &self,
+        request: Request<RegisterAccountRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::PersonsWrite).map_err(to_status)?;
+        actor.require(Permission::UsersWrite).map_err(to_status)?;
+        let command = RegisterAccountCommand::from(request.into_inner());
+        let created_user = self
+            .state
+            .get_domain()
+            .register_account(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(created_user.into()))
+    }
+
+    async fn update_user(
+        &self,
+        request: Request<UpdateUserRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let command = UpdateUserCommand::try_from(request.into_inner())?;
+        actor
+            .require_self_or(&command.uuid, Permission::UsersWrite)
+            .map_err(to_status)?;
+        let updated_user = self
+            .state
+            .get_domain()
+            .update_user(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(updated_user.into()))
+    }
+
+    async fn change_password(
+        &self,
+        request: Request<ChangePasswordRequest>,
+    ) -> Result<Response<ChangePasswordResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let request = request.into_inner();
+        let uuid = parse_uuid(&request.uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::UsersWrite)
+            .map_err(to_status)?;
+        let command = ChangePasswordCommand {
+            current_password: request.current_password,
+            new_password: request.new_password,
+        };
+
+        self.state
+            .get_domain()
+            .change_password(&actor.organization, &uuid, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ChangePasswordResponse {}))
+    }
+
+    async fn unlock_user(
+        &self,
+        request: Request<UnlockUserRequest>,
+    ) -> Result<Response<UnlockUserResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor.require(Permission::UsersWrite).map_err(to_status)?;
+        self.state
+            .get_domain()
+            .unlock_user(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(UnlockUserResponse {}))
+    }
+
+    async fn change_user_status(
+        &self,
+        request: Request<ChangeUserStatusRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor.require(Permission::UsersWrite).map_err(to_status)?;
+        let command = ChangeStatusCommand::try_from(request.into_inner())?;
+        let user = self
+            .state
+            .get_domain()
+            .change_user_status(&actor.organization, &uuid, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(user.into()))
+    }
+
+    async fn list_user_status_changes(
+        &self,
+        request: Request<ListUserStatusChangesRequest>,
+    ) -> Result<Response<ListUserStatusChangesResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::UsersRead)
+            .map_err(to_status)?;
+        let changes = self
+            .state
+            .get_domain()
+            .get_status_history(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListUserStatusChangesResponse {
+            changes: changes
+                .into_iter()
+                .map(|change| StatusChangeView::from(change).into())
+                .collect(),
+        }))
+    }
+
+    async fn get_two_factor(
+        &self,
+        request: Request<GetTwoFactorRequest>,
+    ) -> Result<Response<controller::TwoFactorStatus>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::UsersRead)
+            .map_err(to_status)?;
+        let status = self
+            .state
+            .get_domain()
+            .get_two_factor(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(status.into()))
+    }
+
+    async fn enroll_two_factor(
+        &self,
+        request: Request<EnrollTwoFactorRequest>,
+    ) -> Result<Response<controller::TwoFactorSetup>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor.require_self(&uuid).map_err(to_status)?;
+        let setup = self
+            .state
+            .get_domain()
+            .enroll_two_factor(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(setup.into()))
+    }
+
+    async fn confirm_two_factor(
+        &self,
+        request: Request<ConfirmTwoFactorRequest>,
+    ) -> Result<Response<controller::RecoveryCodes>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor.require_self(&uuid).map_err(to_status)?;
+        let command = ConfirmTwoFactorCommand {
+            code: request.into_inner().code,
+        };
+        let recovery_codes = self
+            .state
+            .get_domain()
+            .confirm_two_factor(&actor.organization, &uuid, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(recovery_codes.into()))
+    }
+
+    async fn reset_two_factor(
+        &self,
+        request: Request<ResetTwoFactorRequest>,
+    ) -> Result<Response<ResetTwoFactorResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor.require(Permission::UsersWrite).map_err(to_status)?;
+        self.state
+            .get_domain()
+            .reset_two_factor(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ResetTwoFactorResponse {}))
+    }
+
+    async fn list_user_sessions(
+        &self,
+        request: Request<ListUserSessionsRequest>,
+    ) -> Result<Response<ListUserSessionsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::SessionsManage)
+            .map_err(to_status)?;
+        let sessions = self
+            .state
+            .get_domain()
+            .get_user_sessions(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListUserSessionsResponse {
+            sessions: sessions
+                .into_iter()
+                .map(|session| SessionView::from(session).into())
+                .collect(),
+        }))
+    }
+
+    async fn delete_user_session(
+        &self,
+        request: Request<DeleteUserSessionRequest>,
+    ) -> Result<Response<DeleteUserSessionResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::SessionsManage)
+            .map_err(to_status)?;
+        let session_uuid = parse_uuid(&request.get_ref().session_uuid)?;
+        self.state
+            .get_domain()
+            .delete_user_session(&actor.organization, &uuid, &session_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(DeleteUserSessionResponse {}))
+    }
+
+    async fn revoke_user_sessions(
+        &self,
+        request: Request<RevokeUserSessionsRequest>,
+    ) -> Result<Response<RevokeUserSessionsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::SessionsManage)
+            .map_err(to_status)?;
+        self.state
+            .get_domain()
+            .revoke_user_sessions(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(RevokeUserSessionsResponse {}))
+    }
+
+    async fn list_user_logins(
+        &self,
+        request: Request<ListUserLoginsRequest>,
+    ) -> Result<Response<ListUserLoginsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::UsersRead)
+            .map_err(to_status)?;
+        let pagination = Pagination::from(request.get_ref());
+        let page = self
+            .state
+            .get_domain()
+            .get_login_history(&actor.organization, &uuid, pagination)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListUserLoginsResponse {
+            events: page
+                .items
+                .into_iter()
+                .map(|event| LoginEventView::from(event).into())
+                .collect(),
+            total: page.total,
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        }))
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> Result<Response<DeleteUserResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::UsersDelete).map_err(to_status)?;
+        let user = self
+            .find_user(&actor.organization, &request.get_ref().uuid, false)
+            .await?;
+        self.state
+            .get_domain()
+            .delete_user(&actor.organization, user, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(DeleteUserResponse {}))
+    }
+
+    async fn restore_user(
+        &self,
+        request: Request<RestoreUserRequest>,
+    ) -> Result<Response<controller::AppUser>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::UsersDelete).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let user = self
+            .state
+            .get_domain()
+            .restore_user(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(user.into()))
+    }
+
+    async fn list_roles(
+        &self,
+        request: Request<ListRolesRequest>,
+    ) -> Result<Response<ListRolesResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let roles = self
+            .state
+            .get_domain()
+            .get_all_roles(&actor.organization)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListRolesResponse {
+            roles: roles
+                .into_iter()
+                .map(|role| RoleView::from(role).into())
+                .collect(),
+        }))
+    }
+
+    async fn create_role(
+        &self,
+        request: Request<CreateRoleRequest>,
+    ) -> Result<Response<controller::Role>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let command = CreateRoleCommand::try_from(request.into_inner())?;
+        let created_role = self
+            .state
+            .get_domain()
+            .create_role(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(RoleView::from(created_role).into()))
+    }
+
+    async fn update_role(
+        &self,
+        request: Request<UpdateRoleRequest>,
+    ) -> Result<Response<controller::Role>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let command = UpdateRoleCommand::try_from(request.into_inner())?;
+        let updated_role = self
+            .state
+            .get_domain()
+            .update_role(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(RoleView::from(updated_role).into()))
+    }
+
+    async fn delete_role(
+        &self,
+        request: Request<DeleteRoleRequest>,
+    ) -> Result<Response<DeleteRoleResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        self.state
+            .get_domain()
+            .delete_role(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(DeleteRoleResponse {}))
+    }
+
+    async fn list_user_roles(
+        &self,
+        request: Request<ListUserRolesRequest>,
+    ) -> Result<Response<ListRolesResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::RolesManage)
+            .map_err(to_status)?;
+        let roles = self
+            .state
+            .get_domain()
+            .get_user_roles(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListRolesResponse {
+            roles: roles
+                .into_iter()
+                .map(|role| RoleView::from(role).into())
+                .collect(),
+        }))
+    }
+
+    async fn assign_user_role(
+        &self,
+        request: Request<AssignUserRoleRequest>,
+    ) -> Result<Response<AssignUserRoleResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let role_uuid = parse_uuid(&request.get_ref().role_uuid)?;
+        self.state
+            .get_domain()
+            .assign_role(&actor.organization, &uuid, &role_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(AssignUserRoleResponse {}))
+    }
+
+    async fn unassign_user_role(
+        &self,
+        request: Request<UnassignUserRoleRequest>,
+    ) -> Result<Response<UnassignUserRoleResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let role_uuid = parse_uuid(&request.get_ref().role_uuid)?;
+        self.state
+            .get_domain()
+            .unassign_role(&actor.organization, &uuid, &role_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(UnassignUserRoleResponse {}))
+    }
+
+    async fn get_group(
+        &self,
+        request: Request<GetGroupRequest>,
+    ) -> Result<Response<controller::Group>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_member_or(&uuid, Permission::GroupsRead)
+            .map_err(to_status)?;
+        let group = self
+            .state
+            .get_domain()
+            .get_group(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+        let group = group.ok_or_else(|| to_status(UserDomainError::not_found("Group")))?;
+        Ok(Response::new(GroupView::from(group).into()))
+    }
+
+    async fn list_groups(
+        &self,
+        request: Request<ListGroupsRequest>,
+    ) -> Result<Response<ListGroupsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor.require(Permission::GroupsRead).map_err(to_status)?;
+        let groups = self
+            .state
+            .get_domain()
+            .get_all_groups(&actor.organization)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListGroupsResponse {
+            groups: groups
+                .into_iter()
+                .map(|item| GroupView::from(item).into())
+                .collect(),
+        }))
+    }
+
+    async fn create_group(
+        &self,
+        request: Request<CreateGroupRequest>,
+    ) -> Result<Response<controller::Group>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::GroupsManage).map_err(to_status)?;
+        let command = CreateGroupCommand::try_from(request.into_inner())?;
+        let created_group = self
+            .state
+            .get_domain()
+            .create_group(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(GroupView::from(created_group).into()))
+    }
+
+    async fn update_group(
+        &self,
+        request: Request<UpdateGroupRequest>,
+    ) -> Result<Response<controller::Group>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::GroupsManage).map_err(to_status)?;
+        let command = UpdateGroupCommand::try_from(request.into_inner())?;
+        let updated_group = self
+            .state
+            .get_domain()
+            .update_group(&actor.organization, command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(GroupView::from(updated_group).into()))
+    }
+
+    async fn delete_group(
+        &self,
+        request: Request<DeleteGroupRequest>,
+    ) -> Result<Response<DeleteGroupResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::GroupsManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        self.state
+            .get_domain()
+            .delete_group(&actor.organization, &uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(DeleteGroupResponse {}))
+    }
+
+    async fn list_group_members(
+        &self,
+        request: Request<ListGroupMembersRequest>,
+    ) -> Result<Response<ListUsersResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_member_or(&uuid, Permission::GroupsRead)
+            .map_err(to_status)?;
+        let members = self
+            .state
+            .get_domain()
+            .get_group_members(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListUsersResponse {
+            total: members.len() as i64,
+            users: members.into_iter().map(|user| user.into()).collect(),
+            next_cursor: String::new(),
+        }))
+    }
+
+    async fn add_group_member(
+        &self,
+        request: Request<AddGroupMemberRequest>,
+    ) -> Result<Response<AddGroupMemberResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::GroupsManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let user_uuid = parse_uuid(&request.get_ref().user_uuid)?;
+        self.state
+            .get_domain()
+            .add_group_member(&actor.organization, &uuid, &user_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(AddGroupMemberResponse {}))
+    }
+
+    async fn remove_group_member(
+        &self,
+        request: Request<RemoveGroupMemberRequest>,
+    ) -> Result<Response<RemoveGroupMemberResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::GroupsManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let user_uuid = parse_uuid(&request.get_ref().user_uuid)?;
+        self.state
+            .get_domain()
+            .remove_group_member(&actor.organization, &uuid, &user_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(RemoveGroupMemberResponse {}))
+    }
+
+    async fn list_group_roles(
+        &self,
+        request: Request<ListGroupRolesRequest>,
+    ) -> Result<Response<ListRolesResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_member_or(&uuid, Permission::GroupsRead)
+            .map_err(to_status)?;
+        let roles = self
+            .state
+            .get_domain()
+            .get_group_roles(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListRolesResponse {
+            roles: roles
+                .into_iter()
+                .map(|item| RoleView::from(item).into())
+                .collect(),
+        }))
+    }
+
+    async fn assign_group_role(
+        &self,
+        request: Request<AssignGroupRoleRequest>,
+    ) -> Result<Response<AssignGroupRoleResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let role_uuid = parse_uuid(&request.get_ref().role_uuid)?;
+        self.state
+            .get_domain()
+            .assign_group_role(&actor.organization, &uuid, &role_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(AssignGroupRoleResponse {}))
+    }
+
+    async fn unassign_group_role(
+        &self,
+        request: Request<UnassignGroupRoleRequest>,
+    ) -> Result<Response<UnassignGroupRoleResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor.require(Permission::RolesManage).map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let role_uuid = parse_uuid(&request.get_ref().role_uuid)?;
+        self.state
+            .get_domain()
+            .unassign_group_role(&actor.organization, &uuid, &role_uuid, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(UnassignGroupRoleResponse {}))
+    }
+
+    async fn list_user_groups(
+        &self,
+        request: Request<ListUserGroupsRequest>,
+    ) -> Result<Response<ListGroupsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        actor
+            .require_self_or(&uuid, Permission::GroupsRead)
+            .map_err(to_status)?;
+        let groups = self
+            .state
+            .get_domain()
+            .get_user_groups(&actor.organization, &uuid)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListGroupsResponse {
+            groups: groups
+                .into_iter()
+                .map(|item| GroupView::from(item).into())
+                .collect(),
+        }))
+    }
+
+    async fn get_organization(
+        &self,
+        request: Request<GetOrganizationRequest>,
+    ) -> Result<Response<controller::Organization>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor
+            .require_default_organization(Permission::OrganizationsManage)
+            .map_err(to_status)?;
+        let uuid = parse_uuid(&request.get_ref().uuid)?;
+        let organization = self
+            .state
+            .get_domain()
+            .get_organization(&uuid)
+            .await
+            .map_err(to_status)?;
+        let organization =
+            organization.ok_or_else(|| to_status(UserDomainError::not_found("Organization")))?;
+        Ok(Response::new(OrganizationView::from(organization).into()))
+    }
+
+    async fn list_organizations(
+        &self,
+        request: Request<ListOrganizationsRequest>,
+    ) -> Result<Response<ListOrganizationsResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor
+            .require_default_organization(Permission::OrganizationsManage)
+            .map_err(to_status)?;
+        let organizations = self
+            .state
+            .get_domain()
+            .get_all_organizations()
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(ListOrganizationsResponse {
+            organizations: organizations
+                .into_iter()
+                .map(|item| OrganizationView::from(item).into())
+                .collect(),
+        }))
+    }
+
+    async fn create_organization(
+        &self,
+        request: Request<CreateOrganizationRequest>,
+    ) -> Result<Response<controller::Organization>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor
+            .require_default_organization(Permission::OrganizationsManage)
+            .map_err(to_status)?;
+        let command = CreateOrganizationCommand::try_from(request.into_inner())?;
+        let created_organization = self
+            .state
+            .get_domain()
+            .create_organization(command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(
+            OrganizationView::from(created_organization).into(),
+        ))
+    }
+
+    async fn update_organization(
+        &self,
+        request: Request<UpdateOrganizationRequest>,
+    ) -> Result<Response<controller::Organization>, Status> {
+        let actor = self.get_actor(&request).await?;
+        let context = get_audit_context(&request, Some(&actor));
+        actor
+            .require_default_organization(Permission::OrganizationsManage)
+            .map_err(to_status)?;
+        let command = UpdateOrganizationCommand::try_from(request.into_inner())?;
+        let updated_organization = self
+            .state
+            .get_domain()
+            .update_organization(command, &context)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(
+            OrganizationView::from(updated_organization).into(),
+        ))
+    }
+
+    async fn list_audit_entries(
+        &self,
+        request: Request<ListAuditEntriesRequest>,
+    ) -> Result<Response<ListAuditEntriesResponse>, Status> {
+        let actor = self.get_actor(&request).await?;
+        actor.require(Permission::AuditRead).map_err(to_status)?;
+        let query = AuditQuery::try_from(request.into_inner())?;
+        let page = self
+            .state
+            .get_domain()
+            .get_audit_entries(&actor.organization, query)
+            .await
+            .map_err(to_status)?;
 
-        Ok(Response::new(AuthResponse {
-            token: "A_VALID_TOKEN".to_string(),
+        Ok(Response::new(ListAuditEntriesResponse {
+            entries: page
+                .items
+                .into_iter()
+                .map(|entry| AuditEntryView::from(entry).into())
+                .collect(),
+            total: page.total,
+            next_cursor: page.next_cursor.unwrap_or_default(),
         }))
     }
 }
@@ -0,0 +1,544 @@
+use crate::controller;
+use chrono::prelude::*;
+use helix_user_domain::core::app_user::{AccountStatus, AppUser};
+use helix_user_domain::core::audit::*;
+use helix_user_domain::core::command::*;
+use helix_user_domain::core::person::Person;
+use helix_user_domain::core::query::*;
+use helix_user_domain::core::role::Permission;
+use helix_user_domain::core::token::TokenPair;
+use helix_user_domain::core::two_factor::*;
+use helix_user_domain::core::view::{
+    AuditEntryView, GroupView, LoginEventView, OrganizationView, PersonView, RoleView, SessionView,
+    StatusChangeView, UserView,
+};
+use std::convert::TryFrom;
+use tonic::Status;
+
+pub fn parse_uuid(value: &str) -> Result<uuid::Uuid, Status> {
+    uuid::Uuid::parse_str(value).map_err(|_| Status::invalid_argument("Invalid uuid."))
+}
+
+fn parse_optional_uuid(value: &str) -> Result<Option<uuid::Uuid>, Status> {
+    match value.is_empty() {
+        true => Ok(None),
+        false => parse_uuid(value).map(Some),
+    }
+}
+
+fn parse_optional_date(value: &str) -> Result<Option<DateTime<Utc>>, Status> {
+    match value.is_empty() {
+        true => Ok(None),
+        false => DateTime::parse_from_rfc3339(value)
+            .map(|date| Some(date.with_timezone(&Utc)))
+            .map_err(|_| Status::invalid_argument("Invalid date.")),
+    }
+}
+
+fn parse_optional_string(value: String) -> Option<String> {
+    match value.is_empty() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+fn parse_optional_number(value: u32) -> Option<u32> {
+    match value {
+        0 => None,
+        value => Some(value),
+    }
+}
+
+fn parse_direction(value: &str) -> Result<SortDirection, Status> {
+    match value.is_empty() {
+        true => Ok(SortDirection::default()),
+        false => SortDirection::from_name(value)
+            .ok_or_else(|| Status::invalid_argument("Invalid sort direction.")),
+    }
+}
+
+fn format_optional_uuid(value: Option<uuid::Uuid>) -> String {
+    value.map(|uuid| uuid.to_string()).unwrap_or_default()
+}
+
+fn format_optional_date(value: Option<DateTime<Utc>>) -> String {
+    value.map(|date| date.to_rfc3339()).unwrap_or_default()
+}
+
+fn parse_permissions(values: Vec<String>) -> Result<Vec<Permission>, Status> {
+    values
+        .iter()
+        .map(|value| {
+            Permission::from_name(value)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown permission {}.", value)))
+        })
+        .collect()
+}
+
+fn parse_optional_bytes(value: Vec<u8>) -> Option<Vec<u8>> {
+    match value.is_empty() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+impl From<PersonView> for controller::Person {
+    fn from(person: PersonView) -> Self {
+        controller::Person {
+            uuid: format_optional_uuid(person.uuid),
+            firstname: person.firstname,
+            lastname: person.lastname,
+            email: person.email,
+            phone: person.phone.unwrap_or_default(),
+            created_on: format_optional_date(person.created_on),
+            updated_on: format_optional_date(person.updated_on),
+            email_status: person.email_status.as_str().to_string(),
+            email_verified_on: format_optional_date(person.email_verified_on),
+            deleted_on: format_optional_date(person.deleted_on),
+            deleted_by: format_optional_uuid(person.deleted_by),
+        }
+    }
+}
+
+impl From<Person> for controller::Person {
+    fn from(person: Person) -> Self {
+        PersonView::from(person).into()
+    }
+}
+
+impl From<UserView> for controller::AppUser {
+    fn from(user: UserView) -> Self {
+        controller::AppUser {
+            uuid: format_optional_uuid(user.uuid),
+            login: user.login,
+            photo_url: user.photo_url.unwrap_or_default(),
+            created_on: format_optional_date(user.created_on),
+            updated_on: format_optional_date(user.updated_on),
+            last_login_on: format_optional_date(user.last_login_on),
+            person: Some(user.person.into()),
+            groups: user.groups.unwrap_or_default(),
+            status: user.status.as_str().to_string(),
+            status_reason: user.status_reason.unwrap_or_default(),
+            suspended_until: format_optional_date(user.suspended_until),
+            deleted_on: format_optional_date(user.deleted_on),
+            deleted_by: format_optional_uuid(user.deleted_by),
+        }
+    }
+}
+
+impl From<AppUser> for controller::AppUser {
+    fn from(user: AppUser) -> Self {
+        UserView::from(user).into()
+    }
+}
+
+impl From<controller::CreatePersonRequest> for CreatePersonCommand {
+    fn from(request: controller::CreatePersonRequest) -> Self {
+        CreatePersonCommand {
+            firstname: request.firstname,
+            lastname: request.lastname,
+            email: request.email,
+            phone: parse_optional_string(request.phone),
+        }
+    }
+}
+
+impl TryFrom<controller::UpdatePersonRequest> for UpdatePersonCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::UpdatePersonRequest) -> Result<Self, Self::Error> {
+        Ok(UpdatePersonCommand {
+            uuid: parse_uuid(&request.uuid)?,
+            firstname: request.firstname,
+            lastname: request.lastname,
+            email: request.email,
+            phone: parse_optional_string(request.phone),
+        })
+    }
+}
+
+impl TryFrom<controller::CreateUserRequest> for CreateUserCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::CreateUserRequest) -> Result<Self, Self::Error> {
+        Ok(CreateUserCommand {
+            login: request.login,
+            password: request.password,
+            photo: parse_optional_bytes(request.photo),
+            person_uuid: parse_uuid(&request.person_uuid)?,
+        })
+    }
+}
+
+impl From<controller::RegisterAccountRequest> for RegisterAccountCommand {
+    fn from(request: controller::RegisterAccountRequest) -> Self {
+        RegisterAccountCommand {
+            firstname: request.firstname,
+            lastname: request.lastname,
+            email: request.email,
+            phone: parse_optional_string(request.phone),
+            login: request.login,
+            password: request.password,
+            photo: parse_optional_bytes(request.photo),
+        }
+    }
+}
+
+impl TryFrom<controller::UpdateUserRequest> for UpdateUserCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::UpdateUserRequest) -> Result<Self, Self::Error> {
+        Ok(UpdateUserCommand {
+            uuid: parse_uuid(&request.uuid)?,
+            login: request.login,
+            photo: parse_optional_bytes(request.photo),
+        })
+    }
+}
+
+impl TryFrom<controller::ChangeUserStatusRequest> for ChangeStatusCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::ChangeUserStatusRequest) -> Result<Self, Self::Error> {
+        Ok(ChangeStatusCommand {
+            status: AccountStatus::from_name(&request.status)
+                .ok_or_else(|| Status::invalid_argument("Invalid status."))?,
+            reason: parse_optional_string(request.reason),
+            suspended_until: parse_optional_date(&request.suspended_until)?,
+        })
+    }
+}
+
+impl TryFrom<controller::ListPersonsRequest> for PersonQuery {
+    type Error = Status;
+
+    fn try_from(request: controller::ListPersonsRequest) -> Result<Self, Self::Error> {
+        let sort = match request.sort.is_empty() {
+            true => PersonSortField::default(),
+            false => PersonSortField::from_name(&request.sort)
+                .ok_or_else(|| Status::invalid_argument("Invalid sort field."))?,
+        };
+
+        Ok(PersonQuery {
+            limit: parse_optional_number(request.limit),
+            cursor: parse_optional_string(request.cursor),
+            page: parse_optional_number(request.page),
+            sort,
+            direction: parse_direction(&request.direction)?,
+            email: parse_optional_string(request.email),
+            name: parse_optional_string(request.name),
+            created_from: parse_optional_date(&request.created_from)?,
+            created_to: parse_optional_date(&request.created_to)?,
+            include_deleted: request.include_deleted,
+        })
+    }
+}
+
+impl TryFrom<controller::ListUsersRequest> for UserQuery {
+    type Error = Status;
+
+    fn try_from(request: controller::ListUsersRequest) -> Result<Self, Self::Error> {
+        let sort = match request.sort.is_empty() {
+            true => UserSortField::default(),
+            false => UserSortField::from_name(&request.sort)
+                .ok_or_else(|| Status::invalid_argument("Invalid sort field."))?,
+        };
+
+        Ok(UserQuery {
+            limit: parse_optional_number(request.limit),
+            cursor: parse_optional_string(request.cursor),
+            page: parse_optional_number(request.page),
+            sort,
+            direction: parse_direction(&request.direction)?,
+            login: parse_optional_string(request.login),
+            email: parse_optional_string(request.email),
+            name: parse_optional_string(request.name),
+            created_from: parse_optional_date(&request.created_from)?,
+            created_to: parse_optional_date(&request.created_to)?,
+            include_deleted: request.include_deleted,
+        })
+    }
+}
+
+impl TryFrom<controller::ListAuditEntriesRequest> for AuditQuery {
+    type Error = Status;
+
+    fn try_from(request: controller::ListAuditEntriesRequest) -> Result<Self, Self::Error> {
+        let action = match request.action.is_empty() {
+            true => None,
+            false => Some(
+                AuditAction::from_name(&request.action)
+                    .ok_or_else(|| Status::invalid_argument("Invalid action."))?,
+            ),
+        };
+        let target = match request.target.is_empty() {
+            true => None,
+            false => Some(
+                AuditTarget::from_name(&request.target)
+                    .ok_or_else(|| Status::invalid_argument("Invalid target."))?,
+            ),
+        };
+
+        Ok(AuditQuery {
+            limit: parse_optional_number(request.limit),
+            cursor: parse_optional_string(request.cursor),
+            page: parse_optional_number(request.page),
+            actor: parse_optional_uuid(&request.actor)?,
+            action,
+            target,
+            target_uuid: parse_optional_uuid(&request.target_uuid)?,
+            request_id: parse_optional_string(request.request_id),
+            from: parse_optional_date(&request.from)?,
+            to: parse_optional_date(&request.to)?,
+        })
+    }
+}
+
+impl From<FieldChange> for controller::FieldChange {
+    fn from(change: FieldChange) -> Self {
+        controller::FieldChange {
+            field: change.field,
+            before: change.before.to_string(),
+            after: change.after.to_string(),
+        }
+    }
+}
+
+impl From<AuditEntryView> for controller::AuditEntry {
+    fn from(entry: AuditEntryView) -> Self {
+        controller::AuditEntry {
+            actor: format_optional_uuid(entry.actor),
+            action: entry.action.as_str().to_string(),
+            target: entry.target.as_str().to_string(),
+            target_uuid: format_optional_uuid(entry.target_uuid),
+            changes: entry.changes.into_iter().map(Into::into).collect(),
+            request_id: entry.request_id.unwrap_or_default(),
+            occurred_on: entry.occurred_on.to_rfc3339(),
+        }
+    }
+}
+
+impl From<LoginEventView> for controller::LoginEvent {
+    fn from(event: LoginEventView) -> Self {
+        controller::LoginEvent {
+            occurred_on: event.occurred_on.to_rfc3339(),
+            ip: event.ip.unwrap_or_default(),
+            user_agent: event.user_agent.unwrap_or_default(),
+            success: event.success,
+            channel: event.channel.as_str().to_string(),
+        }
+    }
+}
+
+impl From<StatusChangeView> for controller::StatusChange {
+    fn from(change: StatusChangeView) -> Self {
+        controller::StatusChange {
+            previous_status: change.previous_status.as_str().to_string(),
+            status: change.status.as_str().to_string(),
+            reason: change.reason.unwrap_or_default(),
+            suspended_until: format_optional_date(change.suspended_until),
+            changed_by: format_optional_uuid(change.changed_by),
+            changed_on: change.changed_on.to_rfc3339(),
+        }
+    }
+}
+
+impl From<SessionView> for controller::Session {
+    fn from(session: SessionView) -> Self {
+        controller::Session {
+            uuid: format_optional_uuid(session.uuid),
+            device: session.device.unwrap_or_default(),
+            ip: session.ip.unwrap_or_default(),
+            user_agent: session.user_agent.unwrap_or_default(),
+            created_on: session.created_on.to_rfc3339(),
+            refreshed_on: format_optional_date(session.refreshed_on),
+            expires_on: session.expires_on.to_rfc3339(),
+        }
+    }
+}
+
+impl From<&controller::ListUserLoginsRequest> for Pagination {
+    fn from(request: &controller::ListUserLoginsRequest) -> Self {
+        Pagination {
+            limit: parse_optional_number(request.limit),
+            cursor: parse_optional_string(request.cursor.clone()),
+            page: parse_optional_number(request.page),
+        }
+    }
+}
+
+impl From<RoleView> for controller::Role {
+    fn from(role: RoleView) -> Self {
+        controller::Role {
+            uuid: format_optional_uuid(role.uuid),
+            name: role.name,
+            description: role.description.unwrap_or_default(),
+            permissions: role
+                .permissions
+                .iter()
+                .map(|permission| permission.as_str().to_string())
+                .collect(),
+            created_on: format_optional_date(role.created_on),
+            updated_on: format_optional_date(role.updated_on),
+        }
+    }
+}
+
+impl TryFrom<controller::CreateRoleRequest> for CreateRoleCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::CreateRoleRequest) -> Result<Self, Self::Error> {
+        Ok(CreateRoleCommand {
+            name: request.name,
+            description: parse_optional_string(request.description),
+            permissions: parse_permissions(request.permissions)?,
+        })
+    }
+}
+
+impl TryFrom<controller::UpdateRoleRequest> for UpdateRoleCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::UpdateRoleRequest) -> Result<Self, Self::Error> {
+        Ok(UpdateRoleCommand {
+            uuid: parse_uuid(&request.uuid)?,
+            name: request.name,
+            description: parse_optional_string(request.description),
+            permissions: parse_permissions(request.permissions)?,
+        })
+    }
+}
+
+impl From<GroupView> for controller::Group {
+    fn from(group: GroupView) -> Self {
+        controller::Group {
+            uuid: format_optional_uuid(group.uuid),
+            name: group.name,
+            description: group.description.unwrap_or_default(),
+            parent_uuid: format_optional_uuid(group.parent_uuid),
+            created_on: format_optional_date(group.created_on),
+            updated_on: format_optional_date(group.updated_on),
+        }
+    }
+}
+
+impl TryFrom<controller::CreateGroupRequest> for CreateGroupCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::CreateGroupRequest) -> Result<Self, Self::Error> {
+        Ok(CreateGroupCommand {
+            name: request.name,
+            description: parse_optional_string(request.description),
+            parent_uuid: parse_optional_uuid(&request.parent_uuid)?,
+        })
+    }
+}
+
+impl TryFrom<controller::UpdateGroupRequest> for UpdateGroupCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::UpdateGroupRequest) -> Result<Self, Self::Error> {
+        Ok(UpdateGroupCommand {
+            uuid: parse_uuid(&request.uuid)?,
+            name: request.name,
+            description: parse_optional_string(request.description),
+            parent_uuid: parse_optional_uuid(&request.parent_uuid)?,
+        })
+    }
+}
+
+impl From<TokenPair> for controller::AuthResponse {
+    fn from(tokens: TokenPair) -> Self {
+        controller::AuthResponse {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            challenge_token: String::new(),
+            challenge_expires_on: String::new(),
+        }
+    }
+}
+
+impl From<LoginStep> for controller::AuthResponse {
+    fn from(step: LoginStep) -> Self {
+        match step {
+            LoginStep::Tokens(tokens) => tokens.into(),
+            LoginStep::Challenge(challenge) => controller::AuthResponse {
+                access_token: String::new(),
+                refresh_token: String::new(),
+                challenge_token: challenge.challenge_token,
+                challenge_expires_on: challenge.expires_on.to_rfc3339(),
+            },
+        }
+    }
+}
+
+impl From<TwoFactorStatus> for controller::TwoFactorStatus {
+    fn from(status: TwoFactorStatus) -> Self {
+        controller::TwoFactorStatus {
+            enabled: status.enabled,
+            confirmed_on: format_optional_date(status.confirmed_on),
+            recovery_codes_left: status.recovery_codes_left as u32,
+        }
+    }
+}
+
+impl From<TwoFactorSetup> for controller::TwoFactorSetup {
+    fn from(setup: TwoFactorSetup) -> Self {
+        controller::TwoFactorSetup {
+            secret: setup.secret,
+            otpauth_uri: setup.otpauth_uri,
+            qr_code: setup.qr_code,
+        }
+    }
+}
+
+impl From<RecoveryCodes> for controller::RecoveryCodes {
+    fn from(codes: RecoveryCodes) -> Self {
+        controller::RecoveryCodes {
+            recovery_codes: codes.recovery_codes,
+        }
+    }
+}
+
+impl From<OrganizationView> for controller::Organization {
+    fn from(organization: OrganizationView) -> Self {
+        controller::Organization {
+            uuid: format_optional_uuid(organization.uuid),
+            name: organization.name,
+            created_on: format_optional_date(organization.created_on),
+            updated_on: format_optional_date(organization.updated_on),
+        }
+    }
+}
+
+impl TryFrom<controller::CreateOrganizationRequest> for CreateOrganizationCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::CreateOrganizationRequest) -> Result<Self, Self::Error> {
+        let admin = request
+            .admin
+            .ok_or_else(|| Status::invalid_argument("Missing admin."))?;
+        Ok(CreateOrganizationCommand {
+            name: request.name,
+            admin: OrganizationAdminCommand {
+                firstname: admin.firstname,
+                lastname: admin.lastname,
+                email: admin.email,
+                phone: parse_optional_string(admin.phone),
+                login: admin.login,
+                password: admin.password,
+            },
+        })
+    }
+}
+
+impl TryFrom<controller::UpdateOrganizationRequest> for UpdateOrganizationCommand {
+    type Error = Status;
+
+    fn try_from(request: controller::UpdateOrganizationRequest) -> Result<Self, Self::Error> {
+        Ok(UpdateOrganizationCommand {
+            uuid: parse_uuid(&request.uuid)?,
+            name: request.name,
+        })
+    }
+}
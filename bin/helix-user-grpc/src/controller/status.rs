@@ -0,0 +1,102 @@
+use crate::controller::{ErrorDetails, FieldViolation};
+use bytes::Bytes;
+use helix_user_domain::business::error::UserDomainError;
+use prost::Message;
+use tonic::{Code, Status};
+
+pub fn get_status_code(error: &UserDomainError) -> Code {
+    match error {
+        UserDomainError::NotImplemented => Code::Unimplemented,
+        UserDomainError::InvalidCredentials => Code::Unauthenticated,
+        UserDomainError::NotFound { .. } => Code::NotFound,
+        UserDomainError::Conflict { .. } => Code::AlreadyExists,
+        UserDomainError::Validation { .. } => Code::InvalidArgument,
+        UserDomainError::InvalidToken => Code::Unauthenticated,
+        UserDomainError::Forbidden => Code::PermissionDenied,
+        UserDomainError::EmailNotVerified => Code::FailedPrecondition,
+        UserDomainError::AccountInactive { .. } => Code::FailedPrecondition,
+        UserDomainError::AccountLocked { .. } => Code::FailedPrecondition,
+        UserDomainError::TooManyAttempts { .. } => Code::ResourceExhausted,
+        UserDomainError::BackendUnavailable => Code::Unavailable,
+        UserDomainError::PasswordHashError => Code::Internal,
+        UserDomainError::TokenGenerationError => Code::Internal,
+        UserDomainError::NotificationError => Code::Internal,
+        UserDomainError::MissingUuid { .. } => Code::Internal,
+        UserDomainError::Storage { .. } => Code::Internal,
+    }
+}
+
+pub fn to_status(error: UserDomainError) -> Status {
+    let code = get_status_code(&error);
+
+    //Do not leak internal details to clients.
+    let message = match code {
+        Code::Internal => {
+            println!("Internal error: {}", error);
+            "Internal Server Error.".to_string()
+        }
+        _ => format!("{}: {}", error.code(), error),
+    };
+
+    let errors = error.field_errors();
+    if errors.is_empty() {
+        return Status::new(code, message);
+    }
+
+    //Field errors travel as an encoded ErrorDetails message.
+    let details = ErrorDetails {
+        code: error.code().to_string(),
+        errors: errors
+            .into_iter()
+            .map(|error| FieldViolation {
+                field: error.field,
+                code: error.code,
+                message: error.message,
+            })
+            .collect(),
+    };
+
+    let mut buffer = Vec::new();
+    match details.encode(&mut buffer) {
+        Ok(_) => Status::with_details(code, message, Bytes::from(buffer)),
+        Err(_) => Status::new(code, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helix_user_domain::business::error::FieldError;
+    use helix_user_domain::storage::error::StorageError;
+
+    #[test]
+    fn not_found_keeps_its_code() {
+        let status = to_status(UserDomainError::not_found("User"));
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "not_found: User not found");
+        assert!(status.details().is_empty());
+    }
+
+    #[test]
+    fn field_errors_travel_as_details() {
+        let status = to_status(UserDomainError::Validation {
+            errors: vec![FieldError::new("login", "too_short", "Value is too short.")],
+        });
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let details = ErrorDetails::decode(status.details()).unwrap();
+        assert_eq!(details.code, "validation_failed");
+        assert_eq!(details.errors.len(), 1);
+        assert_eq!(details.errors[0].field, "login");
+        assert_eq!(details.errors[0].code, "too_short");
+    }
+
+    #[test]
+    fn internal_errors_are_hidden() {
+        let status = to_status(UserDomainError::Storage {
+            source: StorageError::Migration("V001 failed".to_string()),
+        });
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "Internal Server Error.");
+    }
+}
@@ -1,5 +1,7 @@
 use crate::controller::business_controller::ImplUserService;
 use crate::controller::user_service_server::UserServiceServer;
+use helix_user_runtime::configuration::Configuration;
+use helix_user_runtime::state::AppState;
 use tonic::transport::Server;
 
 pub mod controller;
@@ -9,14 +11,21 @@ const APP_NAME: &str = "USER_APP";
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("[HELIX gRPC {} {}]", APP_NAME, env!("CARGO_PKG_VERSION"));
-    let addr = "127.0.0.1:42420".parse()?;
+    dotenv::dotenv().ok();
+    let addr = Configuration::get_grpc_served_addr().parse()?;
 
     let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
     health_reporter
         .set_serving::<UserServiceServer<ImplUserService>>()
         .await;
 
-    let impl_user_service = ImplUserService::default();
+    AppState::migrate_database().await;
+    let app_state = AppState::new();
+    app_state.bootstrap_admin().await;
+
+    //Deleted records are purged in the background once their retention is over.
+    tokio::spawn(app_state.clone().purge_periodically());
+    let impl_user_service = ImplUserService::new(app_state);
 
     print!("--> Started on ");
     println!("http://{}", addr);
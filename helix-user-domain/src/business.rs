@@ -1,3 +1,11 @@
+pub mod authorization;
 pub mod domain;
 pub mod error;
+pub mod lockout;
+pub mod notifier;
+pub mod password;
+pub mod settings;
+pub mod token;
 pub mod traits;
+pub mod two_factor;
+pub mod validation;
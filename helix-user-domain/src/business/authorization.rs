@@ -0,0 +1,84 @@
+use crate::business::error::*;
+use crate::core::organization::Organization;
+use crate::core::role::Permission;
+use uuid;
+
+//Authenticated caller, with the permissions of all its roles.
+#[derive(Debug, Clone)]
+pub struct Actor {
+    //Tenant of the caller, every request it makes acts in it.
+    pub organization: Organization,
+    pub user_uuid: uuid::Uuid,
+    pub person_uuid: uuid::Uuid,
+    pub permissions: Vec<Permission>,
+    //Effective groups, parents of the joined groups included.
+    pub groups: Vec<uuid::Uuid>,
+}
+
+impl Actor {
+    pub fn is_member_of(&self, group_uuid: &uuid::Uuid) -> bool {
+        self.groups.contains(group_uuid)
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    pub fn require(&self, permission: Permission) -> UserDomainResult<()> {
+        match self.has_permission(permission) {
+            true => Ok(()),
+            false => Err(UserDomainError::Forbidden),
+        }
+    }
+
+    //Organizations are managed by the host, from the default organization.
+    pub fn require_default_organization(&self, permission: Permission) -> UserDomainResult<()> {
+        match self.organization.is_default() {
+            true => self.require(permission),
+            false => Err(UserDomainError::Forbidden),
+        }
+    }
+
+    //Only the user itself, whatever its permissions.
+    pub fn require_self(&self, user_uuid: &uuid::Uuid) -> UserDomainResult<()> {
+        match &self.user_uuid == user_uuid {
+            true => Ok(()),
+            false => Err(UserDomainError::Forbidden),
+        }
+    }
+
+    //Users may act on their own account without the permission.
+    pub fn require_self_or(
+        &self,
+        user_uuid: &uuid::Uuid,
+        permission: Permission,
+    ) -> UserDomainResult<()> {
+        match &self.user_uuid == user_uuid {
+            true => Ok(()),
+            false => self.require(permission),
+        }
+    }
+
+    pub fn require_person_or(
+        &self,
+        person_uuid: &uuid::Uuid,
+        permission: Permission,
+    ) -> UserDomainResult<()> {
+        match &self.person_uuid == person_uuid {
+            true => Ok(()),
+            false => self.require(permission),
+        }
+    }
+
+    //Members may see the groups they belong to.
+    pub fn require_member_or(
+        &self,
+        group_uuid: &uuid::Uuid,
+        permission: Permission,
+    ) -> UserDomainResult<()> {
+        match self.is_member_of(group_uuid) {
+            true => Ok(()),
+            false => self.require(permission),
+        }
+    }
+}
@@ -1,98 +1,3032 @@
+use crate::business::authorization::Actor;
 use crate::business::error::*;
+use crate::business::lockout::LockoutPolicy;
+use crate::business::notifier::{Notification, NotificationKind, Notifier};
+use crate::business::password::{PasswordCheck, PasswordManager};
+use crate::business::settings::UserDomainSettings;
+use crate::business::token::{AccessTokenIssuer, TokenManager};
 use crate::business::traits::UserDomainTrait;
-use crate::core::app_user::AppUser;
-use crate::core::person::Person;
-use crate::storage::traits::StorageTrait;
+use crate::business::two_factor::TwoFactorManager;
+use crate::business::validation;
+use crate::core::app_user::*;
+use crate::core::audit::*;
+use crate::core::command::*;
+use crate::core::group::Group;
+use crate::core::login::*;
+use crate::core::organization::Organization;
+use crate::core::password_reset::PasswordReset;
+use crate::core::person::*;
+use crate::core::query::*;
+use crate::core::role::*;
+use crate::core::session::Session;
+use crate::core::token::{RefreshToken, TokenPair};
+use crate::core::two_factor::*;
+use crate::storage::traits::{StorageTrait, StorageTransaction};
 use async_trait::async_trait;
-use crypto::digest::Digest;
-use crypto::sha2::Sha256;
+use chrono::prelude::*;
 use std::boxed::Box;
 
+const DEVICE_MAX_LENGTH: usize = 100;
+
 pub struct UserDomain {
     storage: Box<dyn StorageTrait>,
+    password_manager: PasswordManager,
+    lockout_policy: LockoutPolicy,
+    token_manager: TokenManager,
+    token_issuer: Box<dyn AccessTokenIssuer>,
+    notifier: Box<dyn Notifier>,
+    two_factor_manager: TwoFactorManager,
+    require_verified_email: bool,
+    require_activation: bool,
 }
 
 impl UserDomain {
-    pub fn new(storage: Box<dyn StorageTrait>) -> Self {
-        UserDomain { storage: storage }
+    pub fn new(
+        storage: Box<dyn StorageTrait>,
+        token_issuer: Box<dyn AccessTokenIssuer>,
+        notifier: Box<dyn Notifier>,
+        settings: UserDomainSettings,
+    ) -> Self {
+        UserDomain {
+            storage,
+            token_issuer,
+            notifier,
+            password_manager: PasswordManager::new(settings.password),
+            lockout_policy: LockoutPolicy::new(settings.lockout),
+            token_manager: TokenManager::new(settings.token),
+            two_factor_manager: TwoFactorManager::new(settings.two_factor),
+            require_verified_email: settings.require_verified_email,
+            require_activation: settings.require_activation,
+        }
+    }
+
+    async fn find_organization(&self, uuid: &uuid::Uuid) -> UserDomainResult<Organization> {
+        match self.storage.get_organization_by_uuid(uuid).await? {
+            Some(organization) => Ok(organization),
+            None => Err(UserDomainError::not_found("Organization")),
+        }
+    }
+
+    async fn check_organization_name_available(
+        &self,
+        name: &str,
+        owner_id: i32,
+    ) -> UserDomainResult<()> {
+        let name = name.to_lowercase();
+        match self
+            .storage
+            .get_all_organizations()
+            .await?
+            .iter()
+            .any(|organization| {
+                organization.id != owner_id && organization.name.to_lowercase() == name
+            }) {
+            true => Err(UserDomainError::conflict("name")),
+            false => Ok(()),
+        }
+    }
+
+    fn check_pagination(pagination: &Pagination) -> UserDomainResult<()> {
+        let error = match (pagination.get_offset(), &pagination.cursor) {
+            (Some(_), _) => return Ok(()),
+            (None, Some(_)) => FieldError::new("cursor", "invalid", "Invalid cursor."),
+            (None, None) => FieldError::new("page", "invalid", "Page out of range."),
+        };
+        Err(UserDomainError::Validation {
+            errors: vec![error],
+        })
+    }
+
+    //Early conflict report, the storage constraints still guard concurrent writes.
+    async fn check_login_available(
+        &self,
+        organization: &Organization,
+        login: &str,
+        owner_id: i32,
+    ) -> UserDomainResult<()> {
+        match self
+            .storage
+            .get_user_by_login(organization.id, login)
+            .await?
+        {
+            Some(user) if user.id != owner_id => Err(UserDomainError::conflict("login")),
+            _ => Ok(()),
+        }
+    }
+
+    async fn check_email_available(
+        &self,
+        organization: &Organization,
+        email: &str,
+        owner_id: i32,
+    ) -> UserDomainResult<()> {
+        match self
+            .storage
+            .get_person_by_email(organization.id, email)
+            .await?
+        {
+            Some(person) if person.id != owner_id => Err(UserDomainError::conflict("email")),
+            _ => Ok(()),
+        }
+    }
+
+    async fn find_user(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<AppUser> {
+        match self.storage.get_user(organization.id, uuid, false).await? {
+            Some(user) => Ok(user),
+            None => Err(UserDomainError::not_found("User")),
+        }
+    }
+
+    //Save the new status on the user and in its history.
+    async fn save_status(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &mut AppUser,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_until: Option<DateTime<Utc>>,
+        changed_by: Option<uuid::Uuid>,
+    ) -> UserDomainResult<()> {
+        let change = StatusChange::new(user, status, reason, suspended_until, changed_by);
+        user.status = change.status;
+        user.status_reason = change.reason.clone();
+        user.suspended_until = change.suspended_until;
+        Ok(storage.change_user_status(organization.id, change).await?)
+    }
+
+    //Refuse users who may not log in, lift the suspensions that are over.
+    async fn check_status(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &mut AppUser,
+    ) -> UserDomainResult<()> {
+        if !user.is_active(Utc::now()) {
+            return Err(UserDomainError::AccountInactive {
+                status: user.status,
+                until: user.suspended_until,
+            });
+        }
+
+        if user.status == AccountStatus::Suspended {
+            UserDomain::save_status(
+                storage,
+                organization,
+                user,
+                AccountStatus::Active,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    //Append a mutation to the audit log, through the storage of the transaction writing it.
+    async fn write_audit(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        context: &AuditContext,
+        action: AuditAction,
+        target: AuditTarget,
+        target_uuid: Option<uuid::Uuid>,
+        changes: Vec<FieldChange>,
+    ) -> UserDomainResult<()> {
+        let entry = AuditEntry::new(context, action, target, target_uuid, changes);
+        Ok(storage.add_audit_entry(organization.id, entry).await?)
+    }
+
+    //Commit the work of a transaction when it succeeded, roll it back otherwise.
+    async fn end_transaction<T>(
+        transaction: Box<dyn StorageTransaction>,
+        result: UserDomainResult<T>,
+    ) -> UserDomainResult<T> {
+        match result {
+            Ok(value) => {
+                transaction.commit().await?;
+                Ok(value)
+            }
+            Err(error) => {
+                //A failed rollback ends the connection, which rolls back as well.
+                transaction.rollback().await.ok();
+                Err(error)
+            }
+        }
+    }
+
+    //Rows of a new account, written through the storage of a transaction.
+    async fn save_account(
+        &self,
+        organization: &Organization,
+        storage: &dyn StorageTrait,
+        mut user: AppUser,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        user.person = storage.create_person(organization.id, user.person).await?;
+        let user = storage.create_user(organization.id, user).await?;
+
+        let person_entry = AuditEntry::new(
+            context,
+            AuditAction::Create,
+            AuditTarget::Person,
+            user.person.uuid,
+            FieldChange::diff(None, Some(&user.person)),
+        );
+        let mut changes = FieldChange::diff(None, Some(&user));
+        changes.push(FieldChange::added("person", user.person.uuid));
+        let user_entry = AuditEntry::new(
+            context,
+            AuditAction::Create,
+            AuditTarget::User,
+            user.uuid,
+            changes,
+        );
+        storage
+            .add_audit_entry(organization.id, person_entry)
+            .await?;
+        storage.add_audit_entry(organization.id, user_entry).await?;
+        Ok(user)
+    }
+
+    //Rows of a new organization with its admin, the person of the admin is returned.
+    async fn save_organization(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: Organization,
+        user: AppUser,
+        context: &AuditContext,
+    ) -> UserDomainResult<(Organization, Person)> {
+        let organization = storage.create_organization(organization).await?;
+        UserDomain::write_audit(
+            storage,
+            &organization,
+            context,
+            AuditAction::Create,
+            AuditTarget::Organization,
+            organization.uuid,
+            FieldChange::diff(None, Some(&organization)),
+        )
+        .await?;
+
+        let user = self
+            .save_account(&organization, storage, user, context)
+            .await?;
+        self.grant_admin(storage, &organization, &user.login, context)
+            .await?;
+        Ok((organization, user.person))
+    }
+
+    //Mark the user as deleted and end its sessions, it is purged later.
+    //Written through the storage of a transaction, with the rest of the deletion.
+    async fn soft_delete_user(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &AppUser,
+        deleted_on: DateTime<Utc>,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let mut deleted_user = user.clone();
+        deleted_user.deleted_on = Some(deleted_on);
+        deleted_user.deleted_by = context.actor;
+        storage
+            .update_user_deletion(
+                organization.id,
+                user.id,
+                deleted_user.deleted_on,
+                deleted_user.deleted_by,
+            )
+            .await?;
+        storage
+            .delete_user_sessions(organization.id, user.id)
+            .await?;
+        UserDomain::write_audit(
+            storage,
+            organization,
+            context,
+            AuditAction::Delete,
+            AuditTarget::User,
+            user.uuid,
+            FieldChange::diff(Some(user), Some(&deleted_user)),
+        )
+        .await
+    }
+
+    //The person and its users are deleted together, at the same date.
+    async fn soft_delete_person(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        person: &Person,
+        users: &[AppUser],
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let now = Utc::now();
+        for user in users {
+            UserDomain::soft_delete_user(storage, organization, user, now, context).await?;
+        }
+        let mut deleted_person = person.clone();
+        deleted_person.deleted_on = Some(now);
+        deleted_person.deleted_by = context.actor;
+        storage
+            .update_person_deletion(
+                organization.id,
+                person.id,
+                deleted_person.deleted_on,
+                deleted_person.deleted_by,
+            )
+            .await?;
+        UserDomain::write_audit(
+            storage,
+            organization,
+            context,
+            AuditAction::Delete,
+            AuditTarget::Person,
+            person.uuid,
+            FieldChange::diff(Some(person), Some(&deleted_person)),
+        )
+        .await
+    }
+
+    //New password of a reset, the token is used up with it.
+    async fn apply_password_reset(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        reset: &PasswordReset,
+        new_hash: String,
+        now: DateTime<Utc>,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        if !storage
+            .use_password_reset(organization.id, reset.id, now)
+            .await?
+        {
+            return Err(UserDomainError::InvalidToken);
+        }
+        let user = match storage
+            .get_user_by_id(organization.id, reset.user_id)
+            .await?
+        {
+            Some(user) => user,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+
+        storage
+            .update_user_password(organization.id, user.id, new_hash)
+            .await?;
+
+        //Whoever knew the old password is logged out, the owner gets its account back.
+        storage
+            .delete_user_sessions(organization.id, user.id)
+            .await?;
+        storage
+            .delete_user_password_resets(organization.id, user.id)
+            .await?;
+        storage
+            .delete_login_counter(
+                organization.id,
+                CounterScope::Login,
+                &user.login.to_lowercase(),
+            )
+            .await?;
+        //The token stands for its owner.
+        UserDomain::write_audit(
+            storage,
+            organization,
+            &context.or_actor(user.uuid),
+            AuditAction::ResetPassword,
+            AuditTarget::User,
+            user.uuid,
+            vec![FieldChange::redacted("password")],
+        )
+        .await
+    }
+
+    //Users first, their persons are kept as long as they are referenced.
+    async fn purge_organization(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        before: DateTime<Utc>,
+    ) -> UserDomainResult<u64> {
+        let users = storage.purge_deleted_users(organization.id, before).await?;
+        let persons = storage
+            .purge_deleted_persons(organization.id, before)
+            .await?;
+        if users + persons > 0 {
+            UserDomain::write_audit(
+                storage,
+                organization,
+                &AuditContext::default(),
+                AuditAction::Purge,
+                AuditTarget::Organization,
+                organization.uuid,
+                vec![
+                    FieldChange::removed("users", users),
+                    FieldChange::removed("persons", persons),
+                ],
+            )
+            .await?;
+        }
+        Ok(users + persons)
+    }
+
+    //Undo soft_delete_user, written through the storage of a transaction as well.
+    async fn undelete_user(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &mut AppUser,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let deleted_user = user.clone();
+        storage
+            .update_user_deletion(organization.id, user.id, None, None)
+            .await?;
+        user.deleted_on = None;
+        user.deleted_by = None;
+        UserDomain::write_audit(
+            storage,
+            organization,
+            context,
+            AuditAction::Restore,
+            AuditTarget::User,
+            user.uuid,
+            FieldChange::diff(Some(&deleted_user), Some(&*user)),
+        )
+        .await
+    }
+
+    async fn undelete_person(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        person: &mut Person,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let deleted_person = person.clone();
+        storage
+            .update_person_deletion(organization.id, person.id, None, None)
+            .await?;
+        person.deleted_on = None;
+        person.deleted_by = None;
+        UserDomain::write_audit(
+            storage,
+            organization,
+            context,
+            AuditAction::Restore,
+            AuditTarget::Person,
+            person.uuid,
+            FieldChange::diff(Some(&deleted_person), Some(&*person)),
+        )
+        .await
+    }
+
+    //Admin role of ensure_admin, written through the given storage.
+    async fn grant_admin(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        login: &str,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let mut user = match storage.get_user_by_login(organization.id, login).await? {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+
+        let name = ADMIN_ROLE.to_string();
+        let permissions = Permission::ALL.to_vec();
+        let role = match storage.get_role_by_name(organization.id, &name).await? {
+            //Permissions added since the role was created are granted too.
+            Some(mut role) if role.permissions != permissions => {
+                let before = role.clone();
+                role.permissions = permissions;
+                let role = storage.update_role(organization.id, role).await?;
+                UserDomain::write_audit(
+                    storage,
+                    organization,
+                    context,
+                    AuditAction::Update,
+                    AuditTarget::Role,
+                    role.uuid,
+                    FieldChange::diff(Some(&before), Some(&role)),
+                )
+                .await?;
+                role
+            }
+            Some(role) => role,
+            None => {
+                let role = Role::new(0, None, name, None, permissions, None, None);
+                let role = storage.create_role(organization.id, role).await?;
+                UserDomain::write_audit(
+                    storage,
+                    organization,
+                    context,
+                    AuditAction::Create,
+                    AuditTarget::Role,
+                    role.uuid,
+                    FieldChange::diff(None, Some(&role)),
+                )
+                .await?;
+                role
+            }
+        };
+
+        let roles = storage.get_user_roles(organization.id, user.id).await?;
+        if !roles.iter().any(|assigned| assigned.id == role.id) {
+            storage
+                .add_user_role(organization.id, user.id, role.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::AssignRole,
+                AuditTarget::User,
+                user.uuid,
+                vec![FieldChange::added("role", role.uuid)],
+            )
+            .await?;
+        }
+
+        if user.status == AccountStatus::Pending {
+            UserDomain::save_status(
+                storage,
+                organization,
+                &mut user,
+                AccountStatus::Active,
+                None,
+                None,
+                context.actor,
+            )
+            .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::ChangeStatus,
+                AuditTarget::User,
+                user.uuid,
+                vec![FieldChange::new(
+                    "status",
+                    serde_json::json!(AccountStatus::Pending),
+                    serde_json::json!(user.status),
+                )],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_person_users(
+        &self,
+        organization: &Organization,
+        person: &Person,
+        include_deleted: bool,
+    ) -> UserDomainResult<Vec<AppUser>> {
+        let query = UserQuery {
+            email: Some(person.email.clone()),
+            include_deleted,
+            limit: Some(MAX_LIMIT),
+            ..UserQuery::default()
+        };
+        Ok(self
+            .storage
+            .get_all_users(organization.id, &query)
+            .await?
+            .items
+            .into_iter()
+            .filter(|user| user.person.id == person.id)
+            .collect())
+    }
+
+    //By login first, then by the email of its person.
+    async fn find_user_by_login_or_email(
+        &self,
+        organization: &Organization,
+        value: &str,
+    ) -> UserDomainResult<Option<AppUser>> {
+        if let Some(user) = self
+            .storage
+            .get_user_by_login(organization.id, value)
+            .await?
+        {
+            return Ok(Some(user));
+        }
+
+        let person = match self
+            .storage
+            .get_person_by_email(organization.id, value)
+            .await?
+        {
+            Some(person) => person,
+            None => return Ok(None),
+        };
+        let query = UserQuery {
+            email: Some(person.email.clone()),
+            limit: Some(MAX_LIMIT),
+            ..UserQuery::default()
+        };
+        Ok(self
+            .storage
+            .get_all_users(organization.id, &query)
+            .await?
+            .items
+            .into_iter()
+            .find(|user| user.person.id == person.id))
+    }
+
+    //Replace the tokens of the person by a new one, sent to its current email.
+    async fn issue_email_verification(
+        &self,
+        organization: &Organization,
+        person: &Person,
+    ) -> UserDomainResult<()> {
+        let transaction = self.storage.begin().await?;
+        let result = self
+            .save_email_verification(transaction.as_storage(), organization, person)
+            .await;
+        let notification = UserDomain::end_transaction(transaction, result).await?;
+        self.notifier.send(notification).await
+    }
+
+    //Rows of issue_email_verification, the notification is sent once they are committed.
+    async fn save_email_verification(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        person: &Person,
+    ) -> UserDomainResult<Notification> {
+        let now = Utc::now();
+        storage
+            .delete_expired_email_verifications(organization.id, now)
+            .await?;
+        storage
+            .delete_person_email_verifications(organization.id, person.id)
+            .await?;
+
+        let token = self.token_manager.generate();
+        let expires_on = self.token_manager.get_verification_expires_on(now);
+        let verification = EmailVerification::new(
+            person.id,
+            person.email.clone(),
+            self.token_manager.hash(&token),
+            expires_on,
+        );
+        storage
+            .create_email_verification(organization.id, verification)
+            .await?;
+
+        Ok(Notification {
+            kind: NotificationKind::EmailVerification,
+            organization_uuid: organization.uuid,
+            login: None,
+            email: person.email.clone(),
+            token,
+            expires_on,
+        })
+    }
+
+    async fn find_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Role> {
+        match self.storage.get_role_by_uuid(organization.id, uuid).await? {
+            Some(role) => Ok(role),
+            None => Err(UserDomainError::not_found("Role")),
+        }
+    }
+
+    async fn check_role_name_available(
+        &self,
+        organization: &Organization,
+        name: &str,
+        owner_id: i32,
+    ) -> UserDomainResult<()> {
+        match self.storage.get_role_by_name(organization.id, name).await? {
+            Some(role) if role.id != owner_id => Err(UserDomainError::conflict("name")),
+            _ => Ok(()),
+        }
+    }
+
+    async fn find_group(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Group> {
+        match self
+            .storage
+            .get_group_by_uuid(organization.id, uuid)
+            .await?
+        {
+            Some(group) => Ok(group),
+            None => Err(UserDomainError::not_found("Group")),
+        }
+    }
+
+    async fn check_group_name_available(
+        &self,
+        organization: &Organization,
+        name: &str,
+        owner_id: i32,
+    ) -> UserDomainResult<()> {
+        let name = name.to_lowercase();
+        match self
+            .storage
+            .get_all_groups(organization.id)
+            .await?
+            .iter()
+            .any(|group| group.id != owner_id && group.name.to_lowercase() == name)
+        {
+            true => Err(UserDomainError::conflict("name")),
+            false => Ok(()),
+        }
+    }
+
+    //Parent of a created or moved group, which cannot be one of its subgroups.
+    async fn find_parent_group(
+        &self,
+        organization: &Organization,
+        parent_uuid: &Option<uuid::Uuid>,
+        group_id: i32,
+    ) -> UserDomainResult<Option<Group>> {
+        let parent = match parent_uuid {
+            Some(parent_uuid) => self.find_group(organization, parent_uuid).await?,
+            None => return Ok(None),
+        };
+
+        let all_groups = self.storage.get_all_groups(organization.id).await?;
+        let mut ancestor_id = Some(parent.id);
+        while let Some(id) = ancestor_id {
+            if id == group_id {
+                return Err(UserDomainError::Validation {
+                    errors: vec![FieldError::new(
+                        "parent_uuid",
+                        "invalid",
+                        "A group cannot be nested in one of its subgroups.",
+                    )],
+                });
+            }
+            ancestor_id = all_groups
+                .iter()
+                .find(|group| group.id == id)
+                .and_then(|group| group.parent_id);
+        }
+
+        Ok(Some(parent))
+    }
+
+    //Joined groups and all their parents, sorted by name.
+    async fn get_effective_groups(
+        &self,
+        organization: &Organization,
+        user_id: i32,
+    ) -> UserDomainResult<Vec<Group>> {
+        let all_groups = self.storage.get_all_groups(organization.id).await?;
+        let mut pending: Vec<i32> = self
+            .storage
+            .get_user_groups(organization.id, user_id)
+            .await?
+            .iter()
+            .map(|group| group.id)
+            .collect();
+
+        let mut result: Vec<Group> = Vec::new();
+        while let Some(group_id) = pending.pop() {
+            if result.iter().any(|group| group.id == group_id) {
+                continue;
+            }
+            if let Some(group) = all_groups.iter().find(|group| group.id == group_id) {
+                pending.extend(group.parent_id);
+                result.push(group.clone());
+            }
+        }
+
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    //Sign an access token and remember its digest on the session.
+    fn bind_access_token(
+        &self,
+        user: &AppUser,
+        session: &mut Session,
+        now: DateTime<Utc>,
+    ) -> UserDomainResult<String> {
+        let access_token = self.token_issuer.issue(user)?;
+        session.access_token_hash = Some(self.token_manager.hash(&access_token));
+        session.access_expires_on = Some(self.token_manager.get_access_expires_on(now));
+        Ok(access_token)
+    }
+
+    //Store a new token of the session, return the value for the client.
+    async fn add_refresh_token(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user_id: i32,
+        session_uuid: uuid::Uuid,
+        expires_on: DateTime<Utc>,
+    ) -> UserDomainResult<String> {
+        let token = self.token_manager.generate();
+        let refresh_token = RefreshToken::new(
+            user_id,
+            session_uuid,
+            self.token_manager.hash(&token),
+            expires_on,
+        );
+        storage
+            .add_refresh_token(organization.id, refresh_token)
+            .await?;
+        Ok(token)
+    }
+
+    //New session of the user with its first tokens, written through the storage of a transaction.
+    async fn save_session(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &AppUser,
+        context: &LoginContext,
+        device: Option<String>,
+    ) -> UserDomainResult<TokenPair> {
+        let now = Utc::now();
+        storage
+            .delete_expired_sessions(organization.id, now)
+            .await?;
+
+        //Labels are informative, overly long ones are cut.
+        let device = device
+            .map(|device| {
+                device
+                    .trim()
+                    .chars()
+                    .take(DEVICE_MAX_LENGTH)
+                    .collect::<String>()
+            })
+            .filter(|device| !device.is_empty());
+        let expires_on = self.token_manager.get_expires_on(now);
+        let mut session = Session::new(user.id, device, context, expires_on);
+        let access_token = self.bind_access_token(user, &mut session, now)?;
+        let session = storage.create_session(organization.id, session).await?;
+
+        let session_uuid = session
+            .uuid
+            .ok_or_else(|| UserDomainError::missing_uuid("Session"))?;
+        let refresh_token = self
+            .add_refresh_token(storage, organization, user.id, session_uuid, expires_on)
+            .await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+        })
+    }
+
+    //Refuse attempts on a locked account or from a throttled IP,
+    //return the current counters of both.
+    async fn check_lockout(
+        &self,
+        organization: &Organization,
+        login_key: &str,
+        context: &LoginContext,
+    ) -> UserDomainResult<(Option<LoginCounter>, Option<LoginCounter>)> {
+        let now = Utc::now();
+        let ip_counter = match &context.ip {
+            Some(ip) => {
+                self.storage
+                    .get_login_counter(organization.id, CounterScope::Ip, ip)
+                    .await?
+            }
+            None => None,
+        };
+        if let Some(retry_after) = ip_counter
+            .as_ref()
+            .and_then(|counter| counter.locked_until)
+            .filter(|until| *until > now)
+        {
+            return Err(UserDomainError::TooManyAttempts { retry_after });
+        }
+
+        let login_counter = self
+            .storage
+            .get_login_counter(organization.id, CounterScope::Login, login_key)
+            .await?;
+        if let Some(counter) = login_counter
+            .as_ref()
+            .filter(|counter| counter.is_locked(now))
+        {
+            return Err(UserDomainError::AccountLocked {
+                until: counter.locked_until,
+            });
+        }
+
+        Ok((login_counter, ip_counter))
+    }
+
+    //Clear the failed attempts and record the login.
+    async fn register_success(
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        user: &mut AppUser,
+        login_key: &str,
+        context: &LoginContext,
+        login_counter: Option<&LoginCounter>,
+    ) -> UserDomainResult<()> {
+        if login_counter.is_some() {
+            storage
+                .delete_login_counter(organization.id, CounterScope::Login, login_key)
+                .await?;
+        }
+
+        let event = LoginEvent::new(Some(user.id), user.login.clone(), context, true);
+        user.last_login_on = Some(event.occurred_on);
+        storage
+            .update_last_login(organization.id, user.id, event.occurred_on)
+            .await?;
+        storage.add_login_event(organization.id, event).await?;
+        Ok(())
+    }
+
+    async fn get_enabled_two_factor(
+        &self,
+        organization: &Organization,
+        user_id: i32,
+    ) -> UserDomainResult<Option<TwoFactor>> {
+        Ok(self
+            .storage
+            .get_two_factor(organization.id, user_id)
+            .await?
+            .filter(|two_factor| two_factor.is_enabled()))
+    }
+
+    //TOTP first, a recovery code otherwise. Accepted codes cannot be used again.
+    async fn check_second_factor(
+        &self,
+        storage: &dyn StorageTrait,
+        organization: &Organization,
+        mut two_factor: TwoFactor,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> UserDomainResult<bool> {
+        if let Some(step) =
+            self.two_factor_manager
+                .verify(&two_factor.secret, code, now, two_factor.last_used_step)
+        {
+            two_factor.last_used_step = Some(step);
+            storage.save_two_factor(organization.id, two_factor).await?;
+            return Ok(true);
+        }
+
+        let code_hash = self
+            .token_manager
+            .hash(&self.two_factor_manager.normalize_recovery_code(code));
+        Ok(storage
+            .use_recovery_code(organization.id, two_factor.user_id, &code_hash, now)
+            .await?)
+    }
+
+    //Record a failed login and count it on both the login and the client IP,
+    //return the error to report. Committed on its own, the login itself is refused.
+    async fn register_failure(
+        &self,
+        organization: &Organization,
+        user_id: Option<i32>,
+        login: &str,
+        context: &LoginContext,
+        login_counter: Option<LoginCounter>,
+        ip_counter: Option<LoginCounter>,
+    ) -> UserDomainError {
+        let now = Utc::now();
+        let login_counter = self.lockout_policy.register_failure(
+            login_counter.unwrap_or(LoginCounter::new(CounterScope::Login, login.to_lowercase())),
+            self.lockout_policy.get_max_attempts(),
+            now,
+        );
+        //The IP is only counted against while the login is not locked.
+        let ip_counter = match &context.ip {
+            Some(ip) if !login_counter.is_locked(now) => {
+                Some(self.lockout_policy.register_failure(
+                    ip_counter.unwrap_or(LoginCounter::new(CounterScope::Ip, ip.clone())),
+                    self.lockout_policy.get_ip_max_attempts(),
+                    now,
+                ))
+            }
+            _ => None,
+        };
+
+        let transaction = match self.storage.begin().await {
+            Ok(transaction) => transaction,
+            Err(error) => return error.into(),
+        };
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<()> = async {
+            let event = LoginEvent::new(user_id, login.to_string(), context, false);
+            storage.add_login_event(organization.id, event).await?;
+            storage
+                .save_login_counter(organization.id, login_counter.clone())
+                .await?;
+            if let Some(ip_counter) = &ip_counter {
+                storage
+                    .save_login_counter(organization.id, ip_counter.clone())
+                    .await?;
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(error) = UserDomain::end_transaction(transaction, result).await {
+            return error;
+        }
+
+        if login_counter.is_locked(now) {
+            return UserDomainError::AccountLocked {
+                until: login_counter.locked_until,
+            };
+        }
+        if let Some(retry_after) = ip_counter
+            .and_then(|ip_counter| ip_counter.locked_until)
+            .filter(|until| *until > now)
+        {
+            return UserDomainError::TooManyAttempts { retry_after };
+        }
+        UserDomainError::InvalidCredentials
     }
 }
 
 #[async_trait]
 impl UserDomainTrait for UserDomain {
-    fn generate_user_auth_key(&self, login: &String, password: &String) -> String {
-        let salt: &str = "__H3l!X__";
+    async fn resolve_organization(
+        &self,
+        uuid: Option<uuid::Uuid>,
+    ) -> UserDomainResult<Organization> {
+        self.find_organization(&uuid.unwrap_or(Organization::DEFAULT_UUID))
+            .await
+    }
+
+    async fn get_all_organizations(&self) -> UserDomainResult<Vec<Organization>> {
+        Ok(self.storage.get_all_organizations().await?)
+    }
+
+    async fn get_organization(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<Organization>> {
+        Ok(self.storage.get_organization_by_uuid(uuid).await?)
+    }
 
-        //Hash construct
-        let mut to_hash: String = String::new();
-        to_hash.push_str(&login);
-        to_hash.push_str(&password);
-        to_hash.push_str(&salt.to_owned());
+    async fn create_organization(
+        &self,
+        command: CreateOrganizationCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Organization> {
+        validation::check(&command)?;
+        self.check_organization_name_available(&command.name, 0)
+            .await?;
 
-        let mut hasher = Sha256::new();
-        hasher.input_str(&to_hash);
+        let admin = command.admin;
+        let mut person = Person::new(
+            0,
+            None,
+            admin.firstname,
+            admin.lastname,
+            admin.email,
+            admin.phone,
+            None,
+            None,
+        );
+        person.email_status = EmailStatus::Pending;
+        let mut user = AppUser::new(
+            0,
+            None,
+            admin.login,
+            self.password_manager.hash(&admin.password)?,
+            None,
+            None,
+            None,
+            None,
+            person,
+        );
+        if self.require_activation {
+            user.status = AccountStatus::Pending;
+        }
 
-        //Key construct.
-        let mut key: String = String::new();
-        key.push_str(&login);
-        key.push_str(&":".to_owned());
-        key.push_str(&hasher.result_str());
+        //An organization is never left without its admin.
+        let transaction = self.storage.begin().await?;
+        let organization = Organization::new(0, None, command.name, None, None);
+        let result = self
+            .save_organization(transaction.as_storage(), organization, user, context)
+            .await;
+        let (organization, person) = UserDomain::end_transaction(transaction, result).await?;
 
-        //return
-        key
+        self.issue_email_verification(&organization, &person)
+            .await?;
+        Ok(organization)
     }
-    async fn login(&self, login: &String, password: &String) -> UserDomainResult<AppUser> {
-        match self
+
+    async fn update_organization(
+        &self,
+        command: UpdateOrganizationCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Organization> {
+        validation::check(&command)?;
+        let mut organization = self.find_organization(&command.uuid).await?;
+        self.check_organization_name_available(&command.name, organization.id)
+            .await?;
+
+        let before = organization.clone();
+        organization.name = command.name;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Organization> = async {
+            let organization = storage.update_organization(organization).await?;
+            UserDomain::write_audit(
+                storage,
+                &organization,
+                context,
+                AuditAction::Update,
+                AuditTarget::Organization,
+                organization.uuid,
+                FieldChange::diff(Some(&before), Some(&organization)),
+            )
+            .await?;
+            Ok(organization)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn login(
+        &self,
+        organization: &Organization,
+        login: &str,
+        password: &str,
+        context: &LoginContext,
+    ) -> UserDomainResult<AppUser> {
+        let login_key = login.to_lowercase();
+        let (login_counter, ip_counter) = self
+            .check_lockout(organization, &login_key, context)
+            .await?;
+
+        //Unknown logins are counted too, locking does not reveal which accounts exist.
+        let mut user = match self
+            .storage
+            .get_user_by_login(organization.id, login)
+            .await?
+        {
+            Some(user) => user,
+            None => {
+                return Err(self
+                    .register_failure(
+                        organization,
+                        None,
+                        login,
+                        context,
+                        login_counter,
+                        ip_counter,
+                    )
+                    .await);
+            }
+        };
+
+        let new_hash = match self
+            .password_manager
+            .verify(login, password, &user.password)
+        {
+            PasswordCheck::Invalid => {
+                return Err(self
+                    .register_failure(
+                        organization,
+                        Some(user.id),
+                        login,
+                        context,
+                        login_counter,
+                        ip_counter,
+                    )
+                    .await);
+            }
+            PasswordCheck::Valid => None,
+            //Transparent migration of legacy keys and outdated parameters.
+            PasswordCheck::ValidNeedsRehash => Some(self.password_manager.hash(password)?),
+        };
+
+        //Only told once the password is checked.
+        if self.require_verified_email && !user.person.is_email_verified() {
+            return Err(UserDomainError::EmailNotVerified);
+        }
+        let two_factor = self.get_enabled_two_factor(organization, user.id).await?;
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<()> = async {
+            if let Some(new_hash) = new_hash {
+                storage
+                    .update_user_password(organization.id, user.id, new_hash)
+                    .await?;
+            }
+            UserDomain::check_status(storage, organization, &mut user).await?;
+
+            //With a second factor, the login only succeeds once its code is checked.
+            if two_factor.is_none() {
+                UserDomain::register_success(
+                    storage,
+                    organization,
+                    &mut user,
+                    &login_key,
+                    context,
+                    login_counter.as_ref(),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?;
+
+        //Do not restitute password
+        user.password = "".to_string();
+        Ok(user)
+    }
+
+    async fn open_session(
+        &self,
+        organization: &Organization,
+        user: &AppUser,
+        context: &LoginContext,
+        device: Option<String>,
+    ) -> UserDomainResult<TokenPair> {
+        let transaction = self.storage.begin().await?;
+        let result = self
+            .save_session(
+                transaction.as_storage(),
+                organization,
+                user,
+                context,
+                device,
+            )
+            .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn start_session(
+        &self,
+        organization: &Organization,
+        user: &AppUser,
+        context: &LoginContext,
+        device: Option<String>,
+    ) -> UserDomainResult<LoginStep> {
+        if self
+            .get_enabled_two_factor(organization, user.id)
+            .await?
+            .is_none()
+        {
+            let tokens = self
+                .open_session(organization, user, context, device)
+                .await?;
+            return Ok(LoginStep::Tokens(tokens));
+        }
+
+        let now = Utc::now();
+        let challenge_token = self.token_manager.generate();
+        let expires_on = self.two_factor_manager.get_challenge_expires_on(now);
+        let challenge = LoginChallenge::new(
+            user.id,
+            self.token_manager.hash(&challenge_token),
+            device,
+            expires_on,
+        );
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<()> = async {
+            storage
+                .delete_expired_login_challenges(organization.id, now)
+                .await?;
+            storage
+                .create_login_challenge(organization.id, challenge)
+                .await?;
+            Ok(())
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?;
+        Ok(LoginStep::Challenge(TwoFactorChallenge {
+            challenge_token,
+            expires_on,
+        }))
+    }
+
+    async fn verify_login(
+        &self,
+        organization: &Organization,
+        command: VerifyLoginCommand,
+        context: &LoginContext,
+    ) -> UserDomainResult<TokenPair> {
+        let now = Utc::now();
+        let token_hash = self.token_manager.hash(&command.challenge_token);
+        let challenge = match self
+            .storage
+            .get_login_challenge(organization.id, &token_hash)
+            .await?
+        {
+            Some(challenge) if !challenge.is_expired(now) => challenge,
+            _ => return Err(UserDomainError::InvalidToken),
+        };
+
+        //The second factor may have been reset since the password was checked.
+        let mut user = match self
+            .storage
+            .get_user_by_id(organization.id, challenge.user_id)
+            .await?
+        {
+            Some(user) => user,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+        let two_factor = match self.get_enabled_two_factor(organization, user.id).await? {
+            Some(two_factor) => two_factor,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+
+        let login_key = user.login.to_lowercase();
+        let (login_counter, ip_counter) = self
+            .check_lockout(organization, &login_key, context)
+            .await?;
+
+        //No tokens for a refused code, the failure is recorded once the transaction is over.
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Option<TokenPair>> = async {
+            UserDomain::check_status(storage, organization, &mut user).await?;
+            if !self
+                .check_second_factor(storage, organization, two_factor, &command.code, now)
+                .await?
+            {
+                return Ok(None);
+            }
+
+            storage
+                .delete_login_challenge(organization.id, challenge.id)
+                .await?;
+            UserDomain::register_success(
+                storage,
+                organization,
+                &mut user,
+                &login_key,
+                context,
+                login_counter.as_ref(),
+            )
+            .await?;
+            let tokens = self
+                .save_session(storage, organization, &user, context, challenge.device)
+                .await?;
+            Ok(Some(tokens))
+        }
+        .await;
+        match UserDomain::end_transaction(transaction, result).await? {
+            Some(tokens) => Ok(tokens),
+            None => Err(self
+                .register_failure(
+                    organization,
+                    Some(user.id),
+                    &user.login,
+                    context,
+                    login_counter,
+                    ip_counter,
+                )
+                .await),
+        }
+    }
+
+    async fn refresh(
+        &self,
+        organization: &Organization,
+        refresh_token: &str,
+    ) -> UserDomainResult<TokenPair> {
+        let now = Utc::now();
+        let token_hash = self.token_manager.hash(refresh_token);
+        let token = match self
+            .storage
+            .get_refresh_token(organization.id, &token_hash)
+            .await?
+        {
+            Some(token) => token,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+
+        //Refusals revoke the session, they are committed before being reported.
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<UserDomainResult<TokenPair>> = async {
+            //A rotated token showing up again has leaked, the whole family is revoked.
+            if token.rotated_on.is_some()
+                || token.is_expired(now)
+                || !storage
+                    .rotate_refresh_token(organization.id, token.id, now)
+                    .await?
+            {
+                storage
+                    .delete_session(organization.id, &token.family)
+                    .await?;
+                return Ok(Err(UserDomainError::InvalidToken));
+            }
+
+            let mut user = match storage
+                .get_user_by_id(organization.id, token.user_id)
+                .await?
+            {
+                Some(user) => user,
+                None => return Err(UserDomainError::InvalidToken),
+            };
+            if let Err(error) = UserDomain::check_status(storage, organization, &mut user).await {
+                storage
+                    .delete_session(organization.id, &token.family)
+                    .await?;
+                return Ok(Err(error));
+            }
+            let mut session = match storage.get_session(organization.id, &token.family).await? {
+                Some(session) => session,
+                None => return Err(UserDomainError::InvalidToken),
+            };
+
+            let access_token = self.bind_access_token(&user, &mut session, now)?;
+            session.refreshed_on = Some(now);
+            session.expires_on = self.token_manager.get_expires_on(now);
+            let expires_on = session.expires_on;
+            storage.update_session(organization.id, session).await?;
+
+            let refresh_token = self
+                .add_refresh_token(storage, organization, user.id, token.family, expires_on)
+                .await?;
+            Ok(Ok(TokenPair {
+                access_token,
+                refresh_token,
+            }))
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?
+    }
+
+    async fn authenticate(&self, access_token: &str) -> UserDomainResult<Actor> {
+        let now = Utc::now();
+        let access_token_hash = self.token_manager.hash(access_token);
+        let session = match self
+            .storage
+            .get_session_by_access_token(&access_token_hash)
+            .await?
+        {
+            Some(session) if session.access_expires_on.is_some_and(|until| until > now) => session,
+            _ => return Err(UserDomainError::InvalidToken),
+        };
+
+        let organization = match self
+            .storage
+            .get_organization_by_id(session.organization_id)
+            .await?
+        {
+            Some(organization) => organization,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+        let organization = &organization;
+        let user = match self
+            .storage
+            .get_user_by_id(organization.id, session.user_id)
+            .await?
+        {
+            //Sessions are ended with the account, this also catches one opened meanwhile.
+            Some(user) if !user.is_deleted() && user.is_active(now) => user,
+            _ => return Err(UserDomainError::InvalidToken),
+        };
+
+        let groups = self.get_effective_groups(organization, user.id).await?;
+        let mut roles = self
+            .storage
+            .get_user_roles(organization.id, user.id)
+            .await?;
+        for group in &groups {
+            roles.extend(
+                self.storage
+                    .get_group_roles(organization.id, group.id)
+                    .await?,
+            );
+        }
+
+        let mut permissions: Vec<Permission> = Vec::new();
+        for role in roles {
+            for permission in role.permissions {
+                if !permissions.contains(&permission) {
+                    permissions.push(permission);
+                }
+            }
+        }
+
+        Ok(Actor {
+            organization: organization.clone(),
+            user_uuid: user
+                .uuid
+                .ok_or_else(|| UserDomainError::missing_uuid("User"))?,
+            person_uuid: user
+                .person
+                .uuid
+                .ok_or_else(|| UserDomainError::missing_uuid("Person"))?,
+            permissions,
+            groups: groups.into_iter().filter_map(|group| group.uuid).collect(),
+        })
+    }
+
+    async fn logout(
+        &self,
+        organization: &Organization,
+        refresh_token: &str,
+    ) -> UserDomainResult<()> {
+        let token_hash = self.token_manager.hash(refresh_token);
+        if let Some(token) = self
+            .storage
+            .get_refresh_token(organization.id, &token_hash)
+            .await?
+        {
+            self.storage
+                .delete_session(organization.id, &token.family)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_user_sessions(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Session>> {
+        let user = self.find_user(organization, uuid).await?;
+        Ok(self
+            .storage
+            .get_user_sessions(organization.id, user.id, Utc::now())
+            .await?)
+    }
+
+    async fn delete_user_session(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        session_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = self.find_user(organization, uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            match storage.get_session(organization.id, session_uuid).await? {
+                Some(session) if session.user_id == user.id => {
+                    storage
+                        .delete_session(organization.id, session_uuid)
+                        .await?
+                }
+                _ => return Err(UserDomainError::not_found("Session")),
+            }
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::RevokeSessions,
+                AuditTarget::User,
+                user.uuid,
+                vec![FieldChange::removed("session", session_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn revoke_user_sessions(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = self.find_user(organization, uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .delete_user_sessions(organization.id, user.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::RevokeSessions,
+                AuditTarget::User,
+                user.uuid,
+                Vec::new(),
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_all_roles(&self, organization: &Organization) -> UserDomainResult<Vec<Role>> {
+        Ok(self.storage.get_all_roles(organization.id).await?)
+    }
+
+    async fn get_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Option<Role>> {
+        Ok(self.storage.get_role_by_uuid(organization.id, uuid).await?)
+    }
+
+    async fn create_role(
+        &self,
+        organization: &Organization,
+        command: CreateRoleCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Role> {
+        validation::check(&command)?;
+        self.check_role_name_available(organization, &command.name, 0)
+            .await?;
+
+        let mut permissions = command.permissions;
+        permissions.sort();
+        permissions.dedup();
+        let role = Role::new(
+            0,
+            None,
+            command.name,
+            command.description,
+            permissions,
+            None,
+            None,
+        );
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Role> = async {
+            let role = storage.create_role(organization.id, role).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Create,
+                AuditTarget::Role,
+                role.uuid,
+                FieldChange::diff(None, Some(&role)),
+            )
+            .await?;
+            Ok(role)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn update_role(
+        &self,
+        organization: &Organization,
+        command: UpdateRoleCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Role> {
+        validation::check(&command)?;
+        let mut role = self.find_role(organization, &command.uuid).await?;
+        self.check_role_name_available(organization, &command.name, role.id)
+            .await?;
+
+        let before = role.clone();
+        role.name = command.name;
+        role.description = command.description;
+        role.permissions = command.permissions;
+        role.permissions.sort();
+        role.permissions.dedup();
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Role> = async {
+            let role = storage.update_role(organization.id, role).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Update,
+                AuditTarget::Role,
+                role.uuid,
+                FieldChange::diff(Some(&before), Some(&role)),
+            )
+            .await?;
+            Ok(role)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn delete_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let role = self.find_role(organization, uuid).await?;
+        let changes = FieldChange::diff(Some(&role), None);
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage.delete_role(organization.id, role).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Delete,
+                AuditTarget::Role,
+                Some(*uuid),
+                changes,
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_user_roles(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Role>> {
+        let user = self.find_user(organization, uuid).await?;
+        Ok(self
             .storage
-            .login(self.generate_user_auth_key(login, password))
+            .get_user_roles(organization.id, user.id)
+            .await?)
+    }
+
+    async fn assign_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = self.find_user(organization, uuid).await?;
+        let role = self.find_role(organization, role_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .add_user_role(organization.id, user.id, role.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::AssignRole,
+                AuditTarget::User,
+                user.uuid,
+                vec![FieldChange::added("role", role_uuid)],
+            )
             .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn unassign_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = self.find_user(organization, uuid).await?;
+        let role = self.find_role(organization, role_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .remove_user_role(organization.id, user.id, role.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::UnassignRole,
+                AuditTarget::User,
+                user.uuid,
+                vec![FieldChange::removed("role", role_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_all_groups(&self, organization: &Organization) -> UserDomainResult<Vec<Group>> {
+        Ok(self.storage.get_all_groups(organization.id).await?)
+    }
+
+    async fn get_group(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Option<Group>> {
+        Ok(self
+            .storage
+            .get_group_by_uuid(organization.id, uuid)
+            .await?)
+    }
+
+    async fn create_group(
+        &self,
+        organization: &Organization,
+        command: CreateGroupCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Group> {
+        validation::check(&command)?;
+        self.check_group_name_available(organization, &command.name, 0)
+            .await?;
+        let parent = self
+            .find_parent_group(organization, &command.parent_uuid, 0)
+            .await?;
+
+        let group = Group::new(
+            0,
+            None,
+            command.name,
+            command.description,
+            parent.as_ref().map(|parent| parent.id),
+            parent.and_then(|parent| parent.uuid),
+            None,
+            None,
+        );
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Group> = async {
+            let group = storage.create_group(organization.id, group).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Create,
+                AuditTarget::Group,
+                group.uuid,
+                FieldChange::diff(None, Some(&group)),
+            )
+            .await?;
+            Ok(group)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn update_group(
+        &self,
+        organization: &Organization,
+        command: UpdateGroupCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Group> {
+        validation::check(&command)?;
+        let mut group = self.find_group(organization, &command.uuid).await?;
+        self.check_group_name_available(organization, &command.name, group.id)
+            .await?;
+        let parent = self
+            .find_parent_group(organization, &command.parent_uuid, group.id)
+            .await?;
+
+        let before = group.clone();
+        group.name = command.name;
+        group.description = command.description;
+        group.parent_id = parent.as_ref().map(|parent| parent.id);
+        group.parent_uuid = parent.and_then(|parent| parent.uuid);
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Group> = async {
+            let group = storage.update_group(organization.id, group).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Update,
+                AuditTarget::Group,
+                group.uuid,
+                FieldChange::diff(Some(&before), Some(&group)),
+            )
+            .await?;
+            Ok(group)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn delete_group(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let group = self.find_group(organization, uuid).await?;
+        let changes = FieldChange::diff(Some(&group), None);
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage.delete_group(organization.id, group).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Delete,
+                AuditTarget::Group,
+                Some(*uuid),
+                changes,
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_group_members(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<AppUser>> {
+        let group = self.find_group(organization, uuid).await?;
+        Ok(self
+            .storage
+            .get_group_members(organization.id, group.id)
+            .await?)
+    }
+
+    async fn add_group_member(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        user_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let group = self.find_group(organization, uuid).await?;
+        let user = self.find_user(organization, user_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .add_group_member(organization.id, group.id, user.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::AddMember,
+                AuditTarget::Group,
+                group.uuid,
+                vec![FieldChange::added("member", user_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn remove_group_member(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        user_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let group = self.find_group(organization, uuid).await?;
+        let user = self.find_user(organization, user_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .remove_group_member(organization.id, group.id, user.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::RemoveMember,
+                AuditTarget::Group,
+                group.uuid,
+                vec![FieldChange::removed("member", user_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_group_roles(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Role>> {
+        let group = self.find_group(organization, uuid).await?;
+        Ok(self
+            .storage
+            .get_group_roles(organization.id, group.id)
+            .await?)
+    }
+
+    async fn assign_group_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let group = self.find_group(organization, uuid).await?;
+        let role = self.find_role(organization, role_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .add_group_role(organization.id, group.id, role.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::AssignRole,
+                AuditTarget::Group,
+                group.uuid,
+                vec![FieldChange::added("role", role_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn unassign_group_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let group = self.find_group(organization, uuid).await?;
+        let role = self.find_role(organization, role_uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .remove_group_role(organization.id, group.id, role.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::UnassignRole,
+                AuditTarget::Group,
+                group.uuid,
+                vec![FieldChange::removed("role", role_uuid)],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_user_groups(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Group>> {
+        let user = self.find_user(organization, uuid).await?;
+        self.get_effective_groups(organization, user.id).await
+    }
+
+    async fn get_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<TwoFactorStatus> {
+        let user = self.find_user(organization, uuid).await?;
+        let two_factor = self.get_enabled_two_factor(organization, user.id).await?;
+        let recovery_codes_left = match two_factor {
+            Some(_) => self
+                .storage
+                .get_recovery_codes(organization.id, user.id)
+                .await?
+                .iter()
+                .filter(|code| code.used_on.is_none())
+                .count(),
+            None => 0,
+        };
+
+        Ok(TwoFactorStatus {
+            enabled: two_factor.is_some(),
+            confirmed_on: two_factor.and_then(|two_factor| two_factor.confirmed_on),
+            recovery_codes_left,
+        })
+    }
+
+    async fn enroll_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<TwoFactorSetup> {
+        let user = self.find_user(organization, uuid).await?;
+        if self
+            .get_enabled_two_factor(organization, user.id)
+            .await?
+            .is_some()
+        {
+            return Err(UserDomainError::conflict("two_factor"));
+        }
+
+        let secret = self.two_factor_manager.generate_secret();
+        self.storage
+            .save_two_factor(organization.id, TwoFactor::new(user.id, secret.clone()))
+            .await?;
+
+        let otpauth_uri = self.two_factor_manager.get_uri(&user.login, &secret);
+        Ok(TwoFactorSetup {
+            qr_code: self.two_factor_manager.render_qr_code(&otpauth_uri)?,
+            secret,
+            otpauth_uri,
+        })
+    }
+
+    async fn confirm_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ConfirmTwoFactorCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<RecoveryCodes> {
+        validation::check(&command)?;
+        let user = self.find_user(organization, uuid).await?;
+        let mut two_factor = match self
+            .storage
+            .get_two_factor(organization.id, user.id)
+            .await?
         {
-            Ok(wrap_user) => match wrap_user {
-                Some(user) => Ok(user),
-                //User not found
+            Some(two_factor) if two_factor.is_enabled() => {
+                return Err(UserDomainError::conflict("two_factor"))
+            }
+            Some(two_factor) => two_factor,
+            None => return Err(UserDomainError::not_found("Two factor enrollment")),
+        };
+
+        let now = Utc::now();
+        let step =
+            match self
+                .two_factor_manager
+                .verify(&two_factor.secret, &command.code, now, None)
+            {
+                Some(step) => step,
                 None => {
-                    println!("User not found");
-                    Err(UserDomainError::NotImplemented)
+                    return Err(UserDomainError::Validation {
+                        errors: vec![FieldError::new("code", "invalid", "Invalid code.")],
+                    })
                 }
-            },
-            //Error with backend
-            Err(_) => {
-                println!("Err with backend");
-                Err(UserDomainError::NotImplemented)
+            };
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<RecoveryCodes> = async {
+            two_factor.confirmed_on = Some(now);
+            two_factor.last_used_step = Some(step);
+            storage.save_two_factor(organization.id, two_factor).await?;
+
+            let recovery_codes = self.two_factor_manager.generate_recovery_codes();
+            let code_hashes = recovery_codes
+                .iter()
+                .map(|code| {
+                    self.token_manager
+                        .hash(&self.two_factor_manager.normalize_recovery_code(code))
+                })
+                .collect();
+            storage
+                .replace_recovery_codes(organization.id, user.id, code_hashes)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::EnableTwoFactor,
+                AuditTarget::User,
+                user.uuid,
+                Vec::new(),
+            )
+            .await?;
+
+            Ok(RecoveryCodes { recovery_codes })
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn reset_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = self.find_user(organization, uuid).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage.delete_two_factor(organization.id, user.id).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::ResetTwoFactor,
+                AuditTarget::User,
+                user.uuid,
+                Vec::new(),
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn ensure_admin(
+        &self,
+        organization: &Organization,
+        login: &str,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let transaction = self.storage.begin().await?;
+        let result = self
+            .grant_admin(transaction.as_storage(), organization, login, context)
+            .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_login_history(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        pagination: Pagination,
+    ) -> UserDomainResult<Page<LoginEvent>> {
+        UserDomain::check_pagination(&pagination)?;
+        let user = match self.storage.get_user(organization.id, uuid, false).await? {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+
+        Ok(self
+            .storage
+            .get_login_events(organization.id, user.id, &pagination)
+            .await?)
+    }
+
+    async fn unlock_user(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let user = match self.storage.get_user(organization.id, uuid, false).await? {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .delete_login_counter(
+                    organization.id,
+                    CounterScope::Login,
+                    &user.login.to_lowercase(),
+                )
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Unlock,
+                AuditTarget::User,
+                user.uuid,
+                Vec::new(),
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn change_user_status(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ChangeStatusCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        validation::check(&command)?;
+        let mut user = self.find_user(organization, uuid).await?;
+        if !user.status.can_become(command.status) {
+            return Err(UserDomainError::Validation {
+                errors: vec![FieldError::new(
+                    "status",
+                    "invalid",
+                    &format!(
+                        "A {} user cannot become {}.",
+                        user.status.as_str(),
+                        command.status.as_str()
+                    ),
+                )],
+            });
+        }
+
+        let reason = command
+            .reason
+            .as_ref()
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty());
+        let before = user.clone();
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<AppUser> = async {
+            UserDomain::save_status(
+                storage,
+                organization,
+                &mut user,
+                command.status,
+                reason,
+                command.suspended_until,
+                context.actor,
+            )
+            .await?;
+
+            if !user.is_active(Utc::now()) {
+                storage
+                    .delete_user_sessions(organization.id, user.id)
+                    .await?;
+            }
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::ChangeStatus,
+                AuditTarget::User,
+                user.uuid,
+                FieldChange::diff(Some(&before), Some(&user)),
+            )
+            .await?;
+            Ok(user)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+
+    async fn get_status_history(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<StatusChange>> {
+        let user = self.find_user(organization, uuid).await?;
+        Ok(self
+            .storage
+            .get_status_changes(organization.id, user.id)
+            .await?)
+    }
+
+    async fn get_all_users<'a>(
+        &self,
+        organization: &Organization,
+        query: UserQuery,
+    ) -> UserDomainResult<Page<AppUser>> {
+        UserDomain::check_pagination(&query.get_pagination())?;
+        Ok(self.storage.get_all_users(organization.id, &query).await?)
+    }
+
+    async fn get_user<'a>(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> UserDomainResult<Option<AppUser>> {
+        Ok(self
+            .storage
+            .get_user(organization.id, uuid, include_deleted)
+            .await?)
+    }
+    async fn create_user(
+        &self,
+        organization: &Organization,
+        command: CreateUserCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        validation::check(&command)?;
+        let person = match self
+            .storage
+            .get_person_by_uuid(organization.id, &command.person_uuid, false)
+            .await?
+        {
+            Some(person) => person,
+            None => return Err(UserDomainError::not_found("Person")),
+        };
+        self.check_login_available(organization, &command.login, 0)
+            .await?;
+
+        let mut user = AppUser::new(
+            0,
+            None,
+            command.login,
+            self.password_manager.hash(&command.password)?,
+            command.photo,
+            None,
+            None,
+            None,
+            person,
+        );
+        if self.require_activation {
+            user.status = AccountStatus::Pending;
+        }
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<AppUser> = async {
+            let mut created_user = storage.create_user(organization.id, user).await?;
+            let mut changes = FieldChange::diff(None, Some(&created_user));
+            changes.push(FieldChange::added("person", created_user.person.uuid));
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Create,
+                AuditTarget::User,
+                created_user.uuid,
+                changes,
+            )
+            .await?;
+            created_user.password = "".to_string();
+            Ok(created_user)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+    async fn register_account(
+        &self,
+        organization: &Organization,
+        command: RegisterAccountCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        validation::check(&command)?;
+        self.check_email_available(organization, &command.email, 0)
+            .await?;
+        self.check_login_available(organization, &command.login, 0)
+            .await?;
+
+        let mut person = Person::new(
+            0,
+            None,
+            command.firstname,
+            command.lastname,
+            command.email,
+            command.phone,
+            None,
+            None,
+        );
+        person.email_status = EmailStatus::Pending;
+        let mut user = AppUser::new(
+            0,
+            None,
+            command.login,
+            self.password_manager.hash(&command.password)?,
+            command.photo,
+            None,
+            None,
+            None,
+            person,
+        );
+        if self.require_activation {
+            user.status = AccountStatus::Pending;
+        }
+
+        let transaction = self.storage.begin().await?;
+        let result = self
+            .save_account(organization, transaction.as_storage(), user, context)
+            .await;
+        let mut user = UserDomain::end_transaction(transaction, result).await?;
+
+        self.issue_email_verification(organization, &user.person)
+            .await?;
+        user.password = "".to_string();
+        Ok(user)
+    }
+    async fn update_user(
+        &self,
+        organization: &Organization,
+        command: UpdateUserCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        validation::check(&command)?;
+        let mut user = match self
+            .storage
+            .get_user(organization.id, &command.uuid, false)
+            .await?
+        {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+        self.check_login_available(organization, &command.login, user.id)
+            .await?;
+
+        //An empty password keeps the stored hash.
+        user.password = "".to_string();
+        let before = user.clone();
+        user.login = command.login;
+        if command.photo.is_some() {
+            user.photo = command.photo;
+        }
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<AppUser> = async {
+            let user = storage.update_user(organization.id, user).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Update,
+                AuditTarget::User,
+                user.uuid,
+                FieldChange::diff(Some(&before), Some(&user)),
+            )
+            .await?;
+            Ok(user)
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+    async fn change_password(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ChangePasswordCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        validation::check(&command)?;
+        let user = match self
+            .storage
+            .get_user_with_password(organization.id, uuid)
+            .await?
+        {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+
+        match self
+            .password_manager
+            .verify(&user.login, &command.current_password, &user.password)
+        {
+            PasswordCheck::Invalid => Err(UserDomainError::InvalidCredentials),
+            _ => {
+                let new_hash = self.password_manager.hash(&command.new_password)?;
+                let transaction = self.storage.begin().await?;
+                let storage = transaction.as_storage();
+                let result = async {
+                    storage
+                        .update_user_password(organization.id, user.id, new_hash)
+                        .await?;
+                    UserDomain::write_audit(
+                        storage,
+                        organization,
+                        context,
+                        AuditAction::ChangePassword,
+                        AuditTarget::User,
+                        user.uuid,
+                        vec![FieldChange::redacted("password")],
+                    )
+                    .await
+                }
+                .await;
+                UserDomain::end_transaction(transaction, result).await
             }
         }
     }
+    async fn request_password_reset(
+        &self,
+        organization: &Organization,
+        command: RequestPasswordResetCommand,
+    ) -> UserDomainResult<()> {
+        validation::check(&command)?;
+        let now = Utc::now();
+        self.storage
+            .delete_expired_password_resets(organization.id, now)
+            .await?;
+        let user = match self
+            .find_user_by_login_or_email(organization, command.login.trim())
+            .await?
+        {
+            Some(user) => user,
+            None => return Ok(()),
+        };
+
+        //A new token replaces the ones not used yet.
+        let token = self.token_manager.generate();
+        let expires_on = self.token_manager.get_reset_expires_on(now);
+        let reset = PasswordReset::new(user.id, self.token_manager.hash(&token), expires_on);
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<()> = async {
+            storage
+                .delete_user_password_resets(organization.id, user.id)
+                .await?;
+            storage
+                .create_password_reset(organization.id, reset)
+                .await?;
+            Ok(())
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?;
 
-    async fn get_all_users<'a>(&self) -> UserDomainResult<Vec<AppUser>> {
-        Ok(self.storage.get_all_users().await?)
+        self.notifier
+            .send(Notification {
+                kind: NotificationKind::PasswordReset,
+                organization_uuid: organization.uuid,
+                login: Some(user.login),
+                email: user.person.email,
+                token,
+                expires_on,
+            })
+            .await
     }
 
-    async fn get_user<'a>(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<AppUser>> {
-        Ok(self.storage.get_user(uuid).await?)
+    async fn reset_password(
+        &self,
+        organization: &Organization,
+        command: ResetPasswordCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        validation::check(&command)?;
+        let now = Utc::now();
+        let reset = match self
+            .storage
+            .get_password_reset(organization.id, &self.token_manager.hash(&command.token))
+            .await?
+        {
+            Some(reset) if reset.used_on.is_none() && !reset.is_expired(now) => reset,
+            _ => return Err(UserDomainError::InvalidToken),
+        };
+        let new_hash = self.password_manager.hash(&command.new_password)?;
+
+        //The token is only used up along with the new password.
+        let transaction = self.storage.begin().await?;
+        let result = UserDomain::apply_password_reset(
+            transaction.as_storage(),
+            organization,
+            &reset,
+            new_hash,
+            now,
+            context,
+        )
+        .await;
+        UserDomain::end_transaction(transaction, result).await
     }
-    async fn create_user(&self, user: AppUser) -> UserDomainResult<AppUser> {
-        Ok(self.storage.create_user(user).await?)
+    async fn delete_user(
+        &self,
+        organization: &Organization,
+        user: AppUser,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let transaction = self.storage.begin().await?;
+        let result = UserDomain::soft_delete_user(
+            transaction.as_storage(),
+            organization,
+            &user,
+            Utc::now(),
+            context,
+        )
+        .await;
+        UserDomain::end_transaction(transaction, result).await
     }
-    async fn update_user(&self, user: AppUser) -> UserDomainResult<AppUser> {
-        self.storage.update_person(user.person.clone()).await?;
-        Ok(self.storage.update_user(user).await?)
+    async fn restore_user(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser> {
+        let mut user = match self.storage.get_user(organization.id, uuid, true).await? {
+            Some(user) => user,
+            None => return Err(UserDomainError::not_found("User")),
+        };
+
+        //A user comes back with its person.
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<()> = async {
+            if user.person.is_deleted() {
+                UserDomain::undelete_person(storage, organization, &mut user.person, context)
+                    .await?;
+            }
+            if user.is_deleted() {
+                UserDomain::undelete_user(storage, organization, &mut user, context).await?;
+            }
+            Ok(())
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?;
+        Ok(user)
     }
-    async fn delete_user(&self, user: AppUser) -> UserDomainResult<()> {
-        Ok(self.storage.delete_user(user).await?)
+    async fn purge_deleted(
+        &self,
+        organization: &Organization,
+        before: DateTime<Utc>,
+    ) -> UserDomainResult<u64> {
+        //Services sharing the storage take turns, the ones finding it locked skip theirs.
+        let transaction = self.storage.begin().await?;
+        let result = match transaction.as_storage().try_lock_purge().await {
+            Ok(true) => {
+                UserDomain::purge_organization(transaction.as_storage(), organization, before).await
+            }
+            Ok(false) => Ok(0),
+            Err(error) => Err(error.into()),
+        };
+        UserDomain::end_transaction(transaction, result).await
     }
-    async fn get_all_persons(&self) -> UserDomainResult<Vec<Person>> {
-        Ok(self.storage.get_all_person().await?)
+    async fn get_all_persons(
+        &self,
+        organization: &Organization,
+        query: PersonQuery,
+    ) -> UserDomainResult<Page<Person>> {
+        UserDomain::check_pagination(&query.get_pagination())?;
+        Ok(self.storage.get_all_person(organization.id, &query).await?)
     }
-    async fn get_person(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<Person>> {
-        Ok(self.storage.get_person_by_uuid(uuid).await?)
+    async fn get_person(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> UserDomainResult<Option<Person>> {
+        Ok(self
+            .storage
+            .get_person_by_uuid(organization.id, uuid, include_deleted)
+            .await?)
     }
-    async fn create_person(&self, person: Person) -> UserDomainResult<Person> {
-        Ok(self.storage.create_person(person).await?)
+    async fn create_person(
+        &self,
+        organization: &Organization,
+        command: CreatePersonCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person> {
+        validation::check(&command)?;
+        self.check_email_available(organization, &command.email, 0)
+            .await?;
+
+        let mut person = Person::new(
+            0,
+            None,
+            command.firstname,
+            command.lastname,
+            command.email,
+            command.phone,
+            None,
+            None,
+        );
+        person.email_status = EmailStatus::Pending;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Person> = async {
+            let person = storage.create_person(organization.id, person).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Create,
+                AuditTarget::Person,
+                person.uuid,
+                FieldChange::diff(None, Some(&person)),
+            )
+            .await?;
+            Ok(person)
+        }
+        .await;
+        let person = UserDomain::end_transaction(transaction, result).await?;
+
+        self.issue_email_verification(organization, &person).await?;
+        Ok(person)
     }
-    async fn update_person<'a>(&self, person: Person) -> UserDomainResult<Person> {
-        Ok(self.storage.update_person(person).await?)
+    async fn update_person<'a>(
+        &self,
+        organization: &Organization,
+        command: UpdatePersonCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person> {
+        validation::check(&command)?;
+        let mut person = match self
+            .storage
+            .get_person_by_uuid(organization.id, &command.uuid, false)
+            .await?
+        {
+            Some(person) => person,
+            None => return Err(UserDomainError::not_found("Person")),
+        };
+        self.check_email_available(organization, &command.email, person.id)
+            .await?;
+
+        let before = person.clone();
+        //A new address has to be verified again.
+        let email_changed = person.email.to_lowercase() != command.email.to_lowercase();
+        if email_changed {
+            person.email_status = EmailStatus::Pending;
+            person.email_verified_on = None;
+        }
+
+        person.firstname = command.firstname;
+        person.lastname = command.lastname;
+        person.email = command.email;
+        person.phone = command.phone;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result: UserDomainResult<Person> = async {
+            let person = storage.update_person(organization.id, person).await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::Update,
+                AuditTarget::Person,
+                person.uuid,
+                FieldChange::diff(Some(&before), Some(&person)),
+            )
+            .await?;
+            Ok(person)
+        }
+        .await;
+        let person = UserDomain::end_transaction(transaction, result).await?;
+
+        if email_changed {
+            self.issue_email_verification(organization, &person).await?;
+        }
+        Ok(person)
     }
-    async fn delete_person(&self, person: Person) -> UserDomainResult<()> {
-        Ok(self.storage.delete_person(person).await?)
+    async fn send_email_verification(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<()> {
+        let person = match self
+            .storage
+            .get_person_by_uuid(organization.id, uuid, false)
+            .await?
+        {
+            Some(person) => person,
+            None => return Err(UserDomainError::not_found("Person")),
+        };
+
+        if person.email_status == EmailStatus::Verified {
+            return Ok(());
+        }
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            if person.email_status == EmailStatus::Unverified {
+                storage
+                    .update_email_status(organization.id, person.id, EmailStatus::Pending, None)
+                    .await?;
+            }
+            self.save_email_verification(storage, organization, &person)
+                .await
+        }
+        .await;
+        let notification = UserDomain::end_transaction(transaction, result).await?;
+        self.notifier.send(notification).await
+    }
+    async fn verify_email(
+        &self,
+        organization: &Organization,
+        command: VerifyEmailCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        validation::check(&command)?;
+        let now = Utc::now();
+        let verification = match self
+            .storage
+            .get_email_verification(organization.id, &self.token_manager.hash(&command.token))
+            .await?
+        {
+            Some(verification) if !verification.is_expired(now) => verification,
+            _ => return Err(UserDomainError::InvalidToken),
+        };
+        let person = match self
+            .storage
+            .get_person_by_id(organization.id, verification.person_id)
+            .await?
+        {
+            Some(person) => person,
+            None => return Err(UserDomainError::InvalidToken),
+        };
+
+        //Tokens sent to a previous address do not verify the current one.
+        if person.email.to_lowercase() != verification.email.to_lowercase() {
+            return Err(UserDomainError::InvalidToken);
+        }
+
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            storage
+                .update_email_status(organization.id, person.id, EmailStatus::Verified, Some(now))
+                .await?;
+            storage
+                .delete_person_email_verifications(organization.id, person.id)
+                .await?;
+            UserDomain::write_audit(
+                storage,
+                organization,
+                context,
+                AuditAction::VerifyEmail,
+                AuditTarget::Person,
+                person.uuid,
+                vec![FieldChange::new(
+                    "email_status",
+                    serde_json::json!(person.email_status),
+                    serde_json::json!(EmailStatus::Verified),
+                )],
+            )
+            .await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+    async fn delete_person(
+        &self,
+        organization: &Organization,
+        person: Person,
+        context: &AuditContext,
+    ) -> UserDomainResult<()> {
+        let users = self.get_person_users(organization, &person, false).await?;
+        let transaction = self.storage.begin().await?;
+        let result = UserDomain::soft_delete_person(
+            transaction.as_storage(),
+            organization,
+            &person,
+            &users,
+            context,
+        )
+        .await;
+        UserDomain::end_transaction(transaction, result).await
+    }
+    async fn restore_person(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person> {
+        let mut person = match self
+            .storage
+            .get_person_by_uuid(organization.id, uuid, true)
+            .await?
+        {
+            Some(person) => person,
+            None => return Err(UserDomainError::not_found("Person")),
+        };
+        let deleted_on = match person.deleted_on {
+            Some(deleted_on) => deleted_on,
+            None => return Ok(person),
+        };
+
+        //The users deleted along with the person come back with it.
+        let users = self.get_person_users(organization, &person, true).await?;
+        let transaction = self.storage.begin().await?;
+        let storage = transaction.as_storage();
+        let result = async {
+            for mut user in users {
+                if user.deleted_on == Some(deleted_on) {
+                    UserDomain::undelete_user(storage, organization, &mut user, context).await?;
+                }
+            }
+            UserDomain::undelete_person(storage, organization, &mut person, context).await
+        }
+        .await;
+        UserDomain::end_transaction(transaction, result).await?;
+        Ok(person)
+    }
+
+    async fn get_audit_entries(
+        &self,
+        organization: &Organization,
+        query: AuditQuery,
+    ) -> UserDomainResult<Page<AuditEntry>> {
+        UserDomain::check_pagination(&query.get_pagination())?;
+        Ok(self
+            .storage
+            .get_audit_entries(organization.id, &query)
+            .await?)
+    }
+
+    async fn export_audit_entries(
+        &self,
+        organization: &Organization,
+        mut query: AuditQuery,
+    ) -> UserDomainResult<Vec<AuditEntry>> {
+        UserDomain::check_pagination(&query.get_pagination())?;
+        //Entries written meanwhile would shift the pages.
+        query.to = query.to.or_else(|| Some(Utc::now()));
+        query.limit = Some(MAX_LIMIT);
+
+        let mut entries = Vec::new();
+        loop {
+            let page = self
+                .storage
+                .get_audit_entries(organization.id, &query)
+                .await?;
+            entries.extend(page.items);
+            match page.next_cursor {
+                Some(cursor) => {
+                    query.cursor = Some(cursor);
+                    query.page = None;
+                }
+                None => return Ok(entries),
+            }
+        }
     }
 }
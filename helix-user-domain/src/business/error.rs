@@ -1,21 +1,147 @@
+use crate::core::app_user::AccountStatus;
 use crate::storage::error::StorageError;
+use chrono::prelude::*;
 use std::result::Result;
 use thiserror::Error;
 
+//Describe why a single field was rejected.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: &str) -> FieldError {
+        FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
 //Define the possible errors
 #[derive(Error, Debug)]
 pub enum UserDomainError {
     #[error("NotImplemented")]
     NotImplemented,
-    #[error("Storage Error")]
-    StorageError,
-    #[error("Not found error")]
-    NotFoundError,
-    #[error("Storage error: {source}")]
-    Storage {
-        #[from]
-        source: StorageError,
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("{entity} not found")]
+    NotFound { entity: String },
+    #[error("Conflict on field {field}")]
+    Conflict { field: String },
+    #[error("Validation failed")]
+    Validation { errors: Vec<FieldError> },
+    #[error("Invalid or expired token")]
+    InvalidToken,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Account {}", .status.as_str())]
+    AccountInactive {
+        status: AccountStatus,
+        until: Option<DateTime<Utc>>,
     },
+    #[error("Account locked")]
+    AccountLocked { until: Option<DateTime<Utc>> },
+    #[error("Too many attempts")]
+    TooManyAttempts { retry_after: DateTime<Utc> },
+    #[error("Backend unavailable")]
+    BackendUnavailable,
+    #[error("Password hash error")]
+    PasswordHashError,
+    #[error("Token generation error")]
+    TokenGenerationError,
+    #[error("Notification error")]
+    NotificationError,
+    //Stored records always have one, this one comes from a damaged row.
+    #[error("{entity} without uuid")]
+    MissingUuid { entity: String },
+    #[error("Storage error: {source}")]
+    Storage { source: StorageError },
+}
+
+impl UserDomainError {
+    pub fn not_found(entity: &str) -> UserDomainError {
+        UserDomainError::NotFound {
+            entity: entity.to_string(),
+        }
+    }
+
+    pub fn conflict(field: &str) -> UserDomainError {
+        UserDomainError::Conflict {
+            field: field.to_string(),
+        }
+    }
+
+    pub fn missing_uuid(entity: &str) -> UserDomainError {
+        UserDomainError::MissingUuid {
+            entity: entity.to_string(),
+        }
+    }
+
+    //Stable machine-readable code shared by the REST and gRPC layers.
+    pub fn code(&self) -> &'static str {
+        match self {
+            UserDomainError::NotImplemented => "not_implemented",
+            UserDomainError::InvalidCredentials => "invalid_credentials",
+            UserDomainError::NotFound { .. } => "not_found",
+            UserDomainError::Conflict { .. } => "conflict",
+            UserDomainError::Validation { .. } => "validation_failed",
+            UserDomainError::InvalidToken => "invalid_token",
+            UserDomainError::Forbidden => "forbidden",
+            UserDomainError::EmailNotVerified => "email_not_verified",
+            UserDomainError::AccountInactive { .. } => "account_inactive",
+            UserDomainError::AccountLocked { .. } => "account_locked",
+            UserDomainError::TooManyAttempts { .. } => "too_many_attempts",
+            UserDomainError::BackendUnavailable => "backend_unavailable",
+            UserDomainError::PasswordHashError => "internal_error",
+            UserDomainError::TokenGenerationError => "internal_error",
+            UserDomainError::NotificationError => "internal_error",
+            UserDomainError::MissingUuid { .. } => "internal_error",
+            UserDomainError::Storage { .. } => "internal_error",
+        }
+    }
+
+    //When the client may retry, for lockout errors and suspensions.
+    pub fn get_retry_after(&self) -> Option<DateTime<Utc>> {
+        match self {
+            UserDomainError::AccountLocked { until } => *until,
+            UserDomainError::AccountInactive { until, .. } => *until,
+            UserDomainError::TooManyAttempts { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    //Field level details of validation and conflict errors.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            UserDomainError::Validation { errors } => errors.clone(),
+            UserDomainError::Conflict { field } => vec![FieldError::new(
+                field,
+                "duplicate",
+                "Value already used.",
+            )],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl From<StorageError> for UserDomainError {
+    fn from(source: StorageError) -> Self {
+        match source {
+            StorageError::BackendUnavailable => UserDomainError::BackendUnavailable,
+            StorageError::Conflict { field } => UserDomainError::Conflict { field },
+            StorageError::PostGres { source } if source.is_closed() => {
+                UserDomainError::BackendUnavailable
+            }
+            source => UserDomainError::Storage { source },
+        }
+    }
 }
 
 //Define a generic error type to simplify return.
@@ -0,0 +1,74 @@
+use crate::core::login::LoginCounter;
+use chrono::prelude::*;
+use chrono::Duration;
+
+//Brute-force protection thresholds, durations are in seconds.
+#[derive(Debug, Clone)]
+pub struct LockoutSettings {
+    //Failures allowed per login before locking the account.
+    pub max_attempts: i32,
+    //Failures allowed per client IP before throttling it.
+    pub ip_max_attempts: i32,
+    //Failures older than this are forgotten.
+    pub window: i64,
+    //First lock duration, doubled on each further failure.
+    pub base_delay: i64,
+    pub max_delay: i64,
+}
+
+impl Default for LockoutSettings {
+    fn default() -> Self {
+        LockoutSettings {
+            max_attempts: 5,
+            ip_max_attempts: 20,
+            window: 900,
+            base_delay: 30,
+            max_delay: 3600,
+        }
+    }
+}
+
+pub struct LockoutPolicy {
+    settings: LockoutSettings,
+}
+
+impl LockoutPolicy {
+    pub fn new(settings: LockoutSettings) -> LockoutPolicy {
+        LockoutPolicy { settings }
+    }
+
+    //Count a failure and lock once the threshold is reached, with exponential backoff.
+    pub fn register_failure(
+        &self,
+        mut counter: LoginCounter,
+        max_attempts: i32,
+        now: DateTime<Utc>,
+    ) -> LoginCounter {
+        if now - counter.last_failure_on > Duration::seconds(self.settings.window) {
+            counter.failures = 0;
+        }
+
+        counter.failures += 1;
+        counter.last_failure_on = now;
+
+        if counter.failures >= max_attempts {
+            let exponent = (counter.failures - max_attempts).min(20) as u32;
+            let delay = self
+                .settings
+                .base_delay
+                .saturating_mul(2i64.pow(exponent))
+                .min(self.settings.max_delay);
+            counter.locked_until = Some(now + Duration::seconds(delay));
+        }
+
+        counter
+    }
+
+    pub fn get_max_attempts(&self) -> i32 {
+        self.settings.max_attempts
+    }
+
+    pub fn get_ip_max_attempts(&self) -> i32 {
+        self.settings.ip_max_attempts
+    }
+}
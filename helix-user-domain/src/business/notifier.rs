@@ -0,0 +1,71 @@
+use crate::business::error::*;
+use async_trait::async_trait;
+use chrono::prelude::*;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    PasswordReset,
+    EmailVerification,
+}
+
+//Message for a user, the token is only ever handed out through it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    //Needed by anonymous requests using the token, see the organization header.
+    pub organization_uuid: Option<uuid::Uuid>,
+    //None for persons without a user.
+    pub login: Option<String>,
+    pub email: String,
+    pub token: String,
+    pub expires_on: DateTime<Utc>,
+}
+
+//Delivers notifications to users, by mail or any other channel.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: Notification) -> UserDomainResult<()>;
+}
+
+//Prints notifications, for development without a mail server.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: Notification) -> UserDomainResult<()> {
+        println!(
+            "--> {:?} for {}: {} (expires on {})",
+            notification.kind, notification.email, notification.token, notification.expires_on
+        );
+        Ok(())
+    }
+}
+
+//Appends notifications to a file, one JSON document per line,
+//for another process to deliver or for tests to read.
+pub struct FileNotifier {
+    path: String,
+}
+
+impl FileNotifier {
+    pub fn new(path: String) -> FileNotifier {
+        FileNotifier { path }
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn send(&self, notification: Notification) -> UserDomainResult<()> {
+        let line =
+            serde_json::to_string(&notification).map_err(|_| UserDomainError::NotificationError)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_| UserDomainError::NotificationError)?;
+        writeln!(file, "{}", line).map_err(|_| UserDomainError::NotificationError)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::business::error::*;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use rand_core::OsRng;
+use std::convert::TryFrom;
+
+//Global salt used by the legacy SHA-256 auth keys.
+const LEGACY_SALT: &str = "__H3l!X__";
+const ARGON2_PREFIX: &str = "$argon2";
+
+//Argon2id cost parameters.
+#[derive(Debug, Clone)]
+pub struct PasswordSettings {
+    //Memory size in KiB.
+    pub memory_cost: u32,
+    //Number of iterations.
+    pub time_cost: u32,
+    //Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for PasswordSettings {
+    fn default() -> Self {
+        PasswordSettings {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+//Outcome of a password check against a stored value.
+#[derive(Debug, PartialEq)]
+pub enum PasswordCheck {
+    Invalid,
+    Valid,
+    //Valid, but the stored value is a legacy key or uses outdated parameters.
+    ValidNeedsRehash,
+}
+
+pub struct PasswordManager {
+    settings: PasswordSettings,
+}
+
+impl PasswordManager {
+    pub fn new(settings: PasswordSettings) -> Self {
+        PasswordManager { settings }
+    }
+
+    //Hash a plaintext password into a PHC string with a fresh random salt.
+    pub fn hash(&self, password: &str) -> UserDomainResult<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self
+            .argon2()?
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|_| UserDomainError::PasswordHashError)?;
+
+        Ok(hash.to_string())
+    }
+
+    //Check a plaintext password against a stored PHC hash or legacy auth key.
+    pub fn verify(&self, login: &str, password: &str, stored: &str) -> PasswordCheck {
+        if !PasswordManager::is_hashed(stored) {
+            let legacy_key = PasswordManager::legacy_auth_key(login, password);
+            return match fixed_time_eq(legacy_key.as_bytes(), stored.as_bytes()) {
+                true => PasswordCheck::ValidNeedsRehash,
+                false => PasswordCheck::Invalid,
+            };
+        }
+
+        let parsed_hash = match PasswordHash::new(stored) {
+            Ok(parsed_hash) => parsed_hash,
+            Err(_) => return PasswordCheck::Invalid,
+        };
+
+        //Verification uses the parameters embedded in the stored hash.
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_err()
+        {
+            return PasswordCheck::Invalid;
+        }
+
+        match self.is_up_to_date(&parsed_hash) {
+            true => PasswordCheck::Valid,
+            false => PasswordCheck::ValidNeedsRehash,
+        }
+    }
+
+    //Tell if a stored value is already an Argon2 PHC string.
+    pub fn is_hashed(stored: &str) -> bool {
+        stored.starts_with(ARGON2_PREFIX)
+    }
+
+    fn is_up_to_date(&self, parsed_hash: &PasswordHash) -> bool {
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident() {
+            return false;
+        }
+
+        match Params::try_from(parsed_hash) {
+            Ok(params) => {
+                params.m_cost() == self.settings.memory_cost
+                    && params.t_cost() == self.settings.time_cost
+                    && params.p_cost() == self.settings.parallelism
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn argon2(&self) -> UserDomainResult<Argon2<'static>> {
+        let params = Params::new(
+            self.settings.memory_cost,
+            self.settings.time_cost,
+            self.settings.parallelism,
+            None,
+        )
+        .map_err(|_| UserDomainError::PasswordHashError)?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    //Key format used before Argon2: login:sha256(login+password+salt).
+    fn legacy_auth_key(login: &str, password: &str) -> String {
+        //Hash construct
+        let mut to_hash: String = String::new();
+        to_hash.push_str(login);
+        to_hash.push_str(password);
+        to_hash.push_str(LEGACY_SALT);
+
+        let mut hasher = Sha256::new();
+        hasher.input_str(&to_hash);
+
+        //Key construct.
+        let mut key: String = String::new();
+        key.push_str(login);
+        key.push(':');
+        key.push_str(&hasher.result_str());
+
+        //return
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGIN: &str = "legacy.user";
+    const PASSWORD: &str = "Correct-Horse-42";
+    //Key stored for LOGIN and PASSWORD before Argon2.
+    const LEGACY_KEY: &str =
+        "legacy.user:7cbf2cbf85cc09ed16388fa85271171973b95cc4b6ebee1c87eda0effd9d41a3";
+
+    //Cheap parameters, the defaults make every test slow.
+    fn get_manager(time_cost: u32) -> PasswordManager {
+        PasswordManager::new(PasswordSettings {
+            memory_cost: 1024,
+            time_cost,
+            parallelism: 1,
+        })
+    }
+
+    #[test]
+    fn hash_is_salted_argon2id() {
+        let manager = get_manager(1);
+        let first = manager.hash(PASSWORD).unwrap();
+        let second = manager.hash(PASSWORD).unwrap();
+
+        assert!(first.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert!(PasswordManager::is_hashed(&first));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn verify_argon2_hash() {
+        let manager = get_manager(1);
+        let hash = manager.hash(PASSWORD).unwrap();
+
+        assert_eq!(manager.verify(LOGIN, PASSWORD, &hash), PasswordCheck::Valid);
+        assert_eq!(
+            manager.verify(LOGIN, "Wrong-Horse-42", &hash),
+            PasswordCheck::Invalid
+        );
+        assert_eq!(
+            manager.verify(LOGIN, PASSWORD, "$argon2id$broken"),
+            PasswordCheck::Invalid
+        );
+    }
+
+    #[test]
+    fn outdated_parameters_need_rehash() {
+        let hash = get_manager(1).hash(PASSWORD).unwrap();
+
+        assert_eq!(
+            get_manager(2).verify(LOGIN, PASSWORD, &hash),
+            PasswordCheck::ValidNeedsRehash
+        );
+    }
+
+    #[test]
+    fn verify_legacy_key() {
+        let manager = get_manager(1);
+
+        assert!(!PasswordManager::is_hashed(LEGACY_KEY));
+        assert_eq!(
+            manager.verify(LOGIN, PASSWORD, LEGACY_KEY),
+            PasswordCheck::ValidNeedsRehash
+        );
+        assert_eq!(
+            manager.verify(LOGIN, "Wrong-Horse-42", LEGACY_KEY),
+            PasswordCheck::Invalid
+        );
+        assert_eq!(
+            manager.verify("other.user", PASSWORD, LEGACY_KEY),
+            PasswordCheck::Invalid
+        );
+    }
+}
@@ -0,0 +1,17 @@
+use crate::business::lockout::LockoutSettings;
+use crate::business::password::PasswordSettings;
+use crate::business::token::TokenSettings;
+use crate::business::two_factor::TwoFactorSettings;
+
+//Tunable behaviour of the user domain.
+#[derive(Debug, Clone, Default)]
+pub struct UserDomainSettings {
+    pub password: PasswordSettings,
+    pub lockout: LockoutSettings,
+    pub token: TokenSettings,
+    pub two_factor: TwoFactorSettings,
+    //Refuse logins of users whose person has not verified its email.
+    pub require_verified_email: bool,
+    //New users start pending until an administrator activates them.
+    pub require_activation: bool,
+}
@@ -0,0 +1,74 @@
+use crate::business::error::*;
+use crate::core::app_user::AppUser;
+use chrono::prelude::*;
+use chrono::Duration;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use rand_core::{OsRng, RngCore};
+
+const TOKEN_BYTES: usize = 32;
+
+//Signs access tokens, the domain keeps their digest to resolve the caller.
+pub trait AccessTokenIssuer: Send + Sync {
+    fn issue(&self, user: &AppUser) -> UserDomainResult<String>;
+}
+
+//Lifetimes in minutes.
+#[derive(Debug, Clone)]
+pub struct TokenSettings {
+    pub access_lifetime: i64,
+    //Renewed on each rotation.
+    pub refresh_lifetime: i64,
+    pub reset_lifetime: i64,
+    pub verification_lifetime: i64,
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        TokenSettings {
+            access_lifetime: 60,
+            refresh_lifetime: 480,
+            reset_lifetime: 30,
+            verification_lifetime: 1440,
+        }
+    }
+}
+
+pub struct TokenManager {
+    settings: TokenSettings,
+}
+
+impl TokenManager {
+    pub fn new(settings: TokenSettings) -> TokenManager {
+        TokenManager { settings }
+    }
+
+    //Random URL safe value, handed out once and never stored as is.
+    pub fn generate(&self) -> String {
+        let mut bytes = [0u8; TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn hash(&self, token: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input_str(token);
+        hasher.result_str()
+    }
+
+    pub fn get_expires_on(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(self.settings.refresh_lifetime)
+    }
+
+    pub fn get_access_expires_on(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(self.settings.access_lifetime)
+    }
+
+    pub fn get_reset_expires_on(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(self.settings.reset_lifetime)
+    }
+
+    pub fn get_verification_expires_on(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(self.settings.verification_lifetime)
+    }
+}
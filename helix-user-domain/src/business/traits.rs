@@ -1,23 +1,421 @@
+use crate::business::authorization::Actor;
 use crate::business::error::*;
-use crate::core::app_user::AppUser;
+use crate::core::app_user::*;
+use crate::core::audit::*;
+use crate::core::command::*;
+use crate::core::group::Group;
+use crate::core::login::{LoginContext, LoginEvent};
+use crate::core::organization::Organization;
 use crate::core::person::Person;
+use crate::core::query::*;
+use crate::core::role::Role;
+use crate::core::session::Session;
+use crate::core::token::TokenPair;
+use crate::core::two_factor::*;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
+//Mutations are written to the audit log with the context given, logins and
+//the tokens sent to users are left to the login history.
 #[async_trait]
 pub trait UserDomainTrait: Send + Sync {
-    fn generate_user_auth_key(&self, login: &String, password: &String) -> String;
+    //Organization named by a request, the default one when it names none.
+    async fn resolve_organization(
+        &self,
+        uuid: Option<uuid::Uuid>,
+    ) -> UserDomainResult<Organization>;
+    async fn get_all_organizations(&self) -> UserDomainResult<Vec<Organization>>;
+    async fn get_organization(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<Organization>>;
+    //Create an organization with its first account, granted the admin role.
+    async fn create_organization(
+        &self,
+        command: CreateOrganizationCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Organization>;
+    async fn update_organization(
+        &self,
+        command: UpdateOrganizationCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Organization>;
 
-    async fn login(&self, login: &String, password: &String) -> UserDomainResult<AppUser>;
+    //Other calls act in the organization given, resolved beforehand.
+    async fn login(
+        &self,
+        organization: &Organization,
+        login: &str,
+        password: &str,
+        context: &LoginContext,
+    ) -> UserDomainResult<AppUser>;
+    async fn get_login_history(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        pagination: Pagination,
+    ) -> UserDomainResult<Page<LoginEvent>>;
+    //Open a session for a logged in user, return its first tokens.
+    async fn open_session(
+        &self,
+        organization: &Organization,
+        user: &AppUser,
+        context: &LoginContext,
+        device: Option<String>,
+    ) -> UserDomainResult<TokenPair>;
+    //Open a session, or hand out a challenge when the user has a second factor.
+    async fn start_session(
+        &self,
+        organization: &Organization,
+        user: &AppUser,
+        context: &LoginContext,
+        device: Option<String>,
+    ) -> UserDomainResult<LoginStep>;
+    //Second login step, the session is opened once the code is checked.
+    //Failed codes count towards the lockout of the account.
+    async fn verify_login(
+        &self,
+        organization: &Organization,
+        command: VerifyLoginCommand,
+        context: &LoginContext,
+    ) -> UserDomainResult<TokenPair>;
+    //Exchange a refresh token for new tokens, reusing a rotated token ends its session.
+    async fn refresh(
+        &self,
+        organization: &Organization,
+        refresh_token: &str,
+    ) -> UserDomainResult<TokenPair>;
+    //Resolve the caller of an access token issued by this domain,
+    //with its organization and permissions.
+    async fn authenticate(&self, access_token: &str) -> UserDomainResult<Actor>;
+    async fn logout(
+        &self,
+        organization: &Organization,
+        refresh_token: &str,
+    ) -> UserDomainResult<()>;
+    async fn get_user_sessions(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Session>>;
+    async fn delete_user_session(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        session_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //End every session of a user, on all devices.
+    async fn revoke_user_sessions(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn get_all_roles(&self, organization: &Organization) -> UserDomainResult<Vec<Role>>;
+    async fn get_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Option<Role>>;
+    async fn create_role(
+        &self,
+        organization: &Organization,
+        command: CreateRoleCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Role>;
+    async fn update_role(
+        &self,
+        organization: &Organization,
+        command: UpdateRoleCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Role>;
+    async fn delete_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn get_user_roles(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Role>>;
+    async fn assign_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn unassign_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn get_all_groups(&self, organization: &Organization) -> UserDomainResult<Vec<Group>>;
+    async fn get_group(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Option<Group>>;
+    async fn create_group(
+        &self,
+        organization: &Organization,
+        command: CreateGroupCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Group>;
+    async fn update_group(
+        &self,
+        organization: &Organization,
+        command: UpdateGroupCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Group>;
+    async fn delete_group(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Direct members only.
+    async fn get_group_members(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<AppUser>>;
+    async fn add_group_member(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        user_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn remove_group_member(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        user_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn get_group_roles(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Role>>;
+    async fn assign_group_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    async fn unassign_group_role(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        role_uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Effective groups of a user, the parents of its groups included.
+    async fn get_user_groups(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<Group>>;
 
-    async fn get_all_users<'a>(&self) -> UserDomainResult<Vec<AppUser>>;
-    async fn get_user<'a>(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<AppUser>>;
-    async fn create_user(&self, user: AppUser) -> UserDomainResult<AppUser>;
-    async fn update_user(&self, user: AppUser) -> UserDomainResult<AppUser>;
-    async fn delete_user(&self, user: AppUser) -> UserDomainResult<()>;
+    async fn get_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<TwoFactorStatus>;
+    //Start or restart an enrollment, refused once a second factor is enabled.
+    async fn enroll_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<TwoFactorSetup>;
+    //Enable the enrolled second factor with a first code, return new recovery codes.
+    async fn confirm_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ConfirmTwoFactorCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<RecoveryCodes>;
+    //Remove the second factor and its recovery codes, for users who lost it.
+    async fn reset_two_factor(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
 
-    async fn get_all_persons(&self) -> UserDomainResult<Vec<Person>>;
-    async fn get_person(&self, uuid: &uuid::Uuid) -> UserDomainResult<Option<Person>>;
-    async fn create_person(&self, person: Person) -> UserDomainResult<Person>;
-    async fn update_person<'a>(&self, person: Person) -> UserDomainResult<Person>;
-    async fn delete_person(&self, person: Person) -> UserDomainResult<()>;
+    //Grant the admin role to a login, creating the role when missing.
+    //A pending admin is activated, nobody could do it otherwise.
+    async fn ensure_admin(
+        &self,
+        organization: &Organization,
+        login: &str,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+
+    //Clear the failed attempts of a locked account.
+    async fn unlock_user(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+
+    //Move a user to another status, recording who did it.
+    //Suspended and disabled users are logged out of every session.
+    async fn change_user_status(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ChangeStatusCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser>;
+    async fn get_status_history(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<Vec<StatusChange>>;
+
+    async fn get_all_users<'a>(
+        &self,
+        organization: &Organization,
+        query: UserQuery,
+    ) -> UserDomainResult<Page<AppUser>>;
+    async fn get_user<'a>(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> UserDomainResult<Option<AppUser>>;
+    async fn create_user(
+        &self,
+        organization: &Organization,
+        command: CreateUserCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser>;
+    //Create a person and its user in one transaction, none of them is kept on failure.
+    //The email verification is sent once both are saved.
+    async fn register_account(
+        &self,
+        organization: &Organization,
+        command: RegisterAccountCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser>;
+    async fn update_user(
+        &self,
+        organization: &Organization,
+        command: UpdateUserCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser>;
+    async fn change_password(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        command: ChangePasswordCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Send a reset token through the notifier. Unknown users are ignored silently,
+    //the answer does not tell whether an account exists.
+    async fn request_password_reset(
+        &self,
+        organization: &Organization,
+        command: RequestPasswordResetCommand,
+    ) -> UserDomainResult<()>;
+    //Set a new password with a reset token, ending every session of the user.
+    async fn reset_password(
+        &self,
+        organization: &Organization,
+        command: ResetPasswordCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Users are only marked as deleted and logged out, until purged.
+    async fn delete_user(
+        &self,
+        organization: &Organization,
+        user: AppUser,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Bring back a deleted user, with its person if it was deleted too.
+    async fn restore_user(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<AppUser>;
+    //Remove for good what was deleted before the given date, returns the number of rows.
+    async fn purge_deleted(
+        &self,
+        organization: &Organization,
+        before: DateTime<Utc>,
+    ) -> UserDomainResult<u64>;
+
+    async fn get_all_persons(
+        &self,
+        organization: &Organization,
+        query: PersonQuery,
+    ) -> UserDomainResult<Page<Person>>;
+    async fn get_person(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> UserDomainResult<Option<Person>>;
+    async fn create_person(
+        &self,
+        organization: &Organization,
+        command: CreatePersonCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person>;
+    //Changing the email sends a verification token to the new address.
+    async fn update_person<'a>(
+        &self,
+        organization: &Organization,
+        command: UpdatePersonCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person>;
+    //Send a new verification token to the current email, verified emails are left as is.
+    async fn send_email_verification(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+    ) -> UserDomainResult<()>;
+    //Mark the email as verified with the token sent to it.
+    async fn verify_email(
+        &self,
+        organization: &Organization,
+        command: VerifyEmailCommand,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Deleting a person deletes its users as well.
+    async fn delete_person(
+        &self,
+        organization: &Organization,
+        person: Person,
+        context: &AuditContext,
+    ) -> UserDomainResult<()>;
+    //Bring back a deleted person, with the users deleted along with it.
+    async fn restore_person(
+        &self,
+        organization: &Organization,
+        uuid: &uuid::Uuid,
+        context: &AuditContext,
+    ) -> UserDomainResult<Person>;
+
+    //Newest entries first.
+    async fn get_audit_entries(
+        &self,
+        organization: &Organization,
+        query: AuditQuery,
+    ) -> UserDomainResult<Page<AuditEntry>>;
+    //Every entry matching the query, newest first, read page after page.
+    async fn export_audit_entries(
+        &self,
+        organization: &Organization,
+        query: AuditQuery,
+    ) -> UserDomainResult<Vec<AuditEntry>>;
 }
@@ -0,0 +1,160 @@
+use crate::business::error::*;
+use chrono::prelude::*;
+use chrono::Duration;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha1::Sha1;
+use crypto::util::fixed_time_eq;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand_core::{OsRng, RngCore};
+
+//RFC 6238 defaults, the only ones all authenticator apps support.
+const SECRET_BYTES: usize = 20;
+const CODE_DIGITS: u32 = 6;
+const TIME_STEP: i64 = 30;
+const RECOVERY_CODE_BYTES: usize = 10;
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+#[derive(Debug, Clone)]
+pub struct TwoFactorSettings {
+    //Shown by authenticator apps next to the account name.
+    pub issuer: String,
+    //Lifetime of login challenges in minutes.
+    pub challenge_lifetime: i64,
+    //Time steps accepted around the current one, for clock drift.
+    pub skew: i64,
+    pub recovery_codes: usize,
+}
+
+impl Default for TwoFactorSettings {
+    fn default() -> Self {
+        TwoFactorSettings {
+            issuer: "Helix".to_string(),
+            challenge_lifetime: 5,
+            skew: 1,
+            recovery_codes: 10,
+        }
+    }
+}
+
+pub struct TwoFactorManager {
+    settings: TwoFactorSettings,
+}
+
+impl TwoFactorManager {
+    pub fn new(settings: TwoFactorSettings) -> TwoFactorManager {
+        TwoFactorManager { settings }
+    }
+
+    pub fn generate_secret(&self) -> String {
+        let mut bytes = [0u8; SECRET_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        base32::encode(BASE32, &bytes)
+    }
+
+    //Key URI understood by authenticator apps.
+    pub fn get_uri(&self, account: &str, secret: &str) -> String {
+        let issuer = encode_uri_component(&self.settings.issuer);
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            issuer,
+            encode_uri_component(account),
+            secret,
+            issuer,
+            CODE_DIGITS,
+            TIME_STEP
+        )
+    }
+
+    pub fn render_qr_code(&self, uri: &str) -> UserDomainResult<String> {
+        let code =
+            QrCode::new(uri.as_bytes()).map_err(|_| UserDomainError::TokenGenerationError)?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+
+    //Code an authenticator app shows at the given date.
+    pub fn get_code(&self, secret: &str, now: DateTime<Utc>) -> Option<String> {
+        let key = base32::decode(BASE32, secret)?;
+        Some(generate_code(&key, now.timestamp() / TIME_STEP))
+    }
+
+    //Time step matching the code, None when it matches none or is not newer than the last used.
+    pub fn verify(
+        &self,
+        secret: &str,
+        code: &str,
+        now: DateTime<Utc>,
+        last_used_step: Option<i64>,
+    ) -> Option<i64> {
+        let key = base32::decode(BASE32, secret)?;
+        let code = code.trim();
+        if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let current_step = now.timestamp() / TIME_STEP;
+        (current_step - self.settings.skew..=current_step + self.settings.skew)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| fixed_time_eq(generate_code(&key, *step).as_bytes(), code.as_bytes()))
+    }
+
+    //Codes as shown to the user, grouped by four characters.
+    pub fn generate_recovery_codes(&self) -> Vec<String> {
+        (0..self.settings.recovery_codes)
+            .map(|_| {
+                let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+                OsRng.fill_bytes(&mut bytes);
+                let code = base32::encode(BASE32, &bytes).to_lowercase();
+                code.as_bytes()
+                    .chunks(4)
+                    .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+                    .collect::<Vec<String>>()
+                    .join("-")
+            })
+            .collect()
+    }
+
+    //Recovery codes are compared without separators nor case.
+    pub fn normalize_recovery_code(&self, code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    }
+
+    pub fn get_challenge_expires_on(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(self.settings.challenge_lifetime)
+    }
+}
+
+//HOTP value of a time step (RFC 4226 dynamic truncation).
+fn generate_code(key: &[u8], step: i64) -> String {
+    let mut mac = Hmac::new(Sha1::new(), key);
+    mac.input(&(step as u64).to_be_bytes());
+    let hash = mac.result();
+    let hash = hash.code();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = ((hash[offset] as u32 & 0x7f) << 24)
+        | ((hash[offset + 1] as u32) << 16)
+        | ((hash[offset + 2] as u32) << 8)
+        | (hash[offset + 3] as u32);
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(CODE_DIGITS),
+        width = CODE_DIGITS as usize
+    )
+}
+
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
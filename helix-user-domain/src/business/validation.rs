@@ -0,0 +1,505 @@
+use crate::business::error::*;
+use crate::core::app_user::AccountStatus;
+use crate::core::command::*;
+use chrono::prelude::*;
+
+const NAME_MAX_LENGTH: usize = 100;
+const EMAIL_MAX_LENGTH: usize = 254;
+const PHONE_MIN_DIGITS: usize = 6;
+const PHONE_MAX_DIGITS: usize = 15;
+const LOGIN_MIN_LENGTH: usize = 3;
+const LOGIN_MAX_LENGTH: usize = 50;
+const PASSWORD_MIN_LENGTH: usize = 8;
+const PASSWORD_MAX_LENGTH: usize = 128;
+const ROLE_NAME_MAX_LENGTH: usize = 50;
+const DESCRIPTION_MAX_LENGTH: usize = 255;
+
+//Commands checked by the domain before any storage call.
+pub trait Validate {
+    fn validate(&self) -> Vec<FieldError>;
+}
+
+//Gather every field error at once, clients fix them in a single round trip.
+pub fn check<T: Validate>(value: &T) -> UserDomainResult<()> {
+    let errors = value.validate();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(UserDomainError::Validation { errors }),
+    }
+}
+
+fn check_name(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "required", "Value is required."));
+    } else if value.chars().count() > NAME_MAX_LENGTH {
+        errors.push(FieldError::new(field, "too_long", "Value is too long."));
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    let mut parts = value.split('@');
+    let (local, domain) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None) => (local, domain),
+        _ => return false,
+    };
+
+    !local.is_empty()
+        && !value.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+fn check_email(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "required", "Value is required."));
+    } else if value.len() > EMAIL_MAX_LENGTH {
+        errors.push(FieldError::new(field, "too_long", "Value is too long."));
+    } else if !is_email(value) {
+        errors.push(FieldError::new(
+            field,
+            "invalid_format",
+            "Invalid email address.",
+        ));
+    }
+}
+
+//Digits with an optional leading +, usual separators are tolerated.
+fn check_phone(errors: &mut Vec<FieldError>, field: &str, value: &Option<String>) {
+    let value = match value {
+        Some(value) => value.strip_prefix('+').unwrap_or(value),
+        None => return,
+    };
+
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    let is_valid = value
+        .chars()
+        .all(|c| c.is_ascii_digit() || " .-()".contains(c))
+        && (PHONE_MIN_DIGITS..=PHONE_MAX_DIGITS).contains(&digits);
+
+    if !is_valid {
+        errors.push(FieldError::new(
+            field,
+            "invalid_format",
+            "Invalid phone number.",
+        ));
+    }
+}
+
+fn check_login(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    let length = value.chars().count();
+    if length < LOGIN_MIN_LENGTH {
+        errors.push(FieldError::new(field, "too_short", "Value is too short."));
+    } else if length > LOGIN_MAX_LENGTH {
+        errors.push(FieldError::new(field, "too_long", "Value is too long."));
+    } else if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+    {
+        errors.push(FieldError::new(
+            field,
+            "invalid_format",
+            "Only letters, digits, '.', '_' and '-' are allowed.",
+        ));
+    }
+}
+
+fn check_password(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    let length = value.chars().count();
+    let has_letter = value.chars().any(char::is_alphabetic);
+    let has_other = value.chars().any(|c| !c.is_alphabetic());
+    if length < PASSWORD_MIN_LENGTH {
+        errors.push(FieldError::new(
+            field,
+            "too_short",
+            "Password is too short.",
+        ));
+    } else if length > PASSWORD_MAX_LENGTH {
+        errors.push(FieldError::new(field, "too_long", "Password is too long."));
+    } else if !has_letter || !has_other {
+        errors.push(FieldError::new(
+            field,
+            "too_weak",
+            "Password must mix letters with digits or symbols.",
+        ));
+    }
+}
+
+//Lowercase names, they are referred to in configuration.
+fn check_role_name(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    if value.is_empty() {
+        errors.push(FieldError::new(field, "required", "Value is required."));
+    } else if value.chars().count() > ROLE_NAME_MAX_LENGTH {
+        errors.push(FieldError::new(field, "too_long", "Value is too long."));
+    } else if !value
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-".contains(c))
+    {
+        errors.push(FieldError::new(
+            field,
+            "invalid_format",
+            "Only lowercase letters, digits, '_' and '-' are allowed.",
+        ));
+    }
+}
+
+fn check_description(errors: &mut Vec<FieldError>, field: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        if value.chars().count() > DESCRIPTION_MAX_LENGTH {
+            errors.push(FieldError::new(field, "too_long", "Value is too long."));
+        }
+    }
+}
+
+impl Validate for CreateOrganizationCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "name", &self.name);
+        check_name(&mut errors, "admin.firstname", &self.admin.firstname);
+        check_name(&mut errors, "admin.lastname", &self.admin.lastname);
+        check_email(&mut errors, "admin.email", &self.admin.email);
+        check_phone(&mut errors, "admin.phone", &self.admin.phone);
+        check_login(&mut errors, "admin.login", &self.admin.login);
+        check_password(&mut errors, "admin.password", &self.admin.password);
+        errors
+    }
+}
+
+impl Validate for UpdateOrganizationCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "name", &self.name);
+        errors
+    }
+}
+
+impl Validate for CreatePersonCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "firstname", &self.firstname);
+        check_name(&mut errors, "lastname", &self.lastname);
+        check_email(&mut errors, "email", &self.email);
+        check_phone(&mut errors, "phone", &self.phone);
+        errors
+    }
+}
+
+impl Validate for UpdatePersonCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "firstname", &self.firstname);
+        check_name(&mut errors, "lastname", &self.lastname);
+        check_email(&mut errors, "email", &self.email);
+        check_phone(&mut errors, "phone", &self.phone);
+        errors
+    }
+}
+
+impl Validate for CreateUserCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_login(&mut errors, "login", &self.login);
+        check_password(&mut errors, "password", &self.password);
+        errors
+    }
+}
+
+impl Validate for RegisterAccountCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "firstname", &self.firstname);
+        check_name(&mut errors, "lastname", &self.lastname);
+        check_email(&mut errors, "email", &self.email);
+        check_phone(&mut errors, "phone", &self.phone);
+        check_login(&mut errors, "login", &self.login);
+        check_password(&mut errors, "password", &self.password);
+        errors
+    }
+}
+
+impl Validate for UpdateUserCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_login(&mut errors, "login", &self.login);
+        errors
+    }
+}
+
+impl Validate for ChangePasswordCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_password(&mut errors, "new_password", &self.new_password);
+        errors
+    }
+}
+
+impl Validate for RequestPasswordResetCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.login.trim().is_empty() {
+            errors.push(FieldError::new("login", "required", "Value is required."));
+        }
+        errors
+    }
+}
+
+impl Validate for ResetPasswordCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.token.trim().is_empty() {
+            errors.push(FieldError::new("token", "required", "Value is required."));
+        }
+        check_password(&mut errors, "new_password", &self.new_password);
+        errors
+    }
+}
+
+impl Validate for ChangeStatusCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_description(&mut errors, "reason", &self.reason);
+        let has_reason = self
+            .reason
+            .as_ref()
+            .is_some_and(|reason| !reason.trim().is_empty());
+        if self.status == AccountStatus::Suspended && !has_reason {
+            errors.push(FieldError::new("reason", "required", "Value is required."));
+        }
+        match self.suspended_until {
+            Some(_) if self.status != AccountStatus::Suspended => errors.push(FieldError::new(
+                "suspended_until",
+                "invalid",
+                "Only suspensions have an end date.",
+            )),
+            Some(until) if until <= Utc::now() => errors.push(FieldError::new(
+                "suspended_until",
+                "invalid",
+                "Date must be in the future.",
+            )),
+            _ => {}
+        }
+        errors
+    }
+}
+
+impl Validate for VerifyEmailCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.token.trim().is_empty() {
+            errors.push(FieldError::new("token", "required", "Value is required."));
+        }
+        errors
+    }
+}
+
+impl Validate for ConfirmTwoFactorCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.code.trim().is_empty() {
+            errors.push(FieldError::new("code", "required", "Value is required."));
+        }
+        errors
+    }
+}
+
+impl Validate for CreateGroupCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "name", &self.name);
+        check_description(&mut errors, "description", &self.description);
+        errors
+    }
+}
+
+impl Validate for UpdateGroupCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_name(&mut errors, "name", &self.name);
+        check_description(&mut errors, "description", &self.description);
+        if self.parent_uuid == Some(self.uuid) {
+            errors.push(FieldError::new(
+                "parent_uuid",
+                "invalid",
+                "A group cannot be its own parent.",
+            ));
+        }
+        errors
+    }
+}
+
+impl Validate for CreateRoleCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_role_name(&mut errors, "name", &self.name);
+        check_description(&mut errors, "description", &self.description);
+        errors
+    }
+}
+
+impl Validate for UpdateRoleCommand {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_role_name(&mut errors, "name", &self.name);
+        check_description(&mut errors, "description", &self.description);
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    //Codes raised by one rule, in order.
+    fn run(rule: fn(&mut Vec<FieldError>, &str, &str), value: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        rule(&mut errors, "field", value);
+        errors.into_iter().map(|error| error.code).collect()
+    }
+
+    fn phone(value: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        check_phone(&mut errors, "phone", &Some(value.to_string()));
+        errors.into_iter().map(|error| error.code).collect()
+    }
+
+    fn status(
+        status: AccountStatus,
+        reason: Option<&str>,
+        suspended_until: Option<DateTime<Utc>>,
+    ) -> Vec<String> {
+        let command = ChangeStatusCommand {
+            status,
+            reason: reason.map(str::to_string),
+            suspended_until,
+        };
+        command
+            .validate()
+            .into_iter()
+            .map(|error| format!("{}:{}", error.field, error.code))
+            .collect()
+    }
+
+    #[test]
+    fn email() {
+        assert!(run(check_email, "john.doe@helix.test").is_empty());
+        assert_eq!(run(check_email, " "), vec!["required"]);
+        for value in &[
+            "john.doe",
+            "@helix.test",
+            "john@doe@helix.test",
+            "john@helix",
+            "john@helix..test",
+            "john doe@helix.test",
+        ] {
+            assert_eq!(run(check_email, value), vec!["invalid_format"], "{}", value);
+        }
+        let long = format!("{}@helix.test", "a".repeat(EMAIL_MAX_LENGTH));
+        assert_eq!(run(check_email, &long), vec!["too_long"]);
+    }
+
+    #[test]
+    fn phone_number() {
+        assert!(phone("+33 6 12 34 56 78").is_empty());
+        assert!(phone("(555) 123-4567").is_empty());
+        assert!(phone("06.12.34.56.78").is_empty());
+        for value in &[
+            "++33612345678",
+            "+++",
+            "+",
+            "12345",
+            "0612345678x",
+            "1234567890123456",
+        ] {
+            assert_eq!(phone(value), vec!["invalid_format"], "{}", value);
+        }
+
+        let mut errors = Vec::new();
+        check_phone(&mut errors, "phone", &None);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn login() {
+        assert!(run(check_login, "john.doe-42_x").is_empty());
+        assert_eq!(run(check_login, "jd"), vec!["too_short"]);
+        assert_eq!(
+            run(check_login, &"j".repeat(LOGIN_MAX_LENGTH + 1)),
+            vec!["too_long"]
+        );
+        assert_eq!(run(check_login, "john doe"), vec!["invalid_format"]);
+        assert_eq!(run(check_login, "jöhn"), vec!["invalid_format"]);
+    }
+
+    #[test]
+    fn password() {
+        assert!(run(check_password, "Correct-Horse-42").is_empty());
+        assert!(run(check_password, "horses42").is_empty());
+        assert_eq!(run(check_password, "Hors3"), vec!["too_short"]);
+        assert_eq!(
+            run(check_password, &"a1".repeat(PASSWORD_MAX_LENGTH)),
+            vec!["too_long"]
+        );
+        assert_eq!(run(check_password, "onlyletters"), vec!["too_weak"]);
+        assert_eq!(run(check_password, "1234567890"), vec!["too_weak"]);
+    }
+
+    #[test]
+    fn role_name() {
+        assert!(run(check_role_name, "user_admin-2").is_empty());
+        assert_eq!(run(check_role_name, ""), vec!["required"]);
+        assert_eq!(run(check_role_name, "Admin"), vec!["invalid_format"]);
+        assert_eq!(run(check_role_name, "user admin"), vec!["invalid_format"]);
+        assert_eq!(
+            run(check_role_name, &"a".repeat(ROLE_NAME_MAX_LENGTH + 1)),
+            vec!["too_long"]
+        );
+    }
+
+    #[test]
+    fn change_status() {
+        let tomorrow = Some(Utc::now() + Duration::days(1));
+        let yesterday = Some(Utc::now() - Duration::days(1));
+
+        assert!(status(AccountStatus::Active, None, None).is_empty());
+        assert!(status(AccountStatus::Disabled, Some("Left"), None).is_empty());
+        assert!(status(AccountStatus::Suspended, Some("Abuse"), tomorrow).is_empty());
+        assert_eq!(
+            status(AccountStatus::Suspended, None, None),
+            vec!["reason:required"]
+        );
+        assert_eq!(
+            status(AccountStatus::Suspended, Some("  "), None),
+            vec!["reason:required"]
+        );
+        assert_eq!(
+            status(AccountStatus::Suspended, Some("Abuse"), yesterday),
+            vec!["suspended_until:invalid"]
+        );
+        assert_eq!(
+            status(AccountStatus::Active, None, tomorrow),
+            vec!["suspended_until:invalid"]
+        );
+        let reason = "a".repeat(DESCRIPTION_MAX_LENGTH + 1);
+        assert_eq!(
+            status(AccountStatus::Disabled, Some(&reason), None),
+            vec!["reason:too_long"]
+        );
+    }
+
+    #[test]
+    fn errors_are_gathered() {
+        let command = CreatePersonCommand {
+            firstname: "".to_string(),
+            lastname: "Doe".to_string(),
+            email: "john.doe".to_string(),
+            phone: Some("++33".to_string()),
+        };
+        match check(&command) {
+            Err(UserDomainError::Validation { errors }) => {
+                let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+                assert_eq!(fields, vec!["firstname", "email", "phone"]);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
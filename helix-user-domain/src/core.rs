@@ -1,2 +1,14 @@
 pub mod app_user;
+pub mod audit;
+pub mod command;
+pub mod group;
+pub mod login;
+pub mod organization;
+pub mod password_reset;
 pub mod person;
+pub mod query;
+pub mod role;
+pub mod session;
+pub mod token;
+pub mod two_factor;
+pub mod view;
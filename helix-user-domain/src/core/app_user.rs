@@ -2,6 +2,51 @@ use crate::core::person::Person;
 use chrono::prelude::*;
 use uuid;
 
+//Only active users may log in. Suspensions end by themselves once their date is over.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatus {
+    //Waiting for an administrator to activate it.
+    Pending,
+    #[default]
+    Active,
+    Suspended,
+    Disabled,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Pending => "pending",
+            AccountStatus::Active => "active",
+            AccountStatus::Suspended => "suspended",
+            AccountStatus::Disabled => "disabled",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AccountStatus> {
+        match name {
+            "pending" => Some(AccountStatus::Pending),
+            "active" => Some(AccountStatus::Active),
+            "suspended" => Some(AccountStatus::Suspended),
+            "disabled" => Some(AccountStatus::Disabled),
+            _ => None,
+        }
+    }
+
+    //Nothing goes back to pending, only active users are suspended,
+    //a suspension may be changed while it lasts.
+    pub fn can_become(&self, status: AccountStatus) -> bool {
+        match (self, status) {
+            (_, AccountStatus::Pending) => false,
+            (AccountStatus::Pending, AccountStatus::Suspended) => false,
+            (AccountStatus::Disabled, AccountStatus::Suspended) => false,
+            (AccountStatus::Suspended, _) => true,
+            (current, status) => *current != status,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppUser {
     pub id: i32,
@@ -13,6 +58,17 @@ pub struct AppUser {
     pub updated_on: Option<DateTime<Utc>>,
     pub last_login_on: Option<DateTime<Utc>>,
     pub person: Person,
+    #[serde(default)]
+    pub status: AccountStatus,
+    #[serde(default)]
+    pub status_reason: Option<String>,
+    #[serde(default)]
+    pub suspended_until: Option<DateTime<Utc>>,
+    //Deleted users are kept until purged, deleted_by is the uuid of the acting user.
+    #[serde(default)]
+    pub deleted_on: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deleted_by: Option<uuid::Uuid>,
 }
 
 impl AppUser {
@@ -37,6 +93,58 @@ impl AppUser {
             updated_on: updated_on,
             last_login_on: last_login_date,
             person: person,
+            status: AccountStatus::Active,
+            status_reason: None,
+            suspended_until: None,
+            deleted_on: None,
+            deleted_by: None,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_on.is_some()
+    }
+
+    //Suspensions without an end date last until lifted.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        match self.status {
+            AccountStatus::Active => true,
+            AccountStatus::Suspended => self.suspended_until.is_some_and(|until| until <= now),
+            _ => false,
+        }
+    }
+}
+
+//One status transition, changed_by is None when made by the service itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusChange {
+    pub id: i32,
+    pub user_id: i32,
+    pub previous_status: AccountStatus,
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub changed_by: Option<uuid::Uuid>,
+    pub changed_on: DateTime<Utc>,
+}
+
+impl StatusChange {
+    pub fn new(
+        user: &AppUser,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_until: Option<DateTime<Utc>>,
+        changed_by: Option<uuid::Uuid>,
+    ) -> StatusChange {
+        StatusChange {
+            id: 0,
+            user_id: user.id,
+            previous_status: user.status,
+            status,
+            reason,
+            suspended_until,
+            changed_by,
+            changed_on: Utc::now(),
         }
     }
 }
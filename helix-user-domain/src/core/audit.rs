@@ -0,0 +1,251 @@
+use chrono::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+
+//Values of these fields never reach the log, a change only shows that they moved.
+pub const REDACTED_FIELDS: [&str; 2] = ["password", "photo"];
+pub const REDACTED_VALUE: &str = "[redacted]";
+//Left out of diffs, the nested person of a user is audited on its own.
+const IGNORED_FIELDS: [&str; 4] = ["id", "updated_on", "person", "parent_id"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+    ChangeStatus,
+    ChangePassword,
+    ResetPassword,
+    Unlock,
+    VerifyEmail,
+    EnableTwoFactor,
+    ResetTwoFactor,
+    RevokeSessions,
+    AssignRole,
+    UnassignRole,
+    AddMember,
+    RemoveMember,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 17] = [
+        AuditAction::Create,
+        AuditAction::Update,
+        AuditAction::Delete,
+        AuditAction::Restore,
+        AuditAction::Purge,
+        AuditAction::ChangeStatus,
+        AuditAction::ChangePassword,
+        AuditAction::ResetPassword,
+        AuditAction::Unlock,
+        AuditAction::VerifyEmail,
+        AuditAction::EnableTwoFactor,
+        AuditAction::ResetTwoFactor,
+        AuditAction::RevokeSessions,
+        AuditAction::AssignRole,
+        AuditAction::UnassignRole,
+        AuditAction::AddMember,
+        AuditAction::RemoveMember,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::ChangeStatus => "change_status",
+            AuditAction::ChangePassword => "change_password",
+            AuditAction::ResetPassword => "reset_password",
+            AuditAction::Unlock => "unlock",
+            AuditAction::VerifyEmail => "verify_email",
+            AuditAction::EnableTwoFactor => "enable_two_factor",
+            AuditAction::ResetTwoFactor => "reset_two_factor",
+            AuditAction::RevokeSessions => "revoke_sessions",
+            AuditAction::AssignRole => "assign_role",
+            AuditAction::UnassignRole => "unassign_role",
+            AuditAction::AddMember => "add_member",
+            AuditAction::RemoveMember => "remove_member",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AuditAction> {
+        AuditAction::ALL
+            .iter()
+            .find(|action| action.as_str() == name)
+            .copied()
+    }
+}
+
+//Kind of record an audit entry is about.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditTarget {
+    Organization,
+    Person,
+    User,
+    Role,
+    Group,
+}
+
+impl AuditTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTarget::Organization => "organization",
+            AuditTarget::Person => "person",
+            AuditTarget::User => "user",
+            AuditTarget::Role => "role",
+            AuditTarget::Group => "group",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AuditTarget> {
+        match name {
+            "organization" => Some(AuditTarget::Organization),
+            "person" => Some(AuditTarget::Person),
+            "user" => Some(AuditTarget::User),
+            "role" => Some(AuditTarget::Role),
+            "group" => Some(AuditTarget::Group),
+            _ => None,
+        }
+    }
+}
+
+//Null stands for a field missing on one side, on creations and deletions.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl FieldChange {
+    pub fn new(field: &str, before: Value, after: Value) -> FieldChange {
+        FieldChange {
+            field: field.to_string(),
+            before,
+            after,
+        }
+    }
+
+    //Both sides of a secret that changed.
+    pub fn redacted(field: &str) -> FieldChange {
+        FieldChange::new(
+            field,
+            Value::from(REDACTED_VALUE),
+            Value::from(REDACTED_VALUE),
+        )
+    }
+
+    //A link to another record, like a role or a member.
+    pub fn added<T: Serialize>(field: &str, value: T) -> FieldChange {
+        FieldChange::new(field, Value::Null, serde_json::json!(value))
+    }
+
+    pub fn removed<T: Serialize>(field: &str, value: T) -> FieldChange {
+        FieldChange::new(field, serde_json::json!(value), Value::Null)
+    }
+
+    //Top level fields that differ between two states of a record.
+    pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Vec<FieldChange> {
+        let before = FieldChange::to_fields(before);
+        let after = FieldChange::to_fields(after);
+
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        fields
+            .into_iter()
+            .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+            .filter_map(|field| {
+                let old = before.get(field).cloned().unwrap_or(Value::Null);
+                let new = after.get(field).cloned().unwrap_or(Value::Null);
+                match (old == new, REDACTED_FIELDS.contains(&field.as_str())) {
+                    (true, _) => None,
+                    (false, false) => Some(FieldChange::new(field, old, new)),
+                    (false, true) => Some(FieldChange::new(
+                        field,
+                        FieldChange::redact(old),
+                        FieldChange::redact(new),
+                    )),
+                }
+            })
+            .collect()
+    }
+
+    fn to_fields<T: Serialize>(record: Option<&T>) -> serde_json::Map<String, Value> {
+        match record.map(serde_json::to_value) {
+            Some(Ok(Value::Object(fields))) => fields,
+            _ => serde_json::Map::new(),
+        }
+    }
+
+    fn redact(value: Value) -> Value {
+        match value {
+            Value::Null => Value::Null,
+            Value::String(text) if text.is_empty() => Value::Null,
+            _ => Value::from(REDACTED_VALUE),
+        }
+    }
+}
+
+//Who is behind a mutation and which request carried it.
+//The actor is None for the service itself, like the purge job or the admin bootstrap.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuditContext {
+    pub actor: Option<uuid::Uuid>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn new(actor: Option<uuid::Uuid>, request_id: Option<String>) -> AuditContext {
+        AuditContext { actor, request_id }
+    }
+
+    //Anonymous callers holding a token act for the user it was issued to.
+    pub fn or_actor(&self, actor: Option<uuid::Uuid>) -> AuditContext {
+        AuditContext {
+            actor: self.actor.or(actor),
+            request_id: self.request_id.clone(),
+        }
+    }
+}
+
+//One mutation, actor and target are uuids so entries outlive what they describe.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub actor: Option<uuid::Uuid>,
+    pub action: AuditAction,
+    pub target: AuditTarget,
+    pub target_uuid: Option<uuid::Uuid>,
+    pub changes: Vec<FieldChange>,
+    pub request_id: Option<String>,
+    pub occurred_on: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        context: &AuditContext,
+        action: AuditAction,
+        target: AuditTarget,
+        target_uuid: Option<uuid::Uuid>,
+        changes: Vec<FieldChange>,
+    ) -> AuditEntry {
+        AuditEntry {
+            id: 0,
+            actor: context.actor,
+            action,
+            target,
+            target_uuid,
+            changes,
+            request_id: context.request_id.clone(),
+            occurred_on: Utc::now(),
+        }
+    }
+}
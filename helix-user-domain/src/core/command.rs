@@ -0,0 +1,153 @@
+use crate::core::app_user::AccountStatus;
+use crate::core::role::Permission;
+use chrono::prelude::*;
+use uuid;
+
+//First account of a new organization, granted its admin role.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrganizationAdminCommand {
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub login: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateOrganizationCommand {
+    pub name: String,
+    pub admin: OrganizationAdminCommand,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateOrganizationCommand {
+    pub uuid: uuid::Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreatePersonCommand {
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdatePersonCommand {
+    pub uuid: uuid::Uuid,
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub phone: Option<String>,
+}
+
+//The password is plaintext here, hashed by the domain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateUserCommand {
+    pub login: String,
+    pub password: String,
+    pub photo: Option<Vec<u8>>,
+    pub person_uuid: uuid::Uuid,
+}
+
+//A person and its user created together, the password is plaintext here too.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegisterAccountCommand {
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub login: String,
+    pub password: String,
+    pub photo: Option<Vec<u8>>,
+}
+
+//Passwords are only changed through ChangePasswordCommand.
+//A missing photo keeps the stored one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateUserCommand {
+    pub uuid: uuid::Uuid,
+    pub login: String,
+    pub photo: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangePasswordCommand {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+//The login, or the email of the person, of a user who forgot its password.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RequestPasswordResetCommand {
+    pub login: String,
+}
+
+//Token received through the notifier.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResetPasswordCommand {
+    pub token: String,
+    pub new_password: String,
+}
+
+//Suspensions need a reason and last until lifted when no date is given.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeStatusCommand {
+    pub status: AccountStatus,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+//Token received through the notifier, at the address to verify.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifyEmailCommand {
+    pub token: String,
+}
+
+//Code of the authenticator app, confirming its enrollment.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConfirmTwoFactorCommand {
+    pub code: String,
+}
+
+//Second step of a login, the code is a TOTP or a recovery code.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifyLoginCommand {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateGroupCommand {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_uuid: Option<uuid::Uuid>,
+}
+
+//A missing parent makes it a top level group.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateGroupCommand {
+    pub uuid: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_uuid: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateRoleCommand {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateRoleCommand {
+    pub uuid: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+}
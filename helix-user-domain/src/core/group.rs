@@ -0,0 +1,41 @@
+use chrono::prelude::*;
+use uuid;
+
+//Members of a group are members of its parent groups too.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Group {
+    pub id: i32,
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_id: Option<i32>,
+    //Filled by storage when reading.
+    pub parent_uuid: Option<uuid::Uuid>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl Group {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        uuid: Option<uuid::Uuid>,
+        name: String,
+        description: Option<String>,
+        parent_id: Option<i32>,
+        parent_uuid: Option<uuid::Uuid>,
+        created_on: Option<DateTime<Utc>>,
+        updated_on: Option<DateTime<Utc>>,
+    ) -> Group {
+        Group {
+            id,
+            uuid,
+            name,
+            description,
+            parent_id,
+            parent_uuid,
+            created_on,
+            updated_on,
+        }
+    }
+}
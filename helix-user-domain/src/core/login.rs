@@ -0,0 +1,130 @@
+use chrono::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginChannel {
+    Rest,
+    Grpc,
+}
+
+impl LoginChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginChannel::Rest => "rest",
+            LoginChannel::Grpc => "grpc",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LoginChannel> {
+        match name {
+            "rest" => Some(LoginChannel::Rest),
+            "grpc" => Some(LoginChannel::Grpc),
+            _ => None,
+        }
+    }
+}
+
+//Where a login attempt comes from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginContext {
+    pub channel: LoginChannel,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl LoginContext {
+    pub fn new(
+        channel: LoginChannel,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> LoginContext {
+        LoginContext {
+            channel,
+            ip,
+            user_agent,
+        }
+    }
+}
+
+//One authentication attempt, user_id is None for unknown logins.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginEvent {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub login: String,
+    pub occurred_on: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub success: bool,
+    pub channel: LoginChannel,
+}
+
+impl LoginEvent {
+    pub fn new(
+        user_id: Option<i32>,
+        login: String,
+        context: &LoginContext,
+        success: bool,
+    ) -> LoginEvent {
+        LoginEvent {
+            id: 0,
+            user_id,
+            login,
+            occurred_on: Utc::now(),
+            ip: context.ip.clone(),
+            user_agent: context.user_agent.clone(),
+            success,
+            channel: context.channel,
+        }
+    }
+}
+
+//Failed attempts are counted per login and per client IP.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum CounterScope {
+    Login,
+    Ip,
+}
+
+impl CounterScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CounterScope::Login => "login",
+            CounterScope::Ip => "ip",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CounterScope> {
+        match name {
+            "login" => Some(CounterScope::Login),
+            "ip" => Some(CounterScope::Ip),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginCounter {
+    pub scope: CounterScope,
+    //Lowercased login or client IP.
+    pub key: String,
+    pub failures: i32,
+    pub last_failure_on: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl LoginCounter {
+    pub fn new(scope: CounterScope, key: String) -> LoginCounter {
+        LoginCounter {
+            scope,
+            key,
+            failures: 0,
+            last_failure_on: Utc::now(),
+            locked_until: None,
+        }
+    }
+
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::prelude::*;
+use uuid;
+
+//Tenant of the user store, persons, users and all attached to them belong to one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Organization {
+    pub id: i32,
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl Organization {
+    //Holds the data created before organizations existed, used when a request names none.
+    pub const DEFAULT_UUID: uuid::Uuid = uuid::Uuid::nil();
+
+    pub fn new(
+        id: i32,
+        uuid: Option<uuid::Uuid>,
+        name: String,
+        created_on: Option<DateTime<Utc>>,
+        updated_on: Option<DateTime<Utc>>,
+    ) -> Organization {
+        Organization {
+            id,
+            uuid,
+            name,
+            created_on,
+            updated_on,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.uuid == Some(Organization::DEFAULT_UUID)
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::prelude::*;
+
+//Single use token sent to a user who forgot its password, only its digest is stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordReset {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub created_on: DateTime<Utc>,
+    pub expires_on: DateTime<Utc>,
+    pub used_on: Option<DateTime<Utc>>,
+}
+
+impl PasswordReset {
+    pub fn new(user_id: i32, token_hash: String, expires_on: DateTime<Utc>) -> PasswordReset {
+        PasswordReset {
+            id: 0,
+            user_id,
+            token_hash,
+            created_on: Utc::now(),
+            expires_on,
+            used_on: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_on <= now
+    }
+}
@@ -1,6 +1,35 @@
 use chrono::prelude::*;
 use uuid;
 
+//Pending once a verification token is sent, verified when it comes back.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailStatus {
+    #[default]
+    Unverified,
+    Pending,
+    Verified,
+}
+
+impl EmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailStatus::Unverified => "unverified",
+            EmailStatus::Pending => "pending",
+            EmailStatus::Verified => "verified",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EmailStatus> {
+        match name {
+            "unverified" => Some(EmailStatus::Unverified),
+            "pending" => Some(EmailStatus::Pending),
+            "verified" => Some(EmailStatus::Verified),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Person {
     pub id: i32,
@@ -11,6 +40,15 @@ pub struct Person {
     pub phone: Option<String>,
     pub created_on: Option<DateTime<Utc>>,
     pub updated_on: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub email_status: EmailStatus,
+    #[serde(default)]
+    pub email_verified_on: Option<DateTime<Utc>>,
+    //Deleted persons are kept until purged, deleted_by is the uuid of the acting user.
+    #[serde(default)]
+    pub deleted_on: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deleted_by: Option<uuid::Uuid>,
 }
 
 impl Person {
@@ -33,6 +71,52 @@ impl Person {
             phone: phone,
             created_on: created_on,
             updated_on: updated_on,
+            email_status: EmailStatus::Unverified,
+            email_verified_on: None,
+            deleted_on: None,
+            deleted_by: None,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_on.is_some()
+    }
+
+    pub fn is_email_verified(&self) -> bool {
+        self.email_status == EmailStatus::Verified
+    }
+}
+
+//Single use token sent to an email address, only its digest is stored.
+//It only verifies the address it was sent to, not a later one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailVerification {
+    pub id: i32,
+    pub person_id: i32,
+    pub email: String,
+    pub token_hash: String,
+    pub created_on: DateTime<Utc>,
+    pub expires_on: DateTime<Utc>,
+}
+
+impl EmailVerification {
+    pub fn new(
+        person_id: i32,
+        email: String,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> EmailVerification {
+        EmailVerification {
+            id: 0,
+            person_id,
+            email,
+            token_hash,
+            created_on: Utc::now(),
+            expires_on,
         }
     }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_on <= now
+    }
 }
@@ -0,0 +1,271 @@
+use crate::core::audit::{AuditAction, AuditTarget};
+use chrono::prelude::*;
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 500;
+//Deeper windows are refused, cursors and page numbers come from clients.
+pub const MAX_OFFSET: u32 = 1_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn from_name(name: &str) -> Option<SortDirection> {
+        match name.to_lowercase().as_str() {
+            "asc" => Some(SortDirection::Asc),
+            "desc" => Some(SortDirection::Desc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    Login,
+    Email,
+    Firstname,
+    Lastname,
+    CreatedOn,
+}
+
+impl UserSortField {
+    pub fn from_name(name: &str) -> Option<UserSortField> {
+        match name.to_lowercase().as_str() {
+            "login" => Some(UserSortField::Login),
+            "email" => Some(UserSortField::Email),
+            "firstname" => Some(UserSortField::Firstname),
+            "lastname" => Some(UserSortField::Lastname),
+            "created_on" => Some(UserSortField::CreatedOn),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PersonSortField {
+    #[default]
+    Firstname,
+    Lastname,
+    Email,
+    CreatedOn,
+}
+
+impl PersonSortField {
+    pub fn from_name(name: &str) -> Option<PersonSortField> {
+        match name.to_lowercase().as_str() {
+            "firstname" => Some(PersonSortField::Firstname),
+            "lastname" => Some(PersonSortField::Lastname),
+            "email" => Some(PersonSortField::Email),
+            "created_on" => Some(PersonSortField::CreatedOn),
+            _ => None,
+        }
+    }
+}
+
+//Either a cursor or a page number (starting at 1) selects the window.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Pagination {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub page: Option<u32>,
+}
+
+impl Pagination {
+    pub fn get_limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    //None when the cursor cannot be decoded or the page is out of reach.
+    pub fn get_offset(&self) -> Option<u32> {
+        match (&self.cursor, self.page) {
+            (Some(cursor), _) => Cursor::decode(cursor),
+            (None, Some(page)) => (page.max(1) - 1)
+                .checked_mul(self.get_limit())
+                .filter(|offset| *offset <= MAX_OFFSET),
+            (None, None) => Some(0),
+        }
+    }
+}
+
+//Filters are case insensitive prefixes, dates are inclusive bounds.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UserQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub page: Option<u32>,
+    pub sort: UserSortField,
+    pub direction: SortDirection,
+    pub login: Option<String>,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    //Deleted users are left out unless asked for.
+    pub include_deleted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PersonQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub page: Option<u32>,
+    pub sort: PersonSortField,
+    pub direction: SortDirection,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub include_deleted: bool,
+}
+
+impl UserQuery {
+    pub fn get_pagination(&self) -> Pagination {
+        Pagination {
+            limit: self.limit,
+            cursor: self.cursor.clone(),
+            page: self.page,
+        }
+    }
+}
+
+impl PersonQuery {
+    pub fn get_pagination(&self) -> Pagination {
+        Pagination {
+            limit: self.limit,
+            cursor: self.cursor.clone(),
+            page: self.page,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, offset: u32, limit: u32) -> Page<T> {
+        let next_cursor = offset
+            .checked_add(limit)
+            .filter(|next_offset| *next_offset <= MAX_OFFSET && (*next_offset as i64) < total)
+            .map(Cursor::encode);
+
+        Page {
+            items,
+            total,
+            next_cursor,
+        }
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+//Entries come newest first, dates are inclusive bounds.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuditQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub page: Option<u32>,
+    pub actor: Option<uuid::Uuid>,
+    pub action: Option<AuditAction>,
+    pub target: Option<AuditTarget>,
+    pub target_uuid: Option<uuid::Uuid>,
+    pub request_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditQuery {
+    pub fn get_pagination(&self) -> Pagination {
+        Pagination {
+            limit: self.limit,
+            cursor: self.cursor.clone(),
+            page: self.page,
+        }
+    }
+}
+
+//Reads of a single record leave deleted ones out unless asked for.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DeletedQuery {
+    pub include_deleted: bool,
+}
+
+//Opaque cursor, clients must not build it themselves.
+pub struct Cursor {}
+
+impl Cursor {
+    pub fn encode(offset: u32) -> String {
+        base64::encode_config(format!("offset:{}", offset), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Option<u32> {
+        let decoded = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        decoded
+            .strip_prefix("offset:")?
+            .parse()
+            .ok()
+            .filter(|offset| *offset <= MAX_OFFSET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page: u32, limit: u32) -> Option<u32> {
+        Pagination {
+            limit: Some(limit),
+            cursor: None,
+            page: Some(page),
+        }
+        .get_offset()
+    }
+
+    #[test]
+    fn page_offset() {
+        assert_eq!(page(0, 10), Some(0));
+        assert_eq!(page(3, 10), Some(20));
+        assert_eq!(page(u32::MAX, MAX_LIMIT), None);
+        assert_eq!(page(MAX_OFFSET / 10 + 2, 10), None);
+    }
+
+    #[test]
+    fn cursor_offset() {
+        assert_eq!(Cursor::decode(&Cursor::encode(150)), Some(150));
+        assert_eq!(Cursor::decode(&Cursor::encode(MAX_OFFSET + 1)), None);
+        let forged = base64::encode_config(format!("offset:{}", u32::MAX), base64::URL_SAFE_NO_PAD);
+        assert_eq!(Cursor::decode(&forged), None);
+        assert_eq!(Cursor::decode("not a cursor"), None);
+    }
+
+    #[test]
+    fn next_cursor() {
+        let next = |offset, total| Page::new(Vec::<()>::new(), total, offset, 10).next_cursor;
+        assert_eq!(next(0, 25).as_deref().and_then(Cursor::decode), Some(10));
+        assert_eq!(next(20, 25), None);
+        assert_eq!(next(u32::MAX, i64::MAX), None);
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::prelude::*;
+use uuid;
+
+//Granted when bootstrapping, holds every permission.
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    PersonsRead,
+    PersonsWrite,
+    PersonsDelete,
+    UsersRead,
+    UsersWrite,
+    UsersDelete,
+    //Manage the sessions of other users.
+    SessionsManage,
+    //Manage roles and their assignments.
+    RolesManage,
+    GroupsRead,
+    //Manage groups and their members.
+    GroupsManage,
+    //Read the audit log of the organization.
+    AuditRead,
+    //Manage all organizations, only from the default one.
+    OrganizationsManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 12] = [
+        Permission::PersonsRead,
+        Permission::PersonsWrite,
+        Permission::PersonsDelete,
+        Permission::UsersRead,
+        Permission::UsersWrite,
+        Permission::UsersDelete,
+        Permission::SessionsManage,
+        Permission::RolesManage,
+        Permission::GroupsRead,
+        Permission::GroupsManage,
+        Permission::AuditRead,
+        Permission::OrganizationsManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::PersonsRead => "persons_read",
+            Permission::PersonsWrite => "persons_write",
+            Permission::PersonsDelete => "persons_delete",
+            Permission::UsersRead => "users_read",
+            Permission::UsersWrite => "users_write",
+            Permission::UsersDelete => "users_delete",
+            Permission::SessionsManage => "sessions_manage",
+            Permission::RolesManage => "roles_manage",
+            Permission::GroupsRead => "groups_read",
+            Permission::GroupsManage => "groups_manage",
+            Permission::AuditRead => "audit_read",
+            Permission::OrganizationsManage => "organizations_manage",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Permission> {
+        Permission::ALL
+            .iter()
+            .find(|permission| permission.as_str() == name)
+            .copied()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Role {
+    pub id: i32,
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl Role {
+    pub fn new(
+        id: i32,
+        uuid: Option<uuid::Uuid>,
+        name: String,
+        description: Option<String>,
+        permissions: Vec<Permission>,
+        created_on: Option<DateTime<Utc>>,
+        updated_on: Option<DateTime<Utc>>,
+    ) -> Role {
+        Role {
+            id,
+            uuid,
+            name,
+            description,
+            permissions,
+            created_on,
+            updated_on,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::core::login::LoginContext;
+use chrono::prelude::*;
+
+//A login on one device, its refresh tokens use the session uuid as family.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub id: i32,
+    pub uuid: Option<uuid::Uuid>,
+    //Set by storage, access tokens resolve to the organization of their session.
+    pub organization_id: i32,
+    pub user_id: i32,
+    //Free label given by the client at login.
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_on: DateTime<Utc>,
+    pub refreshed_on: Option<DateTime<Utc>>,
+    pub expires_on: DateTime<Utc>,
+    //Digest of the last access token issued, to resolve the caller.
+    pub access_token_hash: Option<String>,
+    pub access_expires_on: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn new(
+        user_id: i32,
+        device: Option<String>,
+        context: &LoginContext,
+        expires_on: DateTime<Utc>,
+    ) -> Session {
+        Session {
+            id: 0,
+            uuid: None,
+            organization_id: 0,
+            user_id,
+            device,
+            ip: context.ip.clone(),
+            user_agent: context.user_agent.clone(),
+            created_on: Utc::now(),
+            refreshed_on: None,
+            expires_on,
+            access_token_hash: None,
+            access_expires_on: None,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use chrono::prelude::*;
+
+//Refresh tokens are opaque, only their SHA-256 digest is stored.
+//A family groups the successive rotations of the token issued at login, it is the session uuid.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub family: uuid::Uuid,
+    pub token_hash: String,
+    pub created_on: DateTime<Utc>,
+    pub expires_on: DateTime<Utc>,
+    //Set once exchanged, presenting it again is a reuse.
+    pub rotated_on: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    pub fn new(
+        user_id: i32,
+        family: uuid::Uuid,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> RefreshToken {
+        RefreshToken {
+            id: 0,
+            user_id,
+            family,
+            token_hash,
+            created_on: Utc::now(),
+            expires_on,
+            rotated_on: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_on <= now
+    }
+}
+
+//Handed out at login and on each refresh.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+}
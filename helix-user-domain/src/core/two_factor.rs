@@ -0,0 +1,113 @@
+use crate::core::token::TokenPair;
+use chrono::prelude::*;
+
+//TOTP second factor of a user, pending until a first code confirms the enrollment.
+//The secret is needed as is to check codes, it is never handed out again once confirmed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoFactor {
+    pub user_id: i32,
+    //Base32, as shown to authenticator apps.
+    pub secret: String,
+    pub created_on: DateTime<Utc>,
+    pub confirmed_on: Option<DateTime<Utc>>,
+    //Time step of the last accepted code, a code is only accepted once.
+    pub last_used_step: Option<i64>,
+}
+
+impl TwoFactor {
+    pub fn new(user_id: i32, secret: String) -> TwoFactor {
+        TwoFactor {
+            user_id,
+            secret,
+            created_on: Utc::now(),
+            confirmed_on: None,
+            last_used_step: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.confirmed_on.is_some()
+    }
+}
+
+//Single use codes replacing a lost authenticator, only their SHA-256 digest is stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub user_id: i32,
+    pub code_hash: String,
+    pub used_on: Option<DateTime<Utc>>,
+}
+
+//Issued once the password of a user with a second factor is checked,
+//exchanged for a session with a valid code. Only its digest is stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginChallenge {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    //Label given at login, for the session opened afterwards.
+    pub device: Option<String>,
+    pub created_on: DateTime<Utc>,
+    pub expires_on: DateTime<Utc>,
+}
+
+impl LoginChallenge {
+    pub fn new(
+        user_id: i32,
+        token_hash: String,
+        device: Option<String>,
+        expires_on: DateTime<Utc>,
+    ) -> LoginChallenge {
+        LoginChallenge {
+            id: 0,
+            user_id,
+            token_hash,
+            device,
+            created_on: Utc::now(),
+            expires_on,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_on <= now
+    }
+}
+
+//Second factor state of a user, without its secret.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub confirmed_on: Option<DateTime<Utc>>,
+    pub recovery_codes_left: usize,
+}
+
+//Handed out instead of tokens when a second factor is required.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoFactorChallenge {
+    pub challenge_token: String,
+    pub expires_on: DateTime<Utc>,
+}
+
+//Outcome of a checked password, told apart by their fields once serialized.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum LoginStep {
+    Tokens(TokenPair),
+    Challenge(TwoFactorChallenge),
+}
+
+//Shown once when enrolling, to be scanned or typed in an authenticator app.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+    //SVG image encoding the otpauth URI.
+    pub qr_code: String,
+}
+
+//Shown once when confirming an enrollment.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
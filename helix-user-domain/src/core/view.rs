@@ -0,0 +1,265 @@
+use crate::core::app_user::{AccountStatus, AppUser, StatusChange};
+use crate::core::audit::{AuditAction, AuditEntry, AuditTarget, FieldChange};
+use crate::core::group::Group;
+use crate::core::login::{LoginChannel, LoginEvent};
+use crate::core::organization::Organization;
+use crate::core::person::{EmailStatus, Person};
+use crate::core::role::{Permission, Role};
+use crate::core::session::Session;
+use chrono::prelude::*;
+use uuid;
+
+//Photos are served apart, views only carry their location.
+pub const USER_PHOTO_URL: &str = "/api/users/{uuid}/photo";
+
+//Public representation of a person, internal ids stay in storage.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonView {
+    pub uuid: Option<uuid::Uuid>,
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+    pub email_status: EmailStatus,
+    pub email_verified_on: Option<DateTime<Utc>>,
+    //Only set when deleted records are asked for.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted_on: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted_by: Option<uuid::Uuid>,
+}
+
+impl From<Person> for PersonView {
+    fn from(person: Person) -> Self {
+        PersonView {
+            uuid: person.uuid,
+            firstname: person.firstname,
+            lastname: person.lastname,
+            email: person.email,
+            phone: person.phone,
+            created_on: person.created_on,
+            updated_on: person.updated_on,
+            email_status: person.email_status,
+            email_verified_on: person.email_verified_on,
+            deleted_on: person.deleted_on,
+            deleted_by: person.deleted_by,
+        }
+    }
+}
+
+//Public representation of a user, never carries password material.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserView {
+    pub uuid: Option<uuid::Uuid>,
+    pub login: String,
+    pub photo_url: Option<String>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+    pub last_login_on: Option<DateTime<Utc>>,
+    pub status: AccountStatus,
+    pub status_reason: Option<String>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted_on: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deleted_by: Option<uuid::Uuid>,
+    pub person: PersonView,
+    //Names of the effective groups, only loaded when reading a single user.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub groups: Option<Vec<String>>,
+}
+
+impl UserView {
+    pub fn with_groups(mut self, groups: Vec<Group>) -> UserView {
+        self.groups = Some(groups.into_iter().map(|group| group.name).collect());
+        self
+    }
+}
+
+impl From<AppUser> for UserView {
+    fn from(user: AppUser) -> Self {
+        let photo_url = match (&user.photo, &user.uuid) {
+            (Some(_), Some(uuid)) => Some(USER_PHOTO_URL.replace("{uuid}", &uuid.to_string())),
+            _ => None,
+        };
+
+        UserView {
+            uuid: user.uuid,
+            login: user.login,
+            photo_url,
+            created_on: user.created_on,
+            updated_on: user.updated_on,
+            last_login_on: user.last_login_on,
+            status: user.status,
+            status_reason: user.status_reason,
+            suspended_until: user.suspended_until,
+            deleted_on: user.deleted_on,
+            deleted_by: user.deleted_by,
+            person: user.person.into(),
+            groups: None,
+        }
+    }
+}
+
+//changed_by is the uuid of the acting user, None for the service itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusChangeView {
+    pub previous_status: AccountStatus,
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub changed_by: Option<uuid::Uuid>,
+    pub changed_on: DateTime<Utc>,
+}
+
+impl From<StatusChange> for StatusChangeView {
+    fn from(change: StatusChange) -> Self {
+        StatusChangeView {
+            previous_status: change.previous_status,
+            status: change.status,
+            reason: change.reason,
+            suspended_until: change.suspended_until,
+            changed_by: change.changed_by,
+            changed_on: change.changed_on,
+        }
+    }
+}
+
+//Secrets are redacted before entries are stored, views carry them as they are.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntryView {
+    pub actor: Option<uuid::Uuid>,
+    pub action: AuditAction,
+    pub target: AuditTarget,
+    pub target_uuid: Option<uuid::Uuid>,
+    pub changes: Vec<FieldChange>,
+    pub request_id: Option<String>,
+    pub occurred_on: DateTime<Utc>,
+}
+
+impl From<AuditEntry> for AuditEntryView {
+    fn from(entry: AuditEntry) -> Self {
+        AuditEntryView {
+            actor: entry.actor,
+            action: entry.action,
+            target: entry.target,
+            target_uuid: entry.target_uuid,
+            changes: entry.changes,
+            request_id: entry.request_id,
+            occurred_on: entry.occurred_on,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginEventView {
+    pub occurred_on: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub success: bool,
+    pub channel: LoginChannel,
+}
+
+impl From<LoginEvent> for LoginEventView {
+    fn from(event: LoginEvent) -> Self {
+        LoginEventView {
+            occurred_on: event.occurred_on,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            success: event.success,
+            channel: event.channel,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionView {
+    pub uuid: Option<uuid::Uuid>,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_on: DateTime<Utc>,
+    pub refreshed_on: Option<DateTime<Utc>>,
+    pub expires_on: DateTime<Utc>,
+}
+
+impl From<Session> for SessionView {
+    fn from(session: Session) -> Self {
+        SessionView {
+            uuid: session.uuid,
+            device: session.device,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_on: session.created_on,
+            refreshed_on: session.refreshed_on,
+            expires_on: session.expires_on,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoleView {
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl From<Role> for RoleView {
+    fn from(role: Role) -> Self {
+        RoleView {
+            uuid: role.uuid,
+            name: role.name,
+            description: role.description,
+            permissions: role.permissions,
+            created_on: role.created_on,
+            updated_on: role.updated_on,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupView {
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_uuid: Option<uuid::Uuid>,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl From<Group> for GroupView {
+    fn from(group: Group) -> Self {
+        GroupView {
+            uuid: group.uuid,
+            name: group.name,
+            description: group.description,
+            parent_uuid: group.parent_uuid,
+            created_on: group.created_on,
+            updated_on: group.updated_on,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrganizationView {
+    pub uuid: Option<uuid::Uuid>,
+    pub name: String,
+    pub created_on: Option<DateTime<Utc>>,
+    pub updated_on: Option<DateTime<Utc>>,
+}
+
+impl From<Organization> for OrganizationView {
+    fn from(organization: Organization) -> Self {
+        OrganizationView {
+            uuid: organization.uuid,
+            name: organization.name,
+            created_on: organization.created_on,
+            updated_on: organization.updated_on,
+        }
+    }
+}
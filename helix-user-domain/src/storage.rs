@@ -1,2 +1,4 @@
+#[cfg(feature = "conformance")]
+pub mod conformance;
 pub mod error;
 pub mod traits;
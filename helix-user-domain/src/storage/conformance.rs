@@ -0,0 +1,1856 @@
+//Shared checks every StorageTrait implementation must pass.
+//Records use random names so the suite can run against a shared database.
+use crate::core::app_user::*;
+use crate::core::audit::*;
+use crate::core::group::Group;
+use crate::core::login::*;
+use crate::core::organization::Organization;
+use crate::core::password_reset::PasswordReset;
+use crate::core::person::*;
+use crate::core::query::*;
+use crate::core::role::*;
+use crate::core::session::Session;
+use crate::core::token::RefreshToken;
+use crate::core::two_factor::*;
+use crate::storage::error::*;
+use crate::storage::traits::StorageTrait;
+use chrono::prelude::*;
+use chrono::Duration;
+
+fn unique_name(prefix: &str) -> String {
+    format!("{}_{}", prefix, uuid::Uuid::new_v4().to_simple())
+}
+
+fn new_person() -> Person {
+    let name = unique_name("person");
+    Person::new(
+        0,
+        None,
+        name.clone(),
+        "Conformance".to_string(),
+        format!("{}@helix.test", name),
+        Some("0102030405".to_string()),
+        None,
+        None,
+    )
+}
+
+fn new_user(person: Person) -> AppUser {
+    AppUser::new(
+        0,
+        None,
+        unique_name("login"),
+        "$argon2id$conformance".to_string(),
+        None,
+        None,
+        None,
+        None,
+        person,
+    )
+}
+
+async fn get_default_organization_id(storage: &dyn StorageTrait) -> i32 {
+    storage
+        .get_organization_by_uuid(&Organization::DEFAULT_UUID)
+        .await
+        .unwrap()
+        .expect("default organization must exist")
+        .id
+}
+
+pub async fn check_organizations(storage: &dyn StorageTrait) {
+    let default_id = get_default_organization_id(storage).await;
+    let organization = Organization::new(0, None, unique_name("organization"), None, None);
+    let organization = storage.create_organization(organization).await.unwrap();
+    assert!(organization.id > 0, "id must be assigned");
+    assert!(organization.uuid.is_some(), "uuid must be assigned");
+    assert!(organization.created_on.is_some(), "created_on must be set");
+
+    let by_uuid = storage
+        .get_organization_by_uuid(&organization.uuid.unwrap())
+        .await
+        .unwrap()
+        .expect("organization must be found by uuid");
+    assert_eq!(by_uuid.id, organization.id);
+    assert!(storage
+        .get_all_organizations()
+        .await
+        .unwrap()
+        .iter()
+        .any(|listed| listed.id == organization.id));
+
+    let duplicate = Organization::new(0, None, organization.name.to_uppercase(), None, None);
+    match storage.create_organization(duplicate).await {
+        Err(StorageError::Conflict { field }) => assert_eq!(field, "name"),
+        other => panic!("duplicate name must conflict, got {:?}", other.map(|_| ())),
+    }
+
+    let mut renamed = by_uuid.clone();
+    renamed.name = unique_name("renamed");
+    storage.update_organization(renamed.clone()).await.unwrap();
+    let reloaded = storage
+        .get_organization_by_id(organization.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.name, renamed.name);
+    assert!(reloaded.updated_on.is_some(), "updated_on must be set");
+
+    //The same login and email can be used once per organization.
+    let person = storage
+        .create_person(default_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(default_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let mut other_person = new_person();
+    other_person.email = person.email.to_uppercase();
+    let other_person = storage
+        .create_person(organization.id, other_person)
+        .await
+        .unwrap();
+    let mut other_user = new_user(other_person.clone());
+    other_user.login = user.login.to_uppercase();
+    let other_user = storage
+        .create_user(organization.id, other_user)
+        .await
+        .unwrap();
+
+    let found = storage
+        .get_user_by_login(organization.id, &user.login)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, other_user.id);
+    let found = storage
+        .get_person_by_email(default_id, &person.email)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, person.id);
+
+    //Rows of another organization are not found, not even by id.
+    assert!(storage
+        .get_user(organization.id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_user_by_id(organization.id, user.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_person_by_id(default_id, other_person.id)
+        .await
+        .unwrap()
+        .is_none());
+    let query = UserQuery {
+        login: Some(user.login.clone()),
+        ..UserQuery::default()
+    };
+    let listed = storage.get_all_users(default_id, &query).await.unwrap();
+    assert_eq!(listed.total, 1);
+    assert_eq!(listed.items[0].id, user.id);
+
+    //Access tokens resolve to the organization of their session.
+    let context = LoginContext::new(LoginChannel::Rest, None, None);
+    let mut session = Session::new(
+        other_user.id,
+        None,
+        &context,
+        Utc::now() + Duration::hours(1),
+    );
+    session.access_token_hash = Some(unique_name("access"));
+    let session = storage
+        .create_session(organization.id, session)
+        .await
+        .unwrap();
+    let by_access_token = storage
+        .get_session_by_access_token(session.access_token_hash.as_ref().unwrap())
+        .await
+        .unwrap()
+        .expect("session must be found by access token");
+    assert_eq!(by_access_token.organization_id, organization.id);
+    assert!(storage
+        .get_session(default_id, &session.uuid.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+
+    storage.delete_user(default_id, user).await.unwrap();
+    storage.delete_person(default_id, person).await.unwrap();
+    storage
+        .delete_user(organization.id, other_user)
+        .await
+        .unwrap();
+    storage
+        .delete_person(organization.id, other_person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_person_lifecycle(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    assert!(person.id > 0, "id must be assigned");
+    assert!(person.uuid.is_some(), "uuid must be assigned");
+    assert!(person.created_on.is_some(), "created_on must be set");
+
+    let by_uuid = storage
+        .get_person_by_uuid(organization_id, &person.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .expect("person must be found by uuid");
+    assert_eq!(by_uuid.id, person.id);
+    assert_eq!(by_uuid.email, person.email);
+
+    let by_id = storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .expect("person must be found by id");
+    assert_eq!(by_id.uuid, person.uuid);
+
+    let mut changed = by_id.clone();
+    changed.lastname = "Updated".to_string();
+    changed.phone = None;
+    let updated = storage
+        .update_person(organization_id, changed)
+        .await
+        .unwrap();
+    assert!(updated.updated_on.is_some(), "updated_on must be set");
+
+    let reloaded = storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.lastname, "Updated");
+    assert_eq!(reloaded.phone, None);
+    assert_eq!(reloaded.uuid, person.uuid);
+
+    let query = PersonQuery {
+        email: Some(person.email.to_uppercase()),
+        ..PersonQuery::default()
+    };
+    let found = storage
+        .get_all_person(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(
+        found.total, 1,
+        "email filter must be a case insensitive prefix"
+    );
+    assert_eq!(found.items[0].id, person.id);
+
+    storage
+        .delete_person(organization_id, reloaded)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn check_email_verification(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let mut pending = new_person();
+    pending.email_status = EmailStatus::Pending;
+    let person = storage
+        .create_person(organization_id, pending)
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let reloaded = storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.email_status, EmailStatus::Pending);
+    assert!(reloaded.email_verified_on.is_none());
+
+    let now = Utc::now();
+    let verification = EmailVerification::new(
+        person.id,
+        person.email.clone(),
+        unique_name("verification"),
+        now + Duration::hours(24),
+    );
+    storage
+        .create_email_verification(organization_id, verification.clone())
+        .await
+        .unwrap();
+    let expired = EmailVerification::new(
+        person.id,
+        person.email.clone(),
+        unique_name("verification"),
+        now,
+    );
+    storage
+        .create_email_verification(organization_id, expired.clone())
+        .await
+        .unwrap();
+    let stored = storage
+        .get_email_verification(organization_id, &verification.token_hash)
+        .await
+        .unwrap()
+        .expect("verification must be found by hash");
+    assert_eq!(stored.person_id, person.id);
+    assert_eq!(stored.email, person.email);
+    storage
+        .delete_expired_email_verifications(organization_id, now)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_email_verification(organization_id, &expired.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    //Users carry the status of their person.
+    storage
+        .update_email_status(organization_id, person.id, EmailStatus::Verified, Some(now))
+        .await
+        .unwrap();
+    let found = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(found.person.is_email_verified());
+    assert!(found.person.email_verified_on.is_some());
+
+    //Updates save the status given by the domain.
+    let mut changed = found.person.clone();
+    changed.email = format!("changed.{}", person.email);
+    changed.email_status = EmailStatus::Unverified;
+    changed.email_verified_on = None;
+    storage
+        .update_person(organization_id, changed)
+        .await
+        .unwrap();
+    let reloaded = storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.email_status, EmailStatus::Unverified);
+    assert!(reloaded.email_verified_on.is_none());
+
+    storage
+        .delete_person_email_verifications(organization_id, person.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_email_verification(organization_id, &verification.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    storage.delete_user(organization_id, user).await.unwrap();
+    storage
+        .delete_person(organization_id, reloaded)
+        .await
+        .unwrap();
+}
+
+pub async fn check_user_lifecycle(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    assert!(user.id > 0, "id must be assigned");
+    assert!(user.uuid.is_some(), "uuid must be assigned");
+    assert!(user.created_on.is_some(), "created_on must be set");
+
+    let loaded = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .expect("user must be found by uuid");
+    assert_eq!(loaded.login, user.login);
+    assert_eq!(loaded.password, "", "password must not be restituted");
+    assert_eq!(loaded.person.id, person.id, "person must be linked");
+    assert_eq!(loaded.person.uuid, person.uuid);
+
+    //Person changes are visible through the user.
+    let mut changed_person = person.clone();
+    changed_person.firstname = unique_name("renamed");
+    storage
+        .update_person(organization_id, changed_person.clone())
+        .await
+        .unwrap();
+    let reloaded = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.person.firstname, changed_person.firstname);
+
+    //An empty password keeps the stored one.
+    let mut changed_user = reloaded.clone();
+    changed_user.photo = Some(vec![1, 2, 3]);
+    let updated = storage
+        .update_user(organization_id, changed_user)
+        .await
+        .unwrap();
+    assert!(updated.updated_on.is_some(), "updated_on must be set");
+    let by_login = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(by_login.photo, Some(vec![1, 2, 3]));
+    assert_eq!(by_login.password, user.password);
+
+    let query = UserQuery {
+        login: Some(user.login.clone()),
+        ..UserQuery::default()
+    };
+    let found = storage
+        .get_all_users(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(found.total, 1);
+    assert_eq!(found.items[0].id, user.id);
+
+    storage.delete_user(organization_id, updated).await.unwrap();
+    assert!(storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .is_none());
+    storage
+        .delete_person(organization_id, changed_person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_user_status(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let mut pending = new_user(person.clone());
+    pending.status = AccountStatus::Pending;
+    let user = storage.create_user(organization_id, pending).await.unwrap();
+    let loaded = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        loaded.status,
+        AccountStatus::Pending,
+        "status must be saved"
+    );
+
+    let admin_uuid = uuid::Uuid::new_v4();
+    let activation = StatusChange::new(&loaded, AccountStatus::Active, None, None, None);
+    storage
+        .change_user_status(organization_id, activation)
+        .await
+        .unwrap();
+    let active = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    let until = Utc::now() + Duration::days(7);
+    let suspension = StatusChange::new(
+        &active,
+        AccountStatus::Suspended,
+        Some("Abuse".to_string()),
+        Some(until),
+        Some(admin_uuid),
+    );
+    storage
+        .change_user_status(organization_id, suspension)
+        .await
+        .unwrap();
+
+    let suspended = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(suspended.status, AccountStatus::Suspended);
+    assert_eq!(suspended.status_reason, Some("Abuse".to_string()));
+    assert_eq!(
+        suspended.suspended_until.map(|date| date.timestamp()),
+        Some(until.timestamp())
+    );
+
+    //Saving the user keeps its status.
+    let mut changed = suspended.clone();
+    changed.photo = Some(vec![4, 5, 6]);
+    storage.update_user(organization_id, changed).await.unwrap();
+    let reloaded = storage
+        .get_user_by_id(organization_id, user.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.status, AccountStatus::Suspended);
+
+    let changes = storage
+        .get_status_changes(organization_id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].status, AccountStatus::Suspended, "newest first");
+    assert_eq!(changes[0].previous_status, AccountStatus::Active);
+    assert_eq!(changes[0].changed_by, Some(admin_uuid));
+    assert_eq!(changes[1].previous_status, AccountStatus::Pending);
+    assert_eq!(changes[1].changed_by, None);
+
+    //Changes go away with their user.
+    storage
+        .delete_user(organization_id, reloaded)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_status_changes(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_soft_delete(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let deleted_by = uuid::Uuid::new_v4();
+    let deleted_on = Utc::now() - Duration::days(2);
+
+    storage
+        .update_user_deletion(organization_id, user.id, Some(deleted_on), Some(deleted_by))
+        .await
+        .unwrap();
+    storage
+        .update_person_deletion(
+            organization_id,
+            person.id,
+            Some(deleted_on),
+            Some(deleted_by),
+        )
+        .await
+        .unwrap();
+
+    //Deleted rows are left out of every read, unless asked for.
+    assert!(storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_user_by_id(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_person_by_email(organization_id, &person.email)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_person_by_id(organization_id, person.id)
+        .await
+        .unwrap()
+        .is_none());
+    let mut query = UserQuery {
+        login: Some(user.login.clone()),
+        ..UserQuery::default()
+    };
+    assert_eq!(
+        storage
+            .get_all_users(organization_id, &query)
+            .await
+            .unwrap()
+            .total,
+        0
+    );
+    query.include_deleted = true;
+    assert_eq!(
+        storage
+            .get_all_users(organization_id, &query)
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+    let mut person_query = PersonQuery {
+        email: Some(person.email.clone()),
+        ..PersonQuery::default()
+    };
+    assert_eq!(
+        storage
+            .get_all_person(organization_id, &person_query)
+            .await
+            .unwrap()
+            .total,
+        0
+    );
+    person_query.include_deleted = true;
+    assert_eq!(
+        storage
+            .get_all_person(organization_id, &person_query)
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+
+    let deleted = storage
+        .get_user(organization_id, &user.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .expect("deleted user must be found when included");
+    assert_eq!(
+        deleted.deleted_on.map(|on| on.timestamp()),
+        Some(deleted_on.timestamp())
+    );
+    assert_eq!(deleted.deleted_by, Some(deleted_by));
+    assert!(deleted.person.is_deleted());
+
+    //Updates keep the deletion.
+    storage
+        .update_user(organization_id, deleted.clone())
+        .await
+        .unwrap();
+    assert!(storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .is_none());
+
+    //A restored user is visible again.
+    storage
+        .update_user_deletion(organization_id, user.id, None, None)
+        .await
+        .unwrap();
+    let restored = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .expect("restored user must be found");
+    assert!(restored.deleted_on.is_none());
+    assert!(restored.deleted_by.is_none());
+
+    //Persons still referenced by a user are not purged.
+    storage
+        .purge_deleted_persons(organization_id, Utc::now())
+        .await
+        .unwrap();
+    assert!(storage
+        .get_person_by_uuid(organization_id, &person.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_some());
+
+    //Only what was deleted before the given date is purged.
+    storage
+        .update_user_deletion(organization_id, user.id, Some(deleted_on), Some(deleted_by))
+        .await
+        .unwrap();
+    storage
+        .purge_deleted_users(organization_id, deleted_on - Duration::days(1))
+        .await
+        .unwrap();
+    assert!(storage
+        .get_user(organization_id, &user.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(
+        storage
+            .purge_deleted_users(organization_id, Utc::now())
+            .await
+            .unwrap()
+            >= 1
+    );
+    assert!(storage
+        .get_user(organization_id, &user.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(
+        storage
+            .purge_deleted_persons(organization_id, Utc::now())
+            .await
+            .unwrap()
+            >= 1
+    );
+    assert!(storage
+        .get_person_by_uuid(organization_id, &person.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn check_login_lookup(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+
+    let found = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .expect("user must be found by login");
+    assert_eq!(found.id, user.id);
+    assert_eq!(
+        found.password, user.password,
+        "password is needed to verify"
+    );
+    assert_eq!(found.person.id, person.id);
+    let found = storage
+        .get_user_with_password(organization_id, &user.uuid.unwrap())
+        .await
+        .unwrap()
+        .expect("user must be found by uuid");
+    assert_eq!(found.password, user.password);
+    let listed = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(listed.password, "", "password is only read to verify it");
+
+    let missing = storage
+        .get_user_by_login(organization_id, &unique_name("missing"))
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+
+    storage
+        .update_user_password(organization_id, user.id, "$argon2id$rehashed".to_string())
+        .await
+        .unwrap();
+    let rehashed = storage
+        .get_user_by_login(organization_id, &user.login)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(rehashed.password, "$argon2id$rehashed");
+
+    storage
+        .delete_user(organization_id, rehashed)
+        .await
+        .unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_user_listing(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    //Shared name prefix isolates this run from existing data.
+    let prefix = unique_name("listing");
+    let mut persons = Vec::new();
+    let mut users = Vec::new();
+    for index in 0..3 {
+        let mut person = new_person();
+        person.lastname = format!("{}_{}", prefix, index);
+        let person = storage
+            .create_person(organization_id, person)
+            .await
+            .unwrap();
+        let mut user = new_user(person.clone());
+        user.login = format!("{}_{}", prefix, index);
+        users.push(storage.create_user(organization_id, user).await.unwrap());
+        persons.push(person);
+    }
+
+    let mut query = UserQuery {
+        name: Some(prefix.clone()),
+        limit: Some(2),
+        direction: SortDirection::Desc,
+        ..UserQuery::default()
+    };
+    let first_page = storage
+        .get_all_users(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(first_page.total, 3);
+    assert_eq!(first_page.items.len(), 2);
+    assert_eq!(first_page.items[0].login, format!("{}_2", prefix));
+    assert_eq!(first_page.items[1].login, format!("{}_1", prefix));
+
+    query.cursor = first_page.next_cursor.clone();
+    assert!(query.cursor.is_some(), "a next cursor is expected");
+    let second_page = storage
+        .get_all_users(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(second_page.items.len(), 1);
+    assert_eq!(second_page.items[0].login, format!("{}_0", prefix));
+    assert!(second_page.next_cursor.is_none());
+
+    let query = PersonQuery {
+        name: Some(prefix.clone()),
+        sort: PersonSortField::Lastname,
+        page: Some(2),
+        limit: Some(2),
+        ..PersonQuery::default()
+    };
+    let page = storage
+        .get_all_person(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].lastname, format!("{}_2", prefix));
+
+    for user in users {
+        storage.delete_user(organization_id, user).await.unwrap();
+    }
+    for person in persons {
+        storage
+            .delete_person(organization_id, person)
+            .await
+            .unwrap();
+    }
+}
+
+fn get_conflict_field<T>(result: StorageResult<T>) -> Option<String> {
+    match result {
+        Err(StorageError::Conflict { field }) => Some(field),
+        _ => None,
+    }
+}
+
+pub async fn check_uniqueness(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+
+    //Lookups ignore case like the uniqueness rules.
+    let by_login = storage
+        .get_user_by_login(organization_id, &user.login.to_uppercase())
+        .await
+        .unwrap()
+        .expect("login lookup must ignore case");
+    assert_eq!(by_login.id, user.id);
+    let by_email = storage
+        .get_person_by_email(organization_id, &person.email.to_uppercase())
+        .await
+        .unwrap()
+        .expect("email lookup must ignore case");
+    assert_eq!(by_email.id, person.id);
+
+    let mut duplicate_person = new_person();
+    duplicate_person.email = person.email.to_uppercase();
+    let result = storage
+        .create_person(organization_id, duplicate_person)
+        .await;
+    assert_eq!(get_conflict_field(result), Some("email".to_string()));
+
+    let other_person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let mut duplicate_user = new_user(other_person.clone());
+    duplicate_user.login = user.login.to_uppercase();
+    let result = storage.create_user(organization_id, duplicate_user).await;
+    assert_eq!(get_conflict_field(result), Some("login".to_string()));
+
+    let mut changed_person = other_person.clone();
+    changed_person.email = person.email.clone();
+    let result = storage.update_person(organization_id, changed_person).await;
+    assert_eq!(get_conflict_field(result), Some("email".to_string()));
+
+    //Keeping its own value is not a conflict.
+    storage
+        .update_person(organization_id, person.clone())
+        .await
+        .unwrap();
+    storage
+        .update_user(organization_id, by_login.clone())
+        .await
+        .unwrap();
+
+    storage
+        .delete_user(organization_id, by_login)
+        .await
+        .unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+    storage
+        .delete_person(organization_id, other_person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_login_counters(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let key = unique_name("counter");
+    assert!(storage
+        .get_login_counter(organization_id, CounterScope::Login, &key)
+        .await
+        .unwrap()
+        .is_none());
+
+    let mut counter = LoginCounter::new(CounterScope::Login, key.clone());
+    counter.failures = 1;
+    storage
+        .save_login_counter(organization_id, counter.clone())
+        .await
+        .unwrap();
+
+    //Saving again replaces the stored values.
+    counter.failures = 2;
+    counter.locked_until = Some(counter.last_failure_on);
+    storage
+        .save_login_counter(organization_id, counter.clone())
+        .await
+        .unwrap();
+    let loaded = storage
+        .get_login_counter(organization_id, CounterScope::Login, &key)
+        .await
+        .unwrap()
+        .expect("counter must be found");
+    assert_eq!(loaded.failures, 2);
+    assert!(loaded.locked_until.is_some());
+
+    //Scopes are independent.
+    assert!(storage
+        .get_login_counter(organization_id, CounterScope::Ip, &key)
+        .await
+        .unwrap()
+        .is_none());
+
+    storage
+        .delete_login_counter(organization_id, CounterScope::Login, &key)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_login_counter(organization_id, CounterScope::Login, &key)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn check_login_history(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let context = LoginContext::new(
+        LoginChannel::Grpc,
+        Some("127.0.0.1".to_string()),
+        Some("conformance".to_string()),
+    );
+
+    let failure = LoginEvent::new(Some(user.id), user.login.clone(), &context, false);
+    storage
+        .add_login_event(organization_id, failure)
+        .await
+        .unwrap();
+    let success = LoginEvent::new(Some(user.id), user.login.clone(), &context, true);
+    storage
+        .update_last_login(organization_id, user.id, success.occurred_on)
+        .await
+        .unwrap();
+    storage
+        .add_login_event(organization_id, success.clone())
+        .await
+        .unwrap();
+
+    let reloaded = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        reloaded.last_login_on.is_some(),
+        "last_login_on must be set"
+    );
+
+    let pagination = Pagination {
+        limit: Some(1),
+        ..Pagination::default()
+    };
+    let page = storage
+        .get_login_events(organization_id, user.id, &pagination)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.items.len(), 1);
+    assert!(page.items[0].success, "newest event comes first");
+    assert_eq!(page.items[0].channel, LoginChannel::Grpc);
+    assert_eq!(page.items[0].ip, Some("127.0.0.1".to_string()));
+    assert!(page.next_cursor.is_some());
+
+    //History goes away with its user.
+    storage
+        .delete_user(organization_id, reloaded)
+        .await
+        .unwrap();
+    let page = storage
+        .get_login_events(organization_id, user.id, &pagination)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 0);
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_sessions(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let found = storage
+        .get_user_by_id(organization_id, user.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.uuid, user.uuid);
+    assert!(found.password.is_empty(), "password must not be restituted");
+
+    let now = Utc::now();
+    let expires_on = now + Duration::hours(1);
+    let context = LoginContext::new(
+        LoginChannel::Rest,
+        Some("127.0.0.1".to_string()),
+        Some("conformance".to_string()),
+    );
+    let session = Session::new(user.id, Some("laptop".to_string()), &context, expires_on);
+    let session = storage
+        .create_session(organization_id, session)
+        .await
+        .unwrap();
+    let session_uuid = session.uuid.expect("session uuid must be generated");
+    let other = Session::new(user.id, None, &context, expires_on);
+    let other = storage
+        .create_session(organization_id, other)
+        .await
+        .unwrap();
+    let expired = Session::new(user.id, None, &context, now);
+    let expired = storage
+        .create_session(organization_id, expired)
+        .await
+        .unwrap();
+
+    let stored_session = storage
+        .get_session(organization_id, &session_uuid)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored_session.user_id, user.id);
+    assert_eq!(stored_session.device, Some("laptop".to_string()));
+    assert_eq!(stored_session.ip, Some("127.0.0.1".to_string()));
+
+    //Expired sessions are not listed, the most recently used comes first.
+    let mut refreshed_session = stored_session.clone();
+    let access_token_hash = unique_name("access");
+    refreshed_session.refreshed_on = Some(Utc::now());
+    refreshed_session.expires_on = expires_on + Duration::hours(1);
+    refreshed_session.access_token_hash = Some(access_token_hash.clone());
+    refreshed_session.access_expires_on = Some(expires_on);
+    storage
+        .update_session(organization_id, refreshed_session)
+        .await
+        .unwrap();
+    let sessions = storage
+        .get_user_sessions(organization_id, user.id, now)
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].uuid, Some(session_uuid));
+    assert!(sessions[0].refreshed_on.is_some());
+    assert!(sessions[0].expires_on > expires_on);
+
+    let by_access_token = storage
+        .get_session_by_access_token(&access_token_hash)
+        .await
+        .unwrap()
+        .expect("session must be found by access token");
+    assert_eq!(by_access_token.uuid, Some(session_uuid));
+    assert!(by_access_token.access_expires_on.is_some());
+
+    let first = RefreshToken::new(user.id, session_uuid, unique_name("hash"), expires_on);
+    storage
+        .add_refresh_token(organization_id, first.clone())
+        .await
+        .unwrap();
+    let second = RefreshToken::new(user.id, session_uuid, unique_name("hash"), expires_on);
+    storage
+        .add_refresh_token(organization_id, second.clone())
+        .await
+        .unwrap();
+    let stale = RefreshToken::new(user.id, expired.uuid.unwrap(), unique_name("hash"), now);
+    storage
+        .add_refresh_token(organization_id, stale.clone())
+        .await
+        .unwrap();
+
+    let stored = storage
+        .get_refresh_token(organization_id, &first.token_hash)
+        .await
+        .unwrap()
+        .expect("token must be found by hash");
+    assert_eq!(stored.user_id, user.id);
+    assert_eq!(stored.family, session_uuid);
+    assert!(stored.rotated_on.is_none());
+
+    //Only the first rotation succeeds.
+    assert!(storage
+        .rotate_refresh_token(organization_id, stored.id, now)
+        .await
+        .unwrap());
+    assert!(!storage
+        .rotate_refresh_token(organization_id, stored.id, now)
+        .await
+        .unwrap());
+    let stored = storage
+        .get_refresh_token(organization_id, &first.token_hash)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.rotated_on.is_some());
+
+    //Tokens go away with their session.
+    storage
+        .delete_expired_sessions(organization_id, now)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_session(organization_id, &expired.uuid.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_refresh_token(organization_id, &stale.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    storage
+        .delete_session(organization_id, &session_uuid)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_refresh_token(organization_id, &second.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    storage
+        .delete_user_sessions(organization_id, user.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_session(organization_id, &other.uuid.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+
+    storage.delete_user(organization_id, found).await.unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_two_factor(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    assert!(storage
+        .get_two_factor(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_none());
+
+    //Saving again replaces the enrollment.
+    let pending = TwoFactor::new(user.id, unique_name("SECRET"));
+    storage
+        .save_two_factor(organization_id, pending)
+        .await
+        .unwrap();
+    let mut confirmed = TwoFactor::new(user.id, unique_name("SECRET"));
+    confirmed.confirmed_on = Some(Utc::now());
+    confirmed.last_used_step = Some(42);
+    storage
+        .save_two_factor(organization_id, confirmed.clone())
+        .await
+        .unwrap();
+    let stored = storage
+        .get_two_factor(organization_id, user.id)
+        .await
+        .unwrap()
+        .expect("second factor must be found");
+    assert_eq!(stored.secret, confirmed.secret);
+    assert!(stored.is_enabled());
+    assert_eq!(stored.last_used_step, Some(42));
+
+    //Replacing drops the previous codes, each code is only used once.
+    let first = unique_name("code");
+    storage
+        .replace_recovery_codes(organization_id, user.id, vec![first.clone()])
+        .await
+        .unwrap();
+    let second = unique_name("code");
+    let third = unique_name("code");
+    storage
+        .replace_recovery_codes(organization_id, user.id, vec![second.clone(), third])
+        .await
+        .unwrap();
+    let codes = storage
+        .get_recovery_codes(organization_id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(codes.len(), 2);
+    assert!(codes.iter().all(|code| code.used_on.is_none()));
+    let now = Utc::now();
+    assert!(!storage
+        .use_recovery_code(organization_id, user.id, &first, now)
+        .await
+        .unwrap());
+    assert!(storage
+        .use_recovery_code(organization_id, user.id, &second, now)
+        .await
+        .unwrap());
+    assert!(!storage
+        .use_recovery_code(organization_id, user.id, &second, now)
+        .await
+        .unwrap());
+    let codes = storage
+        .get_recovery_codes(organization_id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        codes.iter().filter(|code| code.used_on.is_some()).count(),
+        1
+    );
+
+    let challenge = LoginChallenge::new(
+        user.id,
+        unique_name("challenge"),
+        Some("phone".to_string()),
+        now + Duration::minutes(5),
+    );
+    storage
+        .create_login_challenge(organization_id, challenge.clone())
+        .await
+        .unwrap();
+    let expired = LoginChallenge::new(user.id, unique_name("challenge"), None, now);
+    storage
+        .create_login_challenge(organization_id, expired.clone())
+        .await
+        .unwrap();
+    let stored_challenge = storage
+        .get_login_challenge(organization_id, &challenge.token_hash)
+        .await
+        .unwrap()
+        .expect("challenge must be found by hash");
+    assert_eq!(stored_challenge.user_id, user.id);
+    assert_eq!(stored_challenge.device, Some("phone".to_string()));
+
+    storage
+        .delete_expired_login_challenges(organization_id, now)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_login_challenge(organization_id, &expired.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+    storage
+        .delete_login_challenge(organization_id, stored_challenge.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_login_challenge(organization_id, &challenge.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    //Recovery codes go with their second factor.
+    storage
+        .delete_two_factor(organization_id, user.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_two_factor(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_recovery_codes(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    storage.delete_user(organization_id, user).await.unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_password_resets(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    let now = Utc::now();
+
+    let reset = PasswordReset::new(user.id, unique_name("reset"), now + Duration::minutes(30));
+    storage
+        .create_password_reset(organization_id, reset.clone())
+        .await
+        .unwrap();
+    let expired = PasswordReset::new(user.id, unique_name("reset"), now);
+    storage
+        .create_password_reset(organization_id, expired.clone())
+        .await
+        .unwrap();
+    let stored = storage
+        .get_password_reset(organization_id, &reset.token_hash)
+        .await
+        .unwrap()
+        .expect("reset must be found by hash");
+    assert_eq!(stored.user_id, user.id);
+    assert!(stored.used_on.is_none());
+
+    //A token is only used once.
+    assert!(storage
+        .use_password_reset(organization_id, stored.id, now)
+        .await
+        .unwrap());
+    assert!(!storage
+        .use_password_reset(organization_id, stored.id, now)
+        .await
+        .unwrap());
+    let stored = storage
+        .get_password_reset(organization_id, &reset.token_hash)
+        .await
+        .unwrap()
+        .expect("used reset must still be found");
+    assert!(stored.used_on.is_some());
+
+    storage
+        .delete_expired_password_resets(organization_id, now)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_password_reset(organization_id, &expired.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+    storage
+        .delete_user_password_resets(organization_id, user.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_password_reset(organization_id, &reset.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    storage.delete_user(organization_id, user).await.unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_roles(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+
+    let name = unique_name("role");
+    let role = Role::new(
+        0,
+        None,
+        name.clone(),
+        Some("Conformance".to_string()),
+        vec![Permission::PersonsRead, Permission::UsersRead],
+        None,
+        None,
+    );
+    let role = storage.create_role(organization_id, role).await.unwrap();
+    assert!(role.uuid.is_some(), "role uuid must be generated");
+
+    let found = storage
+        .get_role_by_name(organization_id, &name.to_uppercase())
+        .await
+        .unwrap()
+        .expect("role must be found by name, case ignored");
+    assert_eq!(found.uuid, role.uuid);
+    assert_eq!(found.permissions, role.permissions);
+
+    let duplicate = Role::new(0, None, name.to_uppercase(), None, vec![], None, None);
+    let result = storage.create_role(organization_id, duplicate).await;
+    assert_eq!(get_conflict_field(result), Some("name".to_string()));
+
+    let mut updated = found.clone();
+    updated.permissions = vec![Permission::RolesManage];
+    storage.update_role(organization_id, updated).await.unwrap();
+    let found = storage
+        .get_role_by_uuid(organization_id, &role.uuid.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.permissions, vec![Permission::RolesManage]);
+    assert!(storage
+        .get_all_roles(organization_id)
+        .await
+        .unwrap()
+        .iter()
+        .any(|role| role.name == name));
+
+    //Assigning twice is harmless.
+    storage
+        .add_user_role(organization_id, user.id, role.id)
+        .await
+        .unwrap();
+    storage
+        .add_user_role(organization_id, user.id, role.id)
+        .await
+        .unwrap();
+    let roles = storage
+        .get_user_roles(organization_id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(roles.len(), 1);
+    assert_eq!(roles[0].uuid, role.uuid);
+
+    storage
+        .remove_user_role(organization_id, user.id, role.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_user_roles(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    //Assignments go away with their role.
+    storage
+        .add_user_role(organization_id, user.id, role.id)
+        .await
+        .unwrap();
+    storage.delete_role(organization_id, found).await.unwrap();
+    assert!(storage
+        .get_user_roles(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(storage
+        .get_role_by_uuid(organization_id, &role.uuid.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+
+    storage.delete_user(organization_id, user).await.unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+fn new_group(parent: Option<&Group>) -> Group {
+    Group::new(
+        0,
+        None,
+        unique_name("group"),
+        None,
+        parent.map(|parent| parent.id),
+        None,
+        None,
+        None,
+    )
+}
+
+pub async fn check_groups(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let person = storage
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = storage
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+
+    let parent = storage
+        .create_group(organization_id, new_group(None))
+        .await
+        .unwrap();
+    assert!(parent.uuid.is_some(), "group uuid must be generated");
+    let child = storage
+        .create_group(organization_id, new_group(Some(&parent)))
+        .await
+        .unwrap();
+
+    let found = storage
+        .get_group_by_uuid(organization_id, &child.uuid.unwrap())
+        .await
+        .unwrap()
+        .expect("group must be found by uuid");
+    assert_eq!(found.parent_id, Some(parent.id));
+    assert_eq!(found.parent_uuid, parent.uuid, "parent uuid must be loaded");
+
+    let mut duplicate = new_group(None);
+    duplicate.name = parent.name.to_uppercase();
+    let result = storage.create_group(organization_id, duplicate).await;
+    assert_eq!(get_conflict_field(result), Some("name".to_string()));
+
+    //Adding twice is harmless, members come without password.
+    storage
+        .add_group_member(organization_id, child.id, user.id)
+        .await
+        .unwrap();
+    storage
+        .add_group_member(organization_id, child.id, user.id)
+        .await
+        .unwrap();
+    let members = storage
+        .get_group_members(organization_id, child.id)
+        .await
+        .unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].uuid, user.uuid);
+    assert!(members[0].password.is_empty());
+    let groups = storage
+        .get_user_groups(organization_id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(groups.len(), 1, "only direct memberships are stored");
+    assert_eq!(groups[0].uuid, child.uuid);
+
+    let role = Role::new(0, None, unique_name("role"), None, vec![], None, None);
+    let role = storage.create_role(organization_id, role).await.unwrap();
+    storage
+        .add_group_role(organization_id, child.id, role.id)
+        .await
+        .unwrap();
+    storage
+        .add_group_role(organization_id, child.id, role.id)
+        .await
+        .unwrap();
+    let roles = storage
+        .get_group_roles(organization_id, child.id)
+        .await
+        .unwrap();
+    assert_eq!(roles.len(), 1);
+    storage
+        .remove_group_role(organization_id, child.id, role.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_group_roles(organization_id, child.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    //Deleting the parent detaches its subgroups.
+    storage.delete_group(organization_id, parent).await.unwrap();
+    let found = storage
+        .get_group_by_uuid(organization_id, &child.uuid.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.parent_id, None);
+    assert_eq!(found.parent_uuid, None);
+
+    storage
+        .remove_group_member(organization_id, child.id, user.id)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_user_groups(organization_id, user.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    //Memberships go away with their user.
+    storage
+        .add_group_member(organization_id, child.id, user.id)
+        .await
+        .unwrap();
+    storage.delete_user(organization_id, user).await.unwrap();
+    assert!(storage
+        .get_group_members(organization_id, child.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    storage.delete_group(organization_id, found).await.unwrap();
+    storage.delete_role(organization_id, role).await.unwrap();
+    storage
+        .delete_person(organization_id, person)
+        .await
+        .unwrap();
+}
+
+pub async fn check_audit_log(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+    let actor = uuid::Uuid::new_v4();
+    let target_uuid = uuid::Uuid::new_v4();
+    let request_id = unique_name("request");
+    let context = AuditContext::new(Some(actor), Some(request_id.clone()));
+
+    let changes = vec![
+        FieldChange::new("login", serde_json::Value::Null, "conformance".into()),
+        FieldChange::redacted("password"),
+    ];
+    let created = AuditEntry::new(
+        &context,
+        AuditAction::Create,
+        AuditTarget::User,
+        Some(target_uuid),
+        changes.clone(),
+    );
+    storage
+        .add_audit_entry(organization_id, created)
+        .await
+        .unwrap();
+    let deleted = AuditEntry::new(
+        &context,
+        AuditAction::Delete,
+        AuditTarget::User,
+        Some(target_uuid),
+        Vec::new(),
+    );
+    storage
+        .add_audit_entry(organization_id, deleted)
+        .await
+        .unwrap();
+
+    let mut query = AuditQuery {
+        target_uuid: Some(target_uuid),
+        ..AuditQuery::default()
+    };
+    let page = storage
+        .get_audit_entries(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(
+        page.items[0].action,
+        AuditAction::Delete,
+        "newest entry comes first"
+    );
+    assert_eq!(page.items[1].changes, changes);
+    assert_eq!(page.items[1].actor, Some(actor));
+    assert_eq!(page.items[1].request_id, Some(request_id.clone()));
+    assert_eq!(page.items[1].target, AuditTarget::User);
+
+    //Filters are combined.
+    query.action = Some(AuditAction::Create);
+    let page = storage
+        .get_audit_entries(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    let query = AuditQuery {
+        request_id: Some(request_id),
+        from: Some(Utc::now() + Duration::days(1)),
+        ..AuditQuery::default()
+    };
+    assert_eq!(
+        storage
+            .get_audit_entries(organization_id, &query)
+            .await
+            .unwrap()
+            .total,
+        0
+    );
+    let query = AuditQuery {
+        actor: Some(actor),
+        limit: Some(1),
+        ..AuditQuery::default()
+    };
+    let page = storage
+        .get_audit_entries(organization_id, &query)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.items.len(), 1);
+    assert!(page.next_cursor.is_some());
+}
+
+pub async fn check_transactions(storage: &dyn StorageTrait) {
+    let organization_id = get_default_organization_id(storage).await;
+
+    //Committed writes are kept, reads of the transaction see its own writes.
+    let transaction = storage.begin().await.unwrap();
+    let person = transaction
+        .as_storage()
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let user = transaction
+        .as_storage()
+        .create_user(organization_id, new_user(person.clone()))
+        .await
+        .unwrap();
+    assert!(transaction
+        .as_storage()
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(
+        matches!(
+            transaction.as_storage().begin().await,
+            Err(StorageError::TransactionAlreadyOpen)
+        ),
+        "transactions do not nest"
+    );
+    transaction.commit().await.unwrap();
+    let found = storage
+        .get_user(organization_id, &user.uuid.unwrap(), false)
+        .await
+        .unwrap()
+        .expect("committed user must exist");
+    assert_eq!(found.person.uuid, person.uuid);
+
+    //A failed step is rolled back with the ones before it.
+    let transaction = storage.begin().await.unwrap();
+    let other_person = transaction
+        .as_storage()
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    let mut duplicate = new_user(other_person.clone());
+    duplicate.login = user.login.clone();
+    assert!(matches!(
+        transaction
+            .as_storage()
+            .create_user(organization_id, duplicate)
+            .await,
+        Err(StorageError::Conflict { .. })
+    ));
+    transaction.rollback().await.unwrap();
+    assert!(storage
+        .get_person_by_uuid(organization_id, &other_person.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_none());
+
+    //Dropping a transaction rolls it back.
+    let transaction = storage.begin().await.unwrap();
+    let dropped_person = transaction
+        .as_storage()
+        .create_person(organization_id, new_person())
+        .await
+        .unwrap();
+    drop(transaction);
+    assert!(storage
+        .get_person_by_uuid(organization_id, &dropped_person.uuid.unwrap(), true)
+        .await
+        .unwrap()
+        .is_none());
+
+    //The purge lock is only held by transactions.
+    assert!(matches!(
+        storage.try_lock_purge().await,
+        Err(StorageError::NoTransaction)
+    ));
+    let transaction = storage.begin().await.unwrap();
+    assert!(transaction.as_storage().try_lock_purge().await.unwrap());
+    transaction.commit().await.unwrap();
+}
+
+pub async fn run_all(storage: &dyn StorageTrait) {
+    check_organizations(storage).await;
+    check_person_lifecycle(storage).await;
+    check_email_verification(storage).await;
+    check_user_lifecycle(storage).await;
+    check_user_status(storage).await;
+    check_soft_delete(storage).await;
+    check_login_lookup(storage).await;
+    check_user_listing(storage).await;
+    check_uniqueness(storage).await;
+    check_login_counters(storage).await;
+    check_login_history(storage).await;
+    check_sessions(storage).await;
+    check_two_factor(storage).await;
+    check_password_resets(storage).await;
+    check_roles(storage).await;
+    check_groups(storage).await;
+    check_audit_log(storage).await;
+    check_transactions(storage).await;
+}
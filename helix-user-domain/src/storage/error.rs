@@ -9,6 +9,16 @@ pub enum StorageError {
     CreationImpossible,
     #[error("Another error")]
     AnotherError,
+    #[error("Backend unavailable")]
+    BackendUnavailable,
+    #[error("Conflict on field {field}")]
+    Conflict { field: String },
+    #[error("A transaction is already open")]
+    TransactionAlreadyOpen,
+    #[error("No open transaction")]
+    NoTransaction,
+    #[error("Migration error: {0}")]
+    Migration(String),
     #[error("IO error: {source}")]
     Io {
         #[from]
@@ -1,21 +1,446 @@
 use crate::core::app_user::*;
+use crate::core::audit::*;
+use crate::core::group::*;
+use crate::core::login::*;
+use crate::core::organization::*;
+use crate::core::password_reset::*;
 use crate::core::person::*;
+use crate::core::query::*;
+use crate::core::role::*;
+use crate::core::session::*;
+use crate::core::token::*;
+use crate::core::two_factor::*;
 use crate::storage::error::*;
 use async_trait::async_trait;
+use chrono::prelude::*;
 
+//Everything but organizations is scoped by the organization_id given to each call,
+//rows of other organizations are never read nor written, even by internal id.
 #[async_trait]
 pub trait StorageTrait: Send + Sync {
-    async fn login(&self, key: String) -> StorageResult<Option<AppUser>>;
-    async fn get_user(&self, uuid: &uuid::Uuid) -> StorageResult<Option<AppUser>>;
-    async fn get_all_users(&self) -> StorageResult<Vec<AppUser>>;
-    async fn create_user(&self, user: AppUser) -> StorageResult<AppUser>;
-    async fn update_user(&self, user: AppUser) -> StorageResult<AppUser>;
-    async fn delete_user(&self, user: AppUser) -> StorageResult<()>;
-
-    async fn create_person(&self, person: Person) -> StorageResult<Person>;
-    async fn update_person(&self, person: Person) -> StorageResult<Person>;
-    async fn delete_person(&self, person: Person) -> StorageResult<()>;
-    async fn get_person_by_uuid(&self, uuid: &uuid::Uuid) -> StorageResult<Option<Person>>;
-    async fn get_person_by_id(&self, id: i32) -> StorageResult<Option<Person>>;
-    async fn get_all_person(&self) -> StorageResult<Vec<Person>>;
+    //Organization names are unique regardless of case, duplicates fail with Conflict.
+    //The default organization always exists.
+    async fn get_all_organizations(&self) -> StorageResult<Vec<Organization>>;
+    async fn get_organization_by_uuid(
+        &self,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Organization>>;
+    async fn get_organization_by_id(&self, id: i32) -> StorageResult<Option<Organization>>;
+    async fn create_organization(&self, organization: Organization) -> StorageResult<Organization>;
+    async fn update_organization(&self, organization: Organization) -> StorageResult<Organization>;
+
+    //Logins and emails are unique per organization regardless of case,
+    //duplicates fail with Conflict. Deleted rows keep theirs until purged.
+    //Deleted users and persons are left out of every read,
+    //unless include_deleted is given.
+    async fn get_user_by_login(
+        &self,
+        organization_id: i32,
+        login: &str,
+    ) -> StorageResult<Option<AppUser>>;
+    async fn update_user_password(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        password: String,
+    ) -> StorageResult<()>;
+    async fn update_last_login(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<()>;
+    async fn get_user(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<AppUser>>;
+    //Same as get_user with the password hash, read to check it.
+    async fn get_user_with_password(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<AppUser>>;
+    async fn get_user_by_id(&self, organization_id: i32, id: i32)
+        -> StorageResult<Option<AppUser>>;
+    async fn get_all_users(
+        &self,
+        organization_id: i32,
+        query: &UserQuery,
+    ) -> StorageResult<Page<AppUser>>;
+    async fn create_user(&self, organization_id: i32, user: AppUser) -> StorageResult<AppUser>;
+    async fn update_user(&self, organization_id: i32, user: AppUser) -> StorageResult<AppUser>;
+    //Removes the row for good, soft deletion is made by update_user_deletion.
+    async fn delete_user(&self, organization_id: i32, user: AppUser) -> StorageResult<()>;
+    //None restores the user. Deletion is only saved here, not by update_user.
+    async fn update_user_deletion(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()>;
+    //Remove the users deleted at or before the given date, return how many.
+    async fn purge_deleted_users(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64>;
+    //Save the status of the user and record the change, in one go.
+    //The status is also saved by create_user and update_user.
+    async fn change_user_status(
+        &self,
+        organization_id: i32,
+        change: StatusChange,
+    ) -> StorageResult<()>;
+    //Status history, newest first. Changes go away with their user.
+    async fn get_status_changes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<StatusChange>>;
+
+    async fn create_person(&self, organization_id: i32, person: Person) -> StorageResult<Person>;
+    async fn update_person(&self, organization_id: i32, person: Person) -> StorageResult<Person>;
+    //Removes the row for good, soft deletion is made by update_person_deletion.
+    async fn delete_person(&self, organization_id: i32, person: Person) -> StorageResult<()>;
+    //None restores the person. Deletion is only saved here, not by update_person.
+    async fn update_person_deletion(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()>;
+    //Remove the persons deleted at or before the given date, return how many.
+    //Persons still referenced by a user are kept.
+    async fn purge_deleted_persons(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64>;
+    //Take the purge lock until the transaction ends, false when another one holds it.
+    //Only the storage of a transaction can hold it, others fail with NoTransaction.
+    async fn try_lock_purge(&self) -> StorageResult<bool>;
+    async fn get_person_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<Person>>;
+    async fn get_person_by_id(
+        &self,
+        organization_id: i32,
+        id: i32,
+    ) -> StorageResult<Option<Person>>;
+    async fn get_person_by_email(
+        &self,
+        organization_id: i32,
+        email: &str,
+    ) -> StorageResult<Option<Person>>;
+    async fn get_all_person(
+        &self,
+        organization_id: i32,
+        query: &PersonQuery,
+    ) -> StorageResult<Page<Person>>;
+    //The email status is also saved by create_person and update_person.
+    async fn update_email_status(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        status: EmailStatus,
+        verified_on: Option<DateTime<Utc>>,
+    ) -> StorageResult<()>;
+
+    //Email verification tokens, looked up by digest. They go away with their person.
+    async fn create_email_verification(
+        &self,
+        organization_id: i32,
+        verification: EmailVerification,
+    ) -> StorageResult<()>;
+    async fn get_email_verification(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<EmailVerification>>;
+    async fn delete_person_email_verifications(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+    ) -> StorageResult<()>;
+    async fn delete_expired_email_verifications(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    //Failed login counters, persisted to survive restarts.
+    async fn get_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<Option<LoginCounter>>;
+    async fn save_login_counter(
+        &self,
+        organization_id: i32,
+        counter: LoginCounter,
+    ) -> StorageResult<()>;
+    async fn delete_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<()>;
+
+    //Login history, newest first. Events go away with their user.
+    async fn add_login_event(&self, organization_id: i32, event: LoginEvent) -> StorageResult<()>;
+    async fn get_login_events(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        pagination: &Pagination,
+    ) -> StorageResult<Page<LoginEvent>>;
+
+    //Sessions go away with their user, refresh tokens with their session.
+    async fn create_session(
+        &self,
+        organization_id: i32,
+        session: Session,
+    ) -> StorageResult<Session>;
+    async fn get_session(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Session>>;
+    //The only lookup across organizations, the digest identifies the tenant of a token.
+    async fn get_session_by_access_token(
+        &self,
+        access_token_hash: &str,
+    ) -> StorageResult<Option<Session>>;
+    //Sessions not expired at the given date, most recently used first.
+    async fn get_user_sessions(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<Vec<Session>>;
+    //Refresh dates, expiry and access token digest.
+    async fn update_session(&self, organization_id: i32, session: Session) -> StorageResult<()>;
+    async fn delete_session(&self, organization_id: i32, uuid: &uuid::Uuid) -> StorageResult<()>;
+    async fn delete_user_sessions(&self, organization_id: i32, user_id: i32) -> StorageResult<()>;
+    async fn delete_expired_sessions(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    //Refresh tokens, looked up by digest.
+    async fn add_refresh_token(
+        &self,
+        organization_id: i32,
+        token: RefreshToken,
+    ) -> StorageResult<()>;
+    async fn get_refresh_token(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<RefreshToken>>;
+    //Mark as exchanged, false when it already was (concurrent reuse).
+    async fn rotate_refresh_token(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool>;
+
+    //Second factor of a user, saving replaces the previous one.
+    //It goes away with its user, recovery codes go with it.
+    async fn get_two_factor(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Option<TwoFactor>>;
+    async fn save_two_factor(
+        &self,
+        organization_id: i32,
+        two_factor: TwoFactor,
+    ) -> StorageResult<()>;
+    async fn delete_two_factor(&self, organization_id: i32, user_id: i32) -> StorageResult<()>;
+    //Recovery codes, looked up by digest. Replacing drops the previous ones.
+    async fn replace_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hashes: Vec<String>,
+    ) -> StorageResult<()>;
+    async fn get_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<RecoveryCode>>;
+    //Mark as used, false when unknown or already used (concurrent use).
+    async fn use_recovery_code(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hash: &str,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool>;
+
+    //Pending second factor logins, looked up by digest. They go away with their user.
+    async fn create_login_challenge(
+        &self,
+        organization_id: i32,
+        challenge: LoginChallenge,
+    ) -> StorageResult<()>;
+    async fn get_login_challenge(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<LoginChallenge>>;
+    async fn delete_login_challenge(&self, organization_id: i32, id: i32) -> StorageResult<()>;
+    async fn delete_expired_login_challenges(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    //Password reset tokens, looked up by digest. They go away with their user.
+    async fn create_password_reset(
+        &self,
+        organization_id: i32,
+        reset: PasswordReset,
+    ) -> StorageResult<()>;
+    async fn get_password_reset(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<PasswordReset>>;
+    //Mark as used, false when it already was (concurrent use).
+    async fn use_password_reset(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool>;
+    async fn delete_user_password_resets(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()>;
+    async fn delete_expired_password_resets(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    //Role names are unique per organization regardless of case,
+    //duplicates fail with Conflict. Assignments go away with their user or role.
+    async fn get_all_roles(&self, organization_id: i32) -> StorageResult<Vec<Role>>;
+    async fn get_role_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Role>>;
+    async fn get_role_by_name(
+        &self,
+        organization_id: i32,
+        name: &str,
+    ) -> StorageResult<Option<Role>>;
+    async fn create_role(&self, organization_id: i32, role: Role) -> StorageResult<Role>;
+    async fn update_role(&self, organization_id: i32, role: Role) -> StorageResult<Role>;
+    async fn delete_role(&self, organization_id: i32, role: Role) -> StorageResult<()>;
+    async fn get_user_roles(&self, organization_id: i32, user_id: i32) -> StorageResult<Vec<Role>>;
+    //Assigning twice is a no-op.
+    async fn add_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()>;
+    async fn remove_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()>;
+
+    //Group names are unique per organization regardless of case,
+    //duplicates fail with Conflict.
+    //Deleting a group makes its subgroups top level, memberships and roles go with it.
+    async fn get_all_groups(&self, organization_id: i32) -> StorageResult<Vec<Group>>;
+    async fn get_group_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Group>>;
+    async fn create_group(&self, organization_id: i32, group: Group) -> StorageResult<Group>;
+    async fn update_group(&self, organization_id: i32, group: Group) -> StorageResult<Group>;
+    async fn delete_group(&self, organization_id: i32, group: Group) -> StorageResult<()>;
+    //Direct members and memberships, nesting is resolved by the domain.
+    async fn get_group_members(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<AppUser>>;
+    async fn get_user_groups(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<Group>>;
+    //Adding twice is a no-op.
+    async fn add_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()>;
+    async fn remove_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()>;
+    async fn get_group_roles(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<Role>>;
+    async fn add_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()>;
+    async fn remove_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()>;
+
+    //The audit log is append only, entries are never updated nor purged.
+    async fn add_audit_entry(&self, organization_id: i32, entry: AuditEntry) -> StorageResult<()>;
+    //Newest first.
+    async fn get_audit_entries(
+        &self,
+        organization_id: i32,
+        query: &AuditQuery,
+    ) -> StorageResult<Page<AuditEntry>>;
+
+    //Start a unit of work, the calls made through the storage of the transaction
+    //returned are applied together on commit. Transactions do not nest, beginning
+    //one from that storage fails with TransactionAlreadyOpen.
+    async fn begin(&self) -> StorageResult<Box<dyn StorageTransaction>>;
+}
+
+//Open unit of work, dropping it without commit rolls it back.
+#[async_trait]
+pub trait StorageTransaction: Send + Sync {
+    //Storage whose calls run in the transaction.
+    fn as_storage(&self) -> &dyn StorageTrait;
+    async fn commit(self: Box<Self>) -> StorageResult<()>;
+    async fn rollback(self: Box<Self>) -> StorageResult<()>;
 }
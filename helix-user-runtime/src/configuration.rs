@@ -0,0 +1,192 @@
+use helix_user_domain::business::lockout::LockoutSettings;
+use helix_user_domain::business::password::PasswordSettings;
+use helix_user_domain::business::token::TokenSettings;
+use helix_user_domain::business::two_factor::TwoFactorSettings;
+use pg_db_storage::migration::MigrationMode;
+use std::env;
+use std::str::FromStr;
+
+pub struct Configuration {}
+
+impl Configuration {
+    //"postgres" (default) or "memory"
+    pub fn get_storage_type() -> String {
+        env::var("HELIX_STORAGE").unwrap_or("postgres".to_string())
+    }
+
+    //"none" (default), "status", "dry-run" or "apply"
+    pub fn get_migration_mode() -> MigrationMode {
+        MigrationMode::from_name(&env::var("HELIX_DB_MIGRATION").unwrap_or("none".to_string()))
+    }
+
+    pub fn get_database_name() -> String {
+        env::var("HELIX_DB_NAME").expect("HELIX_DB_NAME not found.")
+    }
+
+    pub fn get_database_host() -> String {
+        env::var("HELIX_DB_HOST").expect("HELIX_DB_HOST not found.")
+    }
+
+    pub fn get_database_port() -> u16 {
+        env::var("HELIX_DB_PORT")
+            .expect("HELIX_DB_PORT not found.")
+            .parse()
+            .expect("HELIX_DB_PORT is not a port number.")
+    }
+
+    pub fn get_database_user() -> String {
+        env::var("HELIX_DB_USER").expect("HELIX_DB_USER not found.")
+    }
+
+    pub fn get_database_password() -> String {
+        env::var("HELIX_DB_PASSWORD").expect("HELIX_DB_PASSWORD not found.")
+    }
+
+    pub fn get_password_memory_cost() -> u32 {
+        Configuration::parse_var(
+            "HELIX_PASSWORD_MEMORY_COST",
+            PasswordSettings::default().memory_cost,
+        )
+    }
+
+    pub fn get_password_time_cost() -> u32 {
+        Configuration::parse_var(
+            "HELIX_PASSWORD_TIME_COST",
+            PasswordSettings::default().time_cost,
+        )
+    }
+
+    pub fn get_password_parallelism() -> u32 {
+        Configuration::parse_var(
+            "HELIX_PASSWORD_PARALLELISM",
+            PasswordSettings::default().parallelism,
+        )
+    }
+
+    pub fn get_lockout_max_attempts() -> i32 {
+        Configuration::parse_var(
+            "HELIX_LOCKOUT_MAX_ATTEMPTS",
+            LockoutSettings::default().max_attempts,
+        )
+    }
+
+    pub fn get_lockout_ip_max_attempts() -> i32 {
+        Configuration::parse_var(
+            "HELIX_LOCKOUT_IP_MAX_ATTEMPTS",
+            LockoutSettings::default().ip_max_attempts,
+        )
+    }
+
+    //Durations in seconds
+    pub fn get_lockout_window() -> i64 {
+        Configuration::parse_var("HELIX_LOCKOUT_WINDOW", LockoutSettings::default().window)
+    }
+
+    pub fn get_lockout_base_delay() -> i64 {
+        Configuration::parse_var(
+            "HELIX_LOCKOUT_BASE_DELAY",
+            LockoutSettings::default().base_delay,
+        )
+    }
+
+    pub fn get_lockout_max_delay() -> i64 {
+        Configuration::parse_var(
+            "HELIX_LOCKOUT_MAX_DELAY",
+            LockoutSettings::default().max_delay,
+        )
+    }
+
+    //In minutes, shared with the signed tokens settings.
+    pub fn get_access_token_lifetime() -> i64 {
+        Configuration::parse_var(
+            "HELIX_ACCESS_TOKEN_MAX_LIFETIME",
+            TokenSettings::default().access_lifetime,
+        )
+    }
+
+    pub fn get_refresh_token_lifetime() -> i64 {
+        Configuration::parse_var(
+            "HELIX_REFRESH_TOKEN_MAX_LIFETIME",
+            TokenSettings::default().refresh_lifetime,
+        )
+    }
+
+    //In minutes, to use a password reset token.
+    pub fn get_password_reset_lifetime() -> i64 {
+        Configuration::parse_var(
+            "HELIX_PASSWORD_RESET_LIFETIME",
+            TokenSettings::default().reset_lifetime,
+        )
+    }
+
+    pub fn get_email_verification_lifetime() -> i64 {
+        Configuration::parse_var(
+            "HELIX_EMAIL_VERIFICATION_LIFETIME",
+            TokenSettings::default().verification_lifetime,
+        )
+    }
+
+    //"true" to refuse logins until the email of the person is verified.
+    pub fn get_require_verified_email() -> bool {
+        Configuration::parse_var("HELIX_REQUIRE_VERIFIED_EMAIL", false)
+    }
+
+    //"true" to create users pending, until an administrator activates them.
+    pub fn get_require_activation() -> bool {
+        Configuration::parse_var("HELIX_REQUIRE_ACTIVATION", false)
+    }
+
+    //In days, deleted users and persons are purged once it is over.
+    pub fn get_deleted_retention() -> i64 {
+        Configuration::parse_var("HELIX_DELETED_RETENTION", 30)
+    }
+
+    //In minutes, between two purges.
+    pub fn get_purge_interval() -> u64 {
+        Configuration::parse_var("HELIX_PURGE_INTERVAL", 60)
+    }
+
+    //File receiving notifications as JSON lines, printed when missing.
+    pub fn get_notifier_file() -> Option<String> {
+        env::var("HELIX_NOTIFIER_FILE").ok()
+    }
+
+    //Shown by authenticator apps next to the account name.
+    pub fn get_two_factor_issuer() -> String {
+        env::var("HELIX_TWO_FACTOR_ISSUER").unwrap_or(TwoFactorSettings::default().issuer)
+    }
+
+    //In minutes, to enter the code once the password is checked.
+    pub fn get_two_factor_challenge_lifetime() -> i64 {
+        Configuration::parse_var(
+            "HELIX_TWO_FACTOR_CHALLENGE_LIFETIME",
+            TwoFactorSettings::default().challenge_lifetime,
+        )
+    }
+
+    //Login granted the admin role at startup, if any.
+    pub fn get_admin_login() -> Option<String> {
+        env::var("HELIX_ADMIN_LOGIN").ok()
+    }
+
+    //Served by the REST service.
+    pub fn get_static_folder() -> String {
+        env::var("HELIX_STATIC_FOLDER").expect("HELIX_STATIC_FOLDER not found.")
+    }
+
+    //IP:PORT of the gRPC service.
+    pub fn get_grpc_served_addr() -> String {
+        env::var("HELIX_GRPC_ADDR").unwrap_or("127.0.0.1:42420".to_string())
+    }
+
+    //Malformed values are not fatal, the default is used instead.
+    fn parse_var<T: FromStr>(name: &str, default: T) -> T {
+        match env::var(name) {
+            Ok(value) => value.parse().unwrap_or_else(|_| {
+                println!("--> Invalid {} value {:?}, using the default.", name, value);
+                default
+            }),
+            Err(_) => default,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+//Configuration and domain construction shared by the REST and gRPC services.
+pub mod configuration;
+pub mod state;
@@ -0,0 +1,209 @@
+use crate::configuration::Configuration;
+use chrono::{Duration, Utc};
+use helix_auth_lib::HelixAuth;
+use helix_user_domain::business::domain::UserDomain;
+use helix_user_domain::business::error::{UserDomainError, UserDomainResult};
+use helix_user_domain::business::lockout::LockoutSettings;
+use helix_user_domain::business::notifier::{FileNotifier, LogNotifier, Notifier};
+use helix_user_domain::business::password::PasswordSettings;
+use helix_user_domain::business::settings::UserDomainSettings;
+use helix_user_domain::business::token::{AccessTokenIssuer, TokenSettings};
+use helix_user_domain::business::traits::UserDomainTrait;
+use helix_user_domain::business::two_factor::TwoFactorSettings;
+use helix_user_domain::core::app_user::AppUser;
+use helix_user_domain::core::audit::AuditContext;
+use helix_user_domain::storage::traits::StorageTrait;
+use in_memory_storage::InMemoryUserStorage;
+use pg_db_storage::migration::MigrationMode;
+use pg_db_storage::PgDbUserStorage;
+use std::boxed::Box;
+use std::sync::Arc;
+use std::time::Duration as Interval;
+
+//Access tokens signed with the keys shared by the helix services.
+struct HelixTokenIssuer {}
+
+impl AccessTokenIssuer for HelixTokenIssuer {
+    fn issue(&self, user: &AppUser) -> UserDomainResult<String> {
+        let user_uuid = user
+            .uuid
+            .ok_or_else(|| UserDomainError::missing_uuid("User"))?;
+        let person_uuid = user
+            .person
+            .uuid
+            .ok_or_else(|| UserDomainError::missing_uuid("Person"))?;
+        HelixAuth::generate_tokens(&user.login, &user_uuid, &person_uuid)
+            .map(|generated_keys| generated_keys.0)
+            .map_err(|_| UserDomainError::TokenGenerationError)
+    }
+}
+
+//Cheap to clone, handlers and the purge job share the same domain without locking.
+#[derive(Clone)]
+pub struct AppState {
+    user_domain: Arc<dyn UserDomainTrait>,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        AppState::from_domain(Arc::new(UserDomain::new(
+            AppState::get_storage(),
+            Box::new(HelixTokenIssuer {}),
+            AppState::get_notifier(),
+            AppState::get_domain_settings(),
+        )))
+    }
+
+    pub fn from_domain(user_domain: Arc<dyn UserDomainTrait>) -> Self {
+        AppState { user_domain }
+    }
+
+    //Bring the database schema up to date, according to HELIX_DB_MIGRATION.
+    pub async fn migrate_database() {
+        let mode = Configuration::get_migration_mode();
+        if mode == MigrationMode::None || Configuration::get_storage_type() == "memory" {
+            return;
+        }
+
+        let migrations = AppState::get_pg_storage()
+            .migrate(mode.clone())
+            .await
+            .expect("Database migration failed.");
+
+        for migration in migrations {
+            let state = match (&migration.applied_on, &mode) {
+                (Some(applied_on), _) => format!("applied on {}", applied_on),
+                (None, MigrationMode::DryRun) => "checked, would be applied".to_string(),
+                (None, _) => "pending".to_string(),
+            };
+            println!(
+                "--> Migration V{:03} {}: {}",
+                migration.version, migration.name, state
+            );
+        }
+    }
+
+    //The first admin cannot be appointed through the api, HELIX_ADMIN_LOGIN names it.
+    //It belongs to the default organization, admins of the others come with them.
+    pub async fn bootstrap_admin(&self) {
+        let login = match Configuration::get_admin_login() {
+            Some(login) => login,
+            None => return,
+        };
+
+        let domain = self.get_domain();
+        let result = match domain.resolve_organization(None).await {
+            Ok(organization) => {
+                domain
+                    .ensure_admin(&organization, &login, &AuditContext::default())
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(_) => println!("--> Admin role granted to {}.", login),
+            Err(error) => println!("--> Admin role not granted to {}: {}", login, error),
+        }
+    }
+
+    //Remove for good the users and persons deleted longer than the retention ago.
+    pub async fn purge_deleted(&self) {
+        let before = Utc::now() - Duration::days(Configuration::get_deleted_retention());
+        let domain = self.get_domain();
+        let organizations = match domain.get_all_organizations().await {
+            Ok(organizations) => organizations,
+            Err(error) => return println!("--> Purge of deleted records failed: {}", error),
+        };
+
+        for organization in organizations {
+            match domain.purge_deleted(&organization, before).await {
+                Ok(0) => {}
+                Ok(count) => println!(
+                    "--> Purged {} deleted records of {}.",
+                    count, organization.name
+                ),
+                Err(error) => println!(
+                    "--> Purge of deleted records of {} failed: {}",
+                    organization.name, error
+                ),
+            }
+        }
+    }
+
+    //Purge loop run by each service, the purge lock keeps them from overlapping.
+    pub async fn purge_periodically(self) {
+        let interval = Interval::from_secs(60 * Configuration::get_purge_interval());
+        loop {
+            self.purge_deleted().await;
+            tokio::time::delay_for(interval).await;
+        }
+    }
+
+    pub fn get_domain(&self) -> &dyn UserDomainTrait {
+        self.user_domain.as_ref()
+    }
+
+    fn get_domain_settings() -> UserDomainSettings {
+        UserDomainSettings {
+            password: PasswordSettings {
+                memory_cost: Configuration::get_password_memory_cost(),
+                time_cost: Configuration::get_password_time_cost(),
+                parallelism: Configuration::get_password_parallelism(),
+            },
+            lockout: LockoutSettings {
+                max_attempts: Configuration::get_lockout_max_attempts(),
+                ip_max_attempts: Configuration::get_lockout_ip_max_attempts(),
+                window: Configuration::get_lockout_window(),
+                base_delay: Configuration::get_lockout_base_delay(),
+                max_delay: Configuration::get_lockout_max_delay(),
+            },
+            token: TokenSettings {
+                access_lifetime: Configuration::get_access_token_lifetime(),
+                refresh_lifetime: Configuration::get_refresh_token_lifetime(),
+                reset_lifetime: Configuration::get_password_reset_lifetime(),
+                verification_lifetime: Configuration::get_email_verification_lifetime(),
+            },
+            two_factor: TwoFactorSettings {
+                issuer: Configuration::get_two_factor_issuer(),
+                challenge_lifetime: Configuration::get_two_factor_challenge_lifetime(),
+                ..TwoFactorSettings::default()
+            },
+            require_verified_email: Configuration::get_require_verified_email(),
+            require_activation: Configuration::get_require_activation(),
+        }
+    }
+
+    fn get_notifier() -> Box<dyn Notifier> {
+        match Configuration::get_notifier_file() {
+            Some(path) => Box::new(FileNotifier::new(path)),
+            None => {
+                println!("--> No notifier file, notifications will be printed.");
+                Box::new(LogNotifier)
+            }
+        }
+    }
+
+    fn get_storage() -> Box<dyn StorageTrait> {
+        match Configuration::get_storage_type().as_str() {
+            "memory" => {
+                println!("--> Using in-memory storage, data will not be persisted.");
+                Box::new(InMemoryUserStorage::new())
+            }
+            _ => AppState::get_pg_storage(),
+        }
+    }
+
+    fn get_pg_storage() -> Box<PgDbUserStorage> {
+        Box::new(
+            PgDbUserStorage::new(
+                Configuration::get_database_name(),
+                Configuration::get_database_host(),
+                Configuration::get_database_port(),
+                Configuration::get_database_user(),
+                Configuration::get_database_password(),
+            )
+            .unwrap(),
+        )
+    }
+}
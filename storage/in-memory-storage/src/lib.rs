@@ -0,0 +1,1774 @@
+use async_trait::async_trait;
+use chrono::prelude::*;
+use helix_user_domain::core::app_user::*;
+use helix_user_domain::core::audit::*;
+use helix_user_domain::core::group::*;
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::password_reset::*;
+use helix_user_domain::core::person::*;
+use helix_user_domain::core::query::*;
+use helix_user_domain::core::role::*;
+use helix_user_domain::core::session::*;
+use helix_user_domain::core::token::*;
+use helix_user_domain::core::two_factor::*;
+use helix_user_domain::storage::error::*;
+use helix_user_domain::storage::traits::{StorageTrait, StorageTransaction};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+//Users keep a reference to their person, like the person_ column.
+#[derive(Clone)]
+struct UserRow {
+    user: AppUser,
+    person_id: i32,
+}
+
+//Rows of one organization.
+#[derive(Default, Clone)]
+struct InMemoryData {
+    persons: BTreeMap<i32, Person>,
+    email_verifications: Vec<EmailVerification>,
+    users: BTreeMap<i32, UserRow>,
+    status_changes: Vec<StatusChange>,
+    login_counters: HashMap<(&'static str, String), LoginCounter>,
+    login_events: Vec<LoginEvent>,
+    sessions: Vec<Session>,
+    refresh_tokens: Vec<RefreshToken>,
+    //By user id, like the user_ primary key.
+    two_factors: HashMap<i32, TwoFactor>,
+    recovery_codes: Vec<RecoveryCode>,
+    login_challenges: Vec<LoginChallenge>,
+    password_resets: Vec<PasswordReset>,
+    roles: BTreeMap<i32, Role>,
+    //(user id, role id) pairs, like the user_role table.
+    user_roles: Vec<(i32, i32)>,
+    groups: BTreeMap<i32, Group>,
+    //(group id, user id) and (group id, role id) pairs.
+    group_members: Vec<(i32, i32)>,
+    group_roles: Vec<(i32, i32)>,
+    audit_entries: Vec<AuditEntry>,
+}
+
+impl InMemoryData {
+    fn load_user(&self, row: &UserRow) -> Option<AppUser> {
+        let person = self.persons.get(&row.person_id)?;
+        let mut user = row.user.clone();
+        user.person = person.clone();
+        Some(user)
+    }
+
+    //Rows hanging off a user, removed along with it like the database cascades do.
+    fn remove_user(&mut self, user_id: i32) {
+        self.users.remove(&user_id);
+        self.status_changes
+            .retain(|change| change.user_id != user_id);
+        self.login_events
+            .retain(|event| event.user_id != Some(user_id));
+        self.sessions.retain(|session| session.user_id != user_id);
+        self.refresh_tokens.retain(|token| token.user_id != user_id);
+        self.two_factors.remove(&user_id);
+        self.recovery_codes.retain(|code| code.user_id != user_id);
+        self.login_challenges
+            .retain(|challenge| challenge.user_id != user_id);
+        self.password_resets
+            .retain(|reset| reset.user_id != user_id);
+        self.user_roles.retain(|(id, _)| *id != user_id);
+        self.group_members.retain(|(_, id)| *id != user_id);
+    }
+
+    fn remove_person(&mut self, person_id: i32) {
+        self.persons.remove(&person_id);
+        self.email_verifications
+            .retain(|verification| verification.person_id != person_id);
+    }
+
+    //Same rules as the unique indexes of the database, case is ignored.
+    fn check_login(&self, login: &str, user_id: i32) -> StorageResult<()> {
+        let login = login.to_lowercase();
+        match self
+            .users
+            .values()
+            .any(|row| row.user.id != user_id && row.user.login.to_lowercase() == login)
+        {
+            true => Err(StorageError::Conflict {
+                field: "login".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    fn check_email(&self, email: &str, person_id: i32) -> StorageResult<()> {
+        let email = email.to_lowercase();
+        match self
+            .persons
+            .values()
+            .any(|person| person.id != person_id && person.email.to_lowercase() == email)
+        {
+            true => Err(StorageError::Conflict {
+                field: "email".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    //Parent uuids are resolved on read, like the parent join.
+    fn load_group(&self, group: &Group) -> Group {
+        let mut group = group.clone();
+        group.parent_uuid = group
+            .parent_id
+            .and_then(|parent_id| self.groups.get(&parent_id))
+            .and_then(|parent| parent.uuid);
+        group
+    }
+
+    fn check_group_name(&self, name: &str, group_id: i32) -> StorageResult<()> {
+        let name = name.to_lowercase();
+        match self
+            .groups
+            .values()
+            .any(|group| group.id != group_id && group.name.to_lowercase() == name)
+        {
+            true => Err(StorageError::Conflict {
+                field: "name".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    fn check_role_name(&self, name: &str, role_id: i32) -> StorageResult<()> {
+        let name = name.to_lowercase();
+        match self
+            .roles
+            .values()
+            .any(|role| role.id != role_id && role.name.to_lowercase() == name)
+        {
+            true => Err(StorageError::Conflict {
+                field: "name".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+//Case insensitive prefix filter, None matches everything.
+fn has_prefix(value: &str, prefix: &Option<String>) -> bool {
+    match prefix {
+        Some(prefix) => value.to_lowercase().starts_with(&prefix.to_lowercase()),
+        None => true,
+    }
+}
+
+fn is_in_range(
+    value: &Option<DateTime<Utc>>,
+    from: &Option<DateTime<Utc>>,
+    to: &Option<DateTime<Utc>>,
+) -> bool {
+    match (value, from, to) {
+        (None, None, None) => true,
+        (None, _, _) => false,
+        (Some(value), from, to) => {
+            from.is_none_or(|from| *value >= from) && to.is_none_or(|to| *value <= to)
+        }
+    }
+}
+
+//Items must be sorted ascending, the direction is applied here.
+fn get_page<T>(mut items: Vec<T>, direction: SortDirection, pagination: &Pagination) -> Page<T> {
+    if direction == SortDirection::Desc {
+        items.reverse();
+    }
+
+    let offset = pagination.get_offset().unwrap_or(0);
+    let limit = pagination.get_limit();
+    let total = items.len() as i64;
+    let items = items
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect();
+
+    Page::new(items, total, offset, limit)
+}
+
+//Writes to an unknown organization fail, like the organization_ foreign keys.
+fn get_tenant_mut(
+    tenants: &mut HashMap<i32, InMemoryData>,
+    organization_id: i32,
+) -> StorageResult<&mut InMemoryData> {
+    tenants
+        .get_mut(&organization_id)
+        .ok_or(StorageError::CreationImpossible)
+}
+
+//Future awaited before the user reads, see with_read_hook.
+pub type ReadHook = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+//Rows as they were when a transaction began.
+struct Snapshot {
+    organizations: BTreeMap<i32, Organization>,
+    tenants: HashMap<i32, InMemoryData>,
+}
+
+//Non persistent storage, meant for tests and local development.
+//Transactions share the rows of the storage they began from, their writes are seen
+//right away. Other writes wait for the transaction to end, so a rollback putting back
+//the rows of the begin only undoes the writes of the transaction.
+pub struct InMemoryUserStorage {
+    //Ids are unique across tables and organizations, like sequences they are never reused.
+    sequence: Arc<AtomicI32>,
+    organizations: Arc<RwLock<BTreeMap<i32, Organization>>>,
+    tenants: Arc<RwLock<HashMap<i32, InMemoryData>>>,
+    //Held by the open transaction, and by the writes made outside of it.
+    writes: Arc<Mutex<()>>,
+    //Set on the storage of a transaction, its writes run under the lock of the transaction.
+    in_transaction: bool,
+    //Read in place of unknown organizations.
+    empty: InMemoryData,
+    read_hook: Option<ReadHook>,
+}
+
+//Transaction opened by begin, other writes wait until it ends.
+pub struct InMemoryUserTransaction {
+    storage: InMemoryUserStorage,
+    snapshot: Option<Snapshot>,
+    //Released once the snapshot is restored, see drop.
+    _writes: OwnedMutexGuard<()>,
+}
+
+impl Default for InMemoryUserStorage {
+    fn default() -> Self {
+        InMemoryUserStorage::new()
+    }
+}
+
+impl InMemoryUserStorage {
+    //Starts with the default organization, as the migrations do.
+    pub fn new() -> InMemoryUserStorage {
+        let storage = InMemoryUserStorage {
+            sequence: Arc::new(AtomicI32::new(0)),
+            organizations: Arc::new(RwLock::new(BTreeMap::new())),
+            tenants: Arc::new(RwLock::new(HashMap::new())),
+            writes: Arc::new(Mutex::new(())),
+            in_transaction: false,
+            empty: InMemoryData::default(),
+            read_hook: None,
+        };
+
+        let default = Organization::new(
+            storage.next_id(),
+            Some(Organization::DEFAULT_UUID),
+            "default".to_string(),
+            Some(Utc::now()),
+            None,
+        );
+        storage
+            .tenants
+            .write()
+            .unwrap()
+            .insert(default.id, InMemoryData::default());
+        storage
+            .organizations
+            .write()
+            .unwrap()
+            .insert(default.id, default);
+        storage
+    }
+
+    //Awaits the hook before each get_user, tests use it to slow down or hold requests.
+    pub fn with_read_hook(mut self, hook: ReadHook) -> InMemoryUserStorage {
+        self.read_hook = Some(hook);
+        self
+    }
+
+    //Writes wait for the open transaction, those of the transaction already hold the lock.
+    async fn lock_writes(&self) -> Option<OwnedMutexGuard<()>> {
+        match self.in_transaction {
+            true => None,
+            false => Some(self.writes.clone().lock_owned().await),
+        }
+    }
+
+    fn next_id(&self) -> i32 {
+        self.sequence.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn get_tenant<'a>(
+        &'a self,
+        tenants: &'a HashMap<i32, InMemoryData>,
+        organization_id: i32,
+    ) -> &'a InMemoryData {
+        tenants.get(&organization_id).unwrap_or(&self.empty)
+    }
+
+    fn check_organization_name(
+        organizations: &BTreeMap<i32, Organization>,
+        name: &str,
+        organization_id: i32,
+    ) -> StorageResult<()> {
+        let name = name.to_lowercase();
+        match organizations.values().any(|organization| {
+            organization.id != organization_id && organization.name.to_lowercase() == name
+        }) {
+            true => Err(StorageError::Conflict {
+                field: "name".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+impl InMemoryUserTransaction {
+    fn restore_snapshot(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.storage.organizations.write().unwrap() = snapshot.organizations;
+            *self.storage.tenants.write().unwrap() = snapshot.tenants;
+        }
+    }
+}
+
+impl Drop for InMemoryUserTransaction {
+    fn drop(&mut self) {
+        self.restore_snapshot();
+    }
+}
+
+#[async_trait]
+impl StorageTransaction for InMemoryUserTransaction {
+    fn as_storage(&self) -> &dyn StorageTrait {
+        &self.storage
+    }
+
+    async fn commit(mut self: Box<Self>) -> StorageResult<()> {
+        match self.snapshot.take() {
+            Some(_) => Ok(()),
+            None => Err(StorageError::NoTransaction),
+        }
+    }
+
+    async fn rollback(mut self: Box<Self>) -> StorageResult<()> {
+        if self.snapshot.is_none() {
+            return Err(StorageError::NoTransaction);
+        }
+        self.restore_snapshot();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageTrait for InMemoryUserStorage {
+    async fn get_all_organizations(&self) -> StorageResult<Vec<Organization>> {
+        let organizations = self.organizations.read().unwrap();
+        let mut result: Vec<Organization> = organizations.values().cloned().collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn get_organization_by_uuid(
+        &self,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Organization>> {
+        let organizations = self.organizations.read().unwrap();
+        Ok(organizations
+            .values()
+            .find(|organization| organization.uuid.as_ref() == Some(uuid))
+            .cloned())
+    }
+
+    async fn get_organization_by_id(&self, id: i32) -> StorageResult<Option<Organization>> {
+        let organizations = self.organizations.read().unwrap();
+        Ok(organizations.get(&id).cloned())
+    }
+
+    async fn create_organization(
+        &self,
+        mut organization: Organization,
+    ) -> StorageResult<Organization> {
+        organization.created_on = Some(Utc::now());
+        organization.updated_on = None;
+
+        let _writes = self.lock_writes().await;
+        let mut organizations = self.organizations.write().unwrap();
+        InMemoryUserStorage::check_organization_name(&organizations, &organization.name, 0)?;
+        organization.id = self.next_id();
+        organization.uuid = Some(uuid::Uuid::new_v4());
+        organizations.insert(organization.id, organization.clone());
+        self.tenants
+            .write()
+            .unwrap()
+            .insert(organization.id, InMemoryData::default());
+
+        Ok(organization)
+    }
+
+    async fn update_organization(
+        &self,
+        mut organization: Organization,
+    ) -> StorageResult<Organization> {
+        organization.updated_on = Some(Utc::now());
+
+        let _writes = self.lock_writes().await;
+        let mut organizations = self.organizations.write().unwrap();
+        InMemoryUserStorage::check_organization_name(
+            &organizations,
+            &organization.name,
+            organization.id,
+        )?;
+        if let Some(stored_organization) = organizations.get_mut(&organization.id) {
+            stored_organization.name = organization.name.clone();
+            stored_organization.updated_on = organization.updated_on;
+        }
+
+        Ok(organization)
+    }
+
+    async fn get_user_by_login(
+        &self,
+        organization_id: i32,
+        login: &str,
+    ) -> StorageResult<Option<AppUser>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .users
+            .values()
+            .filter(|row| row.user.login.to_lowercase() == login.to_lowercase())
+            .filter(|row| !row.user.is_deleted())
+            .filter_map(|row| data.load_user(row))
+            .next_back())
+    }
+
+    async fn update_user_password(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        password: String,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(row) = data.users.get_mut(&user_id) {
+            row.user.password = password;
+        }
+        Ok(())
+    }
+
+    async fn update_last_login(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(row) = data.users.get_mut(&user_id) {
+            row.user.last_login_on = Some(on);
+        }
+        Ok(())
+    }
+
+    async fn get_user(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<AppUser>> {
+        if let Some(hook) = &self.read_hook {
+            hook().await;
+        }
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .users
+            .values()
+            .filter(|row| row.user.uuid.as_ref() == Some(uuid))
+            .filter(|row| include_deleted || !row.user.is_deleted())
+            .filter_map(|row| data.load_user(row))
+            .map(|mut user| {
+                //Do not restitute password
+                user.password = "".to_string();
+                user
+            })
+            .next_back())
+    }
+
+    async fn get_user_with_password(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<AppUser>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .users
+            .values()
+            .filter(|row| row.user.uuid.as_ref() == Some(uuid))
+            .filter(|row| !row.user.is_deleted())
+            .filter_map(|row| data.load_user(row))
+            .next_back())
+    }
+
+    async fn get_user_by_id(
+        &self,
+        organization_id: i32,
+        id: i32,
+    ) -> StorageResult<Option<AppUser>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .users
+            .get(&id)
+            .filter(|row| !row.user.is_deleted())
+            .and_then(|row| data.load_user(row))
+            .map(|mut user| {
+                //Do not restitute password
+                user.password = "".to_string();
+                user
+            }))
+    }
+
+    async fn get_all_users(
+        &self,
+        organization_id: i32,
+        query: &UserQuery,
+    ) -> StorageResult<Page<AppUser>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<AppUser> = data
+            .users
+            .values()
+            .filter(|row| query.include_deleted || !row.user.is_deleted())
+            .filter_map(|row| data.load_user(row))
+            .filter(|user| has_prefix(&user.login, &query.login))
+            .filter(|user| has_prefix(&user.person.email, &query.email))
+            .filter(|user| {
+                has_prefix(&user.person.firstname, &query.name)
+                    || has_prefix(&user.person.lastname, &query.name)
+            })
+            .filter(|user| is_in_range(&user.created_on, &query.created_from, &query.created_to))
+            .collect();
+
+        result.sort_by(|a, b| {
+            let ordering = match query.sort {
+                UserSortField::Login => a.login.cmp(&b.login),
+                UserSortField::Email => a.person.email.cmp(&b.person.email),
+                UserSortField::Firstname => a.person.firstname.cmp(&b.person.firstname),
+                UserSortField::Lastname => a.person.lastname.cmp(&b.person.lastname),
+                UserSortField::CreatedOn => a.created_on.cmp(&b.created_on),
+            };
+            ordering.then(a.id.cmp(&b.id))
+        });
+
+        Ok(get_page(result, query.direction, &query.get_pagination()))
+    }
+
+    async fn create_user(&self, organization_id: i32, mut user: AppUser) -> StorageResult<AppUser> {
+        user.created_on = Some(Utc::now());
+        user.updated_on = None;
+        user.last_login_on = None;
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_login(&user.login, 0)?;
+        user.id = self.next_id();
+        user.uuid = Some(uuid::Uuid::new_v4());
+
+        let row = UserRow {
+            user: user.clone(),
+            person_id: user.person.id,
+        };
+        data.users.insert(user.id, row);
+
+        Ok(user)
+    }
+
+    async fn update_user(&self, organization_id: i32, mut user: AppUser) -> StorageResult<AppUser> {
+        user.updated_on = Some(Utc::now());
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_login(&user.login, user.id)?;
+        if let Some(row) = data.users.get_mut(&user.id) {
+            let mut stored_user = user.clone();
+            //An empty password keeps the stored hash.
+            if stored_user.password.is_empty() {
+                stored_user.password = row.user.password.clone();
+            }
+            stored_user.deleted_on = row.user.deleted_on;
+            stored_user.deleted_by = row.user.deleted_by;
+            row.person_id = user.person.id;
+            row.user = stored_user;
+        }
+
+        Ok(user)
+    }
+
+    async fn delete_user(&self, organization_id: i32, user: AppUser) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.remove_user(user.id);
+        Ok(())
+    }
+
+    async fn update_user_deletion(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(row) = data.users.get_mut(&user_id) {
+            row.user.deleted_on = deleted_on;
+            row.user.deleted_by = deleted_by;
+        }
+        Ok(())
+    }
+
+    async fn purge_deleted_users(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        let user_ids: Vec<i32> = data
+            .users
+            .values()
+            .filter(|row| row.user.deleted_on.is_some_and(|on| on <= before))
+            .map(|row| row.user.id)
+            .collect();
+        for user_id in &user_ids {
+            data.remove_user(*user_id);
+        }
+        Ok(user_ids.len() as u64)
+    }
+
+    async fn change_user_status(
+        &self,
+        organization_id: i32,
+        mut change: StatusChange,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(row) = data.users.get_mut(&change.user_id) {
+            row.user.status = change.status;
+            row.user.status_reason = change.reason.clone();
+            row.user.suspended_until = change.suspended_until;
+            change.id = self.next_id();
+            data.status_changes.push(change);
+        }
+        Ok(())
+    }
+
+    async fn get_status_changes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<StatusChange>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<StatusChange> = data
+            .status_changes
+            .iter()
+            .filter(|change| change.user_id == user_id)
+            .cloned()
+            .collect();
+
+        result.sort_by(|a, b| b.changed_on.cmp(&a.changed_on).then(b.id.cmp(&a.id)));
+        Ok(result)
+    }
+
+    async fn create_person(
+        &self,
+        organization_id: i32,
+        mut person: Person,
+    ) -> StorageResult<Person> {
+        person.created_on = Some(Utc::now());
+        person.updated_on = None;
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_email(&person.email, 0)?;
+        person.id = self.next_id();
+        person.uuid = Some(uuid::Uuid::new_v4());
+        data.persons.insert(person.id, person.clone());
+
+        Ok(person)
+    }
+
+    async fn update_person(
+        &self,
+        organization_id: i32,
+        mut person: Person,
+    ) -> StorageResult<Person> {
+        person.updated_on = Some(Utc::now());
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_email(&person.email, person.id)?;
+        if let Some(stored_person) = data.persons.get_mut(&person.id) {
+            stored_person.firstname = person.firstname.clone();
+            stored_person.lastname = person.lastname.clone();
+            stored_person.email = person.email.clone();
+            stored_person.phone = person.phone.clone();
+            stored_person.updated_on = person.updated_on;
+            stored_person.email_status = person.email_status;
+            stored_person.email_verified_on = person.email_verified_on;
+            person.deleted_on = stored_person.deleted_on;
+            person.deleted_by = stored_person.deleted_by;
+        }
+
+        Ok(person)
+    }
+
+    async fn delete_person(&self, organization_id: i32, person: Person) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.remove_person(person.id);
+        Ok(())
+    }
+
+    async fn update_person_deletion(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(person) = data.persons.get_mut(&person_id) {
+            person.deleted_on = deleted_on;
+            person.deleted_by = deleted_by;
+        }
+        Ok(())
+    }
+
+    async fn purge_deleted_persons(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        let person_ids: Vec<i32> = data
+            .persons
+            .values()
+            .filter(|person| person.deleted_on.is_some_and(|on| on <= before))
+            .filter(|person| !data.users.values().any(|row| row.person_id == person.id))
+            .map(|person| person.id)
+            .collect();
+        for person_id in &person_ids {
+            data.remove_person(*person_id);
+        }
+        Ok(person_ids.len() as u64)
+    }
+
+    //Transactions already run one at a time, the one asking holds every write.
+    async fn try_lock_purge(&self) -> StorageResult<bool> {
+        match self.in_transaction {
+            true => Ok(true),
+            false => Err(StorageError::NoTransaction),
+        }
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<Person>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .persons
+            .values()
+            .filter(|person| include_deleted || !person.is_deleted())
+            .find(|person| person.uuid.as_ref() == Some(uuid))
+            .cloned())
+    }
+
+    async fn get_person_by_id(
+        &self,
+        organization_id: i32,
+        id: i32,
+    ) -> StorageResult<Option<Person>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .persons
+            .get(&id)
+            .filter(|person| !person.is_deleted())
+            .cloned())
+    }
+
+    async fn get_person_by_email(
+        &self,
+        organization_id: i32,
+        email: &str,
+    ) -> StorageResult<Option<Person>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .persons
+            .values()
+            .filter(|person| !person.is_deleted())
+            .find(|person| person.email.to_lowercase() == email.to_lowercase())
+            .cloned())
+    }
+
+    async fn get_all_person(
+        &self,
+        organization_id: i32,
+        query: &PersonQuery,
+    ) -> StorageResult<Page<Person>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Person> = data
+            .persons
+            .values()
+            .filter(|person| query.include_deleted || !person.is_deleted())
+            .filter(|person| has_prefix(&person.email, &query.email))
+            .filter(|person| {
+                has_prefix(&person.firstname, &query.name)
+                    || has_prefix(&person.lastname, &query.name)
+            })
+            .filter(|person| {
+                is_in_range(&person.created_on, &query.created_from, &query.created_to)
+            })
+            .cloned()
+            .collect();
+
+        result.sort_by(|a, b| {
+            let ordering = match query.sort {
+                PersonSortField::Firstname => a.firstname.cmp(&b.firstname),
+                PersonSortField::Lastname => a.lastname.cmp(&b.lastname),
+                PersonSortField::Email => a.email.cmp(&b.email),
+                PersonSortField::CreatedOn => a.created_on.cmp(&b.created_on),
+            };
+            ordering.then(a.id.cmp(&b.id))
+        });
+
+        Ok(get_page(result, query.direction, &query.get_pagination()))
+    }
+
+    async fn update_email_status(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        status: EmailStatus,
+        verified_on: Option<DateTime<Utc>>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(person) = data.persons.get_mut(&person_id) {
+            person.email_status = status;
+            person.email_verified_on = verified_on;
+        }
+        Ok(())
+    }
+
+    async fn create_email_verification(
+        &self,
+        organization_id: i32,
+        mut verification: EmailVerification,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        verification.id = self.next_id();
+        data.email_verifications.push(verification);
+        Ok(())
+    }
+
+    async fn get_email_verification(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<EmailVerification>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .email_verifications
+            .iter()
+            .find(|verification| verification.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn delete_person_email_verifications(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.email_verifications
+            .retain(|verification| verification.person_id != person_id);
+        Ok(())
+    }
+
+    async fn delete_expired_email_verifications(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.email_verifications
+            .retain(|verification| verification.expires_on > now);
+        Ok(())
+    }
+
+    async fn get_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<Option<LoginCounter>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .login_counters
+            .get(&(scope.as_str(), key.to_string()))
+            .cloned())
+    }
+
+    async fn save_login_counter(
+        &self,
+        organization_id: i32,
+        counter: LoginCounter,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.login_counters
+            .insert((counter.scope.as_str(), counter.key.clone()), counter);
+        Ok(())
+    }
+
+    async fn delete_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.login_counters
+            .remove(&(scope.as_str(), key.to_string()));
+        Ok(())
+    }
+
+    async fn add_login_event(
+        &self,
+        organization_id: i32,
+        mut event: LoginEvent,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        event.id = self.next_id();
+        data.login_events.push(event);
+        Ok(())
+    }
+
+    async fn get_login_events(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        pagination: &Pagination,
+    ) -> StorageResult<Page<LoginEvent>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<LoginEvent> = data
+            .login_events
+            .iter()
+            .filter(|event| event.user_id == Some(user_id))
+            .cloned()
+            .collect();
+
+        result.sort_by(|a, b| a.occurred_on.cmp(&b.occurred_on).then(a.id.cmp(&b.id)));
+        Ok(get_page(result, SortDirection::Desc, pagination))
+    }
+
+    async fn create_session(
+        &self,
+        organization_id: i32,
+        mut session: Session,
+    ) -> StorageResult<Session> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        session.id = self.next_id();
+        session.organization_id = organization_id;
+        session.uuid = Some(uuid::Uuid::new_v4());
+        data.sessions.push(session.clone());
+        Ok(session)
+    }
+
+    async fn get_session(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Session>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .sessions
+            .iter()
+            .find(|session| session.uuid.as_ref() == Some(uuid))
+            .cloned())
+    }
+
+    async fn get_session_by_access_token(
+        &self,
+        access_token_hash: &str,
+    ) -> StorageResult<Option<Session>> {
+        let tenants = self.tenants.read().unwrap();
+        Ok(tenants
+            .values()
+            .flat_map(|data| data.sessions.iter())
+            .find(|session| session.access_token_hash.as_deref() == Some(access_token_hash))
+            .cloned())
+    }
+
+    async fn get_user_sessions(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<Vec<Session>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.user_id == user_id && session.expires_on > now)
+            .cloned()
+            .collect();
+
+        result.sort_by(|a, b| {
+            let a_used_on = a.refreshed_on.unwrap_or(a.created_on);
+            let b_used_on = b.refreshed_on.unwrap_or(b.created_on);
+            b_used_on.cmp(&a_used_on).then(b.id.cmp(&a.id))
+        });
+        Ok(result)
+    }
+
+    async fn update_session(&self, organization_id: i32, session: Session) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if let Some(stored_session) = data
+            .sessions
+            .iter_mut()
+            .find(|stored_session| stored_session.id == session.id)
+        {
+            stored_session.refreshed_on = session.refreshed_on;
+            stored_session.expires_on = session.expires_on;
+            stored_session.access_token_hash = session.access_token_hash;
+            stored_session.access_expires_on = session.access_expires_on;
+        }
+        Ok(())
+    }
+
+    async fn delete_session(&self, organization_id: i32, uuid: &uuid::Uuid) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.sessions
+            .retain(|session| session.uuid.as_ref() != Some(uuid));
+        data.refresh_tokens.retain(|token| &token.family != uuid);
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, organization_id: i32, user_id: i32) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.sessions.retain(|session| session.user_id != user_id);
+        data.refresh_tokens.retain(|token| token.user_id != user_id);
+        Ok(())
+    }
+
+    async fn delete_expired_sessions(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        let expired: Vec<uuid::Uuid> = data
+            .sessions
+            .iter()
+            .filter(|session| session.expires_on <= now)
+            .filter_map(|session| session.uuid)
+            .collect();
+        data.sessions.retain(|session| session.expires_on > now);
+        data.refresh_tokens
+            .retain(|token| !expired.contains(&token.family));
+        Ok(())
+    }
+
+    async fn add_refresh_token(
+        &self,
+        organization_id: i32,
+        mut token: RefreshToken,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        token.id = self.next_id();
+        data.refresh_tokens.push(token);
+        Ok(())
+    }
+
+    async fn get_refresh_token(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<RefreshToken>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .refresh_tokens
+            .iter()
+            .find(|token| token.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        match data
+            .refresh_tokens
+            .iter_mut()
+            .find(|token| token.id == id && token.rotated_on.is_none())
+        {
+            Some(token) => {
+                token.rotated_on = Some(on);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn get_two_factor(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Option<TwoFactor>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data.two_factors.get(&user_id).cloned())
+    }
+
+    async fn save_two_factor(
+        &self,
+        organization_id: i32,
+        two_factor: TwoFactor,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.two_factors.insert(two_factor.user_id, two_factor);
+        Ok(())
+    }
+
+    async fn delete_two_factor(&self, organization_id: i32, user_id: i32) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.two_factors.remove(&user_id);
+        data.recovery_codes.retain(|code| code.user_id != user_id);
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hashes: Vec<String>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.recovery_codes.retain(|code| code.user_id != user_id);
+        for code_hash in code_hashes {
+            data.recovery_codes.push(RecoveryCode {
+                id: self.next_id(),
+                user_id,
+                code_hash,
+                used_on: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn get_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<RecoveryCode>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .recovery_codes
+            .iter()
+            .filter(|code| code.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn use_recovery_code(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hash: &str,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        match data.recovery_codes.iter_mut().find(|code| {
+            code.user_id == user_id && code.code_hash == code_hash && code.used_on.is_none()
+        }) {
+            Some(code) => {
+                code.used_on = Some(on);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn create_login_challenge(
+        &self,
+        organization_id: i32,
+        mut challenge: LoginChallenge,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        challenge.id = self.next_id();
+        data.login_challenges.push(challenge);
+        Ok(())
+    }
+
+    async fn get_login_challenge(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<LoginChallenge>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .login_challenges
+            .iter()
+            .find(|challenge| challenge.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn delete_login_challenge(&self, organization_id: i32, id: i32) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.login_challenges.retain(|challenge| challenge.id != id);
+        Ok(())
+    }
+
+    async fn delete_expired_login_challenges(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.login_challenges
+            .retain(|challenge| challenge.expires_on > now);
+        Ok(())
+    }
+
+    async fn create_password_reset(
+        &self,
+        organization_id: i32,
+        mut reset: PasswordReset,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        reset.id = self.next_id();
+        data.password_resets.push(reset);
+        Ok(())
+    }
+
+    async fn get_password_reset(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<PasswordReset>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .password_resets
+            .iter()
+            .find(|reset| reset.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn use_password_reset(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        match data
+            .password_resets
+            .iter_mut()
+            .find(|reset| reset.id == id && reset.used_on.is_none())
+        {
+            Some(reset) => {
+                reset.used_on = Some(on);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_user_password_resets(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.password_resets
+            .retain(|reset| reset.user_id != user_id);
+        Ok(())
+    }
+
+    async fn delete_expired_password_resets(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.password_resets.retain(|reset| reset.expires_on > now);
+        Ok(())
+    }
+
+    async fn get_all_roles(&self, organization_id: i32) -> StorageResult<Vec<Role>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Role> = data.roles.values().cloned().collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn get_role_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Role>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .roles
+            .values()
+            .find(|role| role.uuid.as_ref() == Some(uuid))
+            .cloned())
+    }
+
+    async fn get_role_by_name(
+        &self,
+        organization_id: i32,
+        name: &str,
+    ) -> StorageResult<Option<Role>> {
+        let name = name.to_lowercase();
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .roles
+            .values()
+            .find(|role| role.name.to_lowercase() == name)
+            .cloned())
+    }
+
+    async fn create_role(&self, organization_id: i32, mut role: Role) -> StorageResult<Role> {
+        role.created_on = Some(Utc::now());
+        role.updated_on = None;
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_role_name(&role.name, 0)?;
+        role.id = self.next_id();
+        role.uuid = Some(uuid::Uuid::new_v4());
+        data.roles.insert(role.id, role.clone());
+
+        Ok(role)
+    }
+
+    async fn update_role(&self, organization_id: i32, mut role: Role) -> StorageResult<Role> {
+        role.updated_on = Some(Utc::now());
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_role_name(&role.name, role.id)?;
+        if let Some(stored_role) = data.roles.get_mut(&role.id) {
+            stored_role.name = role.name.clone();
+            stored_role.description = role.description.clone();
+            stored_role.permissions = role.permissions.clone();
+            stored_role.updated_on = role.updated_on;
+        }
+
+        Ok(role)
+    }
+
+    async fn delete_role(&self, organization_id: i32, role: Role) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.roles.remove(&role.id);
+        data.user_roles.retain(|(_, role_id)| *role_id != role.id);
+        data.group_roles.retain(|(_, role_id)| *role_id != role.id);
+        Ok(())
+    }
+
+    async fn get_user_roles(&self, organization_id: i32, user_id: i32) -> StorageResult<Vec<Role>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Role> = data
+            .user_roles
+            .iter()
+            .filter(|(role_user_id, _)| *role_user_id == user_id)
+            .filter_map(|(_, role_id)| data.roles.get(role_id))
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn add_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if !data.user_roles.contains(&(user_id, role_id)) {
+            data.user_roles.push((user_id, role_id));
+        }
+        Ok(())
+    }
+
+    async fn remove_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.user_roles
+            .retain(|assignment| assignment != &(user_id, role_id));
+        Ok(())
+    }
+
+    async fn get_all_groups(&self, organization_id: i32) -> StorageResult<Vec<Group>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Group> = data
+            .groups
+            .values()
+            .map(|group| data.load_group(group))
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn get_group_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Group>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        Ok(data
+            .groups
+            .values()
+            .find(|group| group.uuid.as_ref() == Some(uuid))
+            .map(|group| data.load_group(group)))
+    }
+
+    async fn create_group(&self, organization_id: i32, mut group: Group) -> StorageResult<Group> {
+        group.created_on = Some(Utc::now());
+        group.updated_on = None;
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_group_name(&group.name, 0)?;
+        group.id = self.next_id();
+        group.uuid = Some(uuid::Uuid::new_v4());
+        data.groups.insert(group.id, group.clone());
+
+        Ok(group)
+    }
+
+    async fn update_group(&self, organization_id: i32, mut group: Group) -> StorageResult<Group> {
+        group.updated_on = Some(Utc::now());
+
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.check_group_name(&group.name, group.id)?;
+        if let Some(stored_group) = data.groups.get_mut(&group.id) {
+            stored_group.name = group.name.clone();
+            stored_group.description = group.description.clone();
+            stored_group.parent_id = group.parent_id;
+            stored_group.updated_on = group.updated_on;
+        }
+
+        Ok(group)
+    }
+
+    async fn delete_group(&self, organization_id: i32, group: Group) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.groups.remove(&group.id);
+        for subgroup in data.groups.values_mut() {
+            if subgroup.parent_id == Some(group.id) {
+                subgroup.parent_id = None;
+            }
+        }
+        data.group_members
+            .retain(|(group_id, _)| *group_id != group.id);
+        data.group_roles
+            .retain(|(group_id, _)| *group_id != group.id);
+        Ok(())
+    }
+
+    async fn get_group_members(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<AppUser>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<AppUser> = data
+            .group_members
+            .iter()
+            .filter(|(member_group_id, _)| *member_group_id == group_id)
+            .filter_map(|(_, user_id)| data.users.get(user_id))
+            .filter(|row| !row.user.is_deleted())
+            .filter_map(|row| data.load_user(row))
+            .map(|mut user| {
+                user.password = "".to_string();
+                user
+            })
+            .collect();
+        result.sort_by(|a, b| a.login.cmp(&b.login));
+        Ok(result)
+    }
+
+    async fn get_user_groups(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<Group>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Group> = data
+            .group_members
+            .iter()
+            .filter(|(_, member_user_id)| *member_user_id == user_id)
+            .filter_map(|(group_id, _)| data.groups.get(group_id))
+            .map(|group| data.load_group(group))
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn add_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if !data.group_members.contains(&(group_id, user_id)) {
+            data.group_members.push((group_id, user_id));
+        }
+        Ok(())
+    }
+
+    async fn remove_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.group_members
+            .retain(|membership| membership != &(group_id, user_id));
+        Ok(())
+    }
+
+    async fn get_group_roles(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<Role>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<Role> = data
+            .group_roles
+            .iter()
+            .filter(|(role_group_id, _)| *role_group_id == group_id)
+            .filter_map(|(_, role_id)| data.roles.get(role_id))
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    async fn add_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        if !data.group_roles.contains(&(group_id, role_id)) {
+            data.group_roles.push((group_id, role_id));
+        }
+        Ok(())
+    }
+
+    async fn remove_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        data.group_roles
+            .retain(|assignment| assignment != &(group_id, role_id));
+        Ok(())
+    }
+
+    async fn add_audit_entry(
+        &self,
+        organization_id: i32,
+        mut entry: AuditEntry,
+    ) -> StorageResult<()> {
+        let _writes = self.lock_writes().await;
+        let mut tenants = self.tenants.write().unwrap();
+        let data = get_tenant_mut(&mut tenants, organization_id)?;
+        entry.id = self.next_id();
+        data.audit_entries.push(entry);
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        organization_id: i32,
+        query: &AuditQuery,
+    ) -> StorageResult<Page<AuditEntry>> {
+        let tenants = self.tenants.read().unwrap();
+        let data = self.get_tenant(&tenants, organization_id);
+        let mut result: Vec<AuditEntry> = data
+            .audit_entries
+            .iter()
+            .filter(|entry| query.actor.is_none() || entry.actor == query.actor)
+            .filter(|entry| query.action.is_none_or(|action| entry.action == action))
+            .filter(|entry| query.target.is_none_or(|target| entry.target == target))
+            .filter(|entry| query.target_uuid.is_none() || entry.target_uuid == query.target_uuid)
+            .filter(|entry| query.request_id.is_none() || entry.request_id == query.request_id)
+            .filter(|entry| is_in_range(&Some(entry.occurred_on), &query.from, &query.to))
+            .cloned()
+            .collect();
+
+        result.sort_by(|a, b| a.occurred_on.cmp(&b.occurred_on).then(a.id.cmp(&b.id)));
+        Ok(get_page(
+            result,
+            SortDirection::Desc,
+            &query.get_pagination(),
+        ))
+    }
+
+    async fn begin(&self) -> StorageResult<Box<dyn StorageTransaction>> {
+        if self.in_transaction {
+            return Err(StorageError::TransactionAlreadyOpen);
+        }
+
+        //Taken before the snapshot, no write is left out of it.
+        let writes = self.writes.clone().lock_owned().await;
+        let snapshot = Snapshot {
+            organizations: self.organizations.read().unwrap().clone(),
+            tenants: self.tenants.read().unwrap().clone(),
+        };
+        Ok(Box::new(InMemoryUserTransaction {
+            storage: InMemoryUserStorage {
+                sequence: self.sequence.clone(),
+                organizations: self.organizations.clone(),
+                tenants: self.tenants.clone(),
+                writes: self.writes.clone(),
+                in_transaction: true,
+                empty: InMemoryData::default(),
+                read_hook: self.read_hook.clone(),
+            },
+            snapshot: Some(snapshot),
+            _writes: writes,
+        }))
+    }
+}
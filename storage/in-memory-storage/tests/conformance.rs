@@ -0,0 +1,127 @@
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::storage::conformance;
+use helix_user_domain::storage::traits::StorageTrait;
+use in_memory_storage::InMemoryUserStorage;
+
+#[tokio::test]
+async fn person_lifecycle() {
+    conformance::check_person_lifecycle(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn user_lifecycle() {
+    conformance::check_user_lifecycle(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn login_lookup() {
+    conformance::check_login_lookup(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn user_listing() {
+    conformance::check_user_listing(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn uniqueness() {
+    conformance::check_uniqueness(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn login_counters() {
+    conformance::check_login_counters(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn login_history() {
+    conformance::check_login_history(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn sessions() {
+    conformance::check_sessions(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn email_verification() {
+    conformance::check_email_verification(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn user_status() {
+    conformance::check_user_status(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn soft_delete() {
+    conformance::check_soft_delete(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn two_factor() {
+    conformance::check_two_factor(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn password_resets() {
+    conformance::check_password_resets(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn roles() {
+    conformance::check_roles(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn groups() {
+    conformance::check_groups(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn organizations() {
+    conformance::check_organizations(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn audit_log() {
+    conformance::check_audit_log(&InMemoryUserStorage::new()).await;
+}
+
+#[tokio::test]
+async fn transactions() {
+    conformance::check_transactions(&InMemoryUserStorage::new()).await;
+}
+
+//Writes made beside a transaction wait for it, its rollback leaves them in place.
+#[tokio::test]
+async fn rollback_keeps_other_writes() {
+    let storage = InMemoryUserStorage::new();
+    let transaction = storage.begin().await.unwrap();
+    transaction
+        .as_storage()
+        .create_organization(Organization::new(
+            0,
+            None,
+            "rolled back".to_string(),
+            None,
+            None,
+        ))
+        .await
+        .unwrap();
+
+    let (kept, rollback) = tokio::join!(
+        storage.create_organization(Organization::new(0, None, "kept".to_string(), None, None)),
+        transaction.rollback()
+    );
+    rollback.unwrap();
+    kept.unwrap();
+    let names: Vec<String> = storage
+        .get_all_organizations()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|organization| organization.name)
+        .collect();
+    assert_eq!(names, vec!["default".to_string(), "kept".to_string()]);
+}
@@ -0,0 +1,151 @@
+//Listing cost by page size, needs a migrated database configured with the HELIX_DB_* variables:
+//cargo bench -p pg-db-storage
+//The joined listing should stay close to flat, unlike one person query per user.
+//Everything runs in a transaction rolled back at the end, the database is left as found.
+use helix_user_domain::core::app_user::AppUser;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::person::Person;
+use helix_user_domain::core::query::UserQuery;
+use helix_user_domain::storage::traits::StorageTrait;
+use pg_db_storage::PgDbUserStorage;
+use std::env;
+use std::time::{Duration, Instant};
+
+const LOGIN_PREFIX: &str = "bench_listing_";
+const USER_COUNT: u32 = 500;
+const PAGE_SIZES: [u32; 4] = [10, 50, 100, 500];
+const ITERATIONS: u32 = 20;
+
+fn get_storage() -> PgDbUserStorage {
+    PgDbUserStorage::new(
+        env::var("HELIX_DB_NAME").expect("HELIX_DB_NAME not found."),
+        env::var("HELIX_DB_HOST").expect("HELIX_DB_HOST not found."),
+        env::var("HELIX_DB_PORT")
+            .expect("HELIX_DB_PORT not found.")
+            .parse()
+            .unwrap(),
+        env::var("HELIX_DB_USER").expect("HELIX_DB_USER not found."),
+        env::var("HELIX_DB_PASSWORD").expect("HELIX_DB_PASSWORD not found."),
+    )
+    .unwrap()
+}
+
+fn get_query(limit: u32) -> UserQuery {
+    UserQuery {
+        limit: Some(limit),
+        login: Some(LOGIN_PREFIX.to_string()),
+        ..UserQuery::default()
+    }
+}
+
+//Users of a dedicated organization, the other ones are not listed.
+async fn seed(storage: &dyn StorageTrait) -> i32 {
+    let organization = storage
+        .create_organization(Organization::new(
+            0,
+            None,
+            format!("{}organization", LOGIN_PREFIX),
+            None,
+            None,
+        ))
+        .await
+        .unwrap();
+    let organization_id = organization.id;
+    for index in 0..USER_COUNT {
+        let person = storage
+            .create_person(
+                organization_id,
+                Person::new(
+                    0,
+                    None,
+                    format!("Bench{}", index),
+                    "Listing".to_string(),
+                    format!("{}{}@helix.test", LOGIN_PREFIX, index),
+                    None,
+                    None,
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+
+        storage
+            .create_user(
+                organization_id,
+                AppUser::new(
+                    0,
+                    None,
+                    format!("{}{:04}", LOGIN_PREFIX, index),
+                    "$argon2id$bench".to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    person,
+                ),
+            )
+            .await
+            .unwrap();
+    }
+    organization_id
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!(
+        "{:>10.3} ms",
+        duration.as_secs_f64() * 1000.0 / ITERATIONS as f64
+    )
+}
+
+#[tokio::main(basic_scheduler)]
+async fn main() {
+    let storage = get_storage();
+    let status = storage.get_migration_status().await.unwrap();
+    if status.iter().any(|migration| migration.is_pending()) {
+        panic!("The database has pending migrations, apply them first.");
+    }
+
+    let transaction = storage.begin().await.unwrap();
+    let storage = transaction.as_storage();
+    let organization_id = seed(storage).await;
+
+    println!(
+        "{:>6} | {:>13} | {:>13}",
+        "limit", "joined", "query per user"
+    );
+    for limit in PAGE_SIZES.iter() {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            storage
+                .get_all_users(organization_id, &get_query(*limit))
+                .await
+                .unwrap();
+        }
+        let joined = start.elapsed();
+
+        //Former behaviour: one extra person query per listed user.
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let page = storage
+                .get_all_users(organization_id, &get_query(*limit))
+                .await
+                .unwrap();
+            for user in page.items {
+                storage
+                    .get_person_by_id(organization_id, user.person.id)
+                    .await
+                    .unwrap();
+            }
+        }
+        let per_user = start.elapsed();
+
+        println!(
+            "{:>6} | {} | {}",
+            limit,
+            format_duration(joined),
+            format_duration(per_user)
+        );
+    }
+
+    transaction.rollback().await.unwrap();
+}
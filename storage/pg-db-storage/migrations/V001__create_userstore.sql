@@ -0,0 +1,31 @@
+-- Baseline of the userstore schema.
+-- IF NOT EXISTS lets databases created before migrations adopt it as is.
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+CREATE SCHEMA IF NOT EXISTS userstore;
+
+CREATE TABLE IF NOT EXISTS userstore.person (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    firstname VARCHAR(255) NOT NULL,
+    lastname VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    phone VARCHAR(50),
+    created_on TIMESTAMPTZ,
+    updated_on TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS userstore.applicationuser (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    login VARCHAR(255) NOT NULL,
+    password TEXT NOT NULL,
+    photo BYTEA,
+    created_on TIMESTAMPTZ,
+    updated_on TIMESTAMPTZ,
+    lastlogin_on TIMESTAMPTZ,
+    person_ INTEGER REFERENCES userstore.person (id)
+);
+
+CREATE INDEX IF NOT EXISTS applicationuser_login_idx ON userstore.applicationuser (login);
+CREATE INDEX IF NOT EXISTS applicationuser_person_idx ON userstore.applicationuser (person_);
@@ -0,0 +1,6 @@
+-- Logins and emails are unique regardless of case.
+-- Existing duplicates must be resolved by hand before applying this migration.
+DROP INDEX IF EXISTS userstore.applicationuser_login_idx;
+
+CREATE UNIQUE INDEX applicationuser_login_unique_idx ON userstore.applicationuser (lower(login));
+CREATE UNIQUE INDEX person_email_unique_idx ON userstore.person (lower(email));
@@ -0,0 +1,9 @@
+-- Failed login attempts, per login and per client IP.
+CREATE TABLE userstore.login_counter (
+    scope VARCHAR(16) NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failure_on TIMESTAMPTZ NOT NULL,
+    locked_until TIMESTAMPTZ,
+    PRIMARY KEY (scope, key)
+);
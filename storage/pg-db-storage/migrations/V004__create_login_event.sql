@@ -0,0 +1,13 @@
+-- Login history, removed together with its user.
+CREATE TABLE userstore.login_event (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    login VARCHAR(255) NOT NULL,
+    occurred_on TIMESTAMPTZ NOT NULL,
+    ip VARCHAR(64),
+    user_agent TEXT,
+    success BOOLEAN NOT NULL,
+    channel VARCHAR(16) NOT NULL
+);
+
+CREATE INDEX login_event_user_idx ON userstore.login_event (user_, occurred_on DESC);
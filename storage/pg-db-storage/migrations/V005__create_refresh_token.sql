@@ -0,0 +1,15 @@
+-- Refresh tokens, stored as SHA-256 digests and removed together with their user.
+CREATE TABLE userstore.refresh_token (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    family UUID NOT NULL,
+    token_hash VARCHAR(64) NOT NULL,
+    created_on TIMESTAMPTZ NOT NULL,
+    expires_on TIMESTAMPTZ NOT NULL,
+    rotated_on TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX refresh_token_hash_idx ON userstore.refresh_token (token_hash);
+CREATE INDEX refresh_token_family_idx ON userstore.refresh_token (family);
+CREATE INDEX refresh_token_user_idx ON userstore.refresh_token (user_);
+CREATE INDEX refresh_token_expires_idx ON userstore.refresh_token (expires_on);
@@ -0,0 +1,23 @@
+-- Sessions group the refresh tokens issued from one login.
+CREATE TABLE userstore.session (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    device VARCHAR(100),
+    ip VARCHAR(64),
+    user_agent TEXT,
+    created_on TIMESTAMPTZ NOT NULL,
+    refreshed_on TIMESTAMPTZ,
+    expires_on TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX session_user_idx ON userstore.session (user_);
+CREATE INDEX session_expires_idx ON userstore.session (expires_on);
+
+-- Tokens issued before sessions existed cannot be attached, their owners log in again.
+DELETE FROM userstore.refresh_token;
+DROP INDEX userstore.refresh_token_user_idx;
+DROP INDEX userstore.refresh_token_expires_idx;
+ALTER TABLE userstore.refresh_token
+    ADD CONSTRAINT refresh_token_family_fkey
+    FOREIGN KEY (family) REFERENCES userstore.session (uuid) ON DELETE CASCADE;
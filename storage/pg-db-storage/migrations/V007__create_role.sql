@@ -0,0 +1,27 @@
+-- Roles grant named permissions to the users they are assigned to.
+CREATE TABLE userstore.role (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    name VARCHAR(50) NOT NULL,
+    description VARCHAR(255),
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    created_on TIMESTAMPTZ,
+    updated_on TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX role_name_unique_idx ON userstore.role (lower(name));
+
+CREATE TABLE userstore.user_role (
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    role_ INTEGER NOT NULL REFERENCES userstore.role (id) ON DELETE CASCADE,
+    PRIMARY KEY (user_, role_)
+);
+
+CREATE INDEX user_role_role_idx ON userstore.user_role (role_);
+
+-- Access tokens are resolved to their session, only their digest is kept.
+ALTER TABLE userstore.session
+    ADD COLUMN access_token_hash VARCHAR(64),
+    ADD COLUMN access_expires_on TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX session_access_token_idx ON userstore.session (access_token_hash);
@@ -0,0 +1,30 @@
+-- Groups nest through their parent, members of a group belong to its parents too.
+-- "group" is a reserved word, hence usergroup.
+CREATE TABLE userstore.usergroup (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    description VARCHAR(255),
+    parent_ INTEGER REFERENCES userstore.usergroup (id) ON DELETE SET NULL,
+    created_on TIMESTAMPTZ,
+    updated_on TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX usergroup_name_unique_idx ON userstore.usergroup (lower(name));
+CREATE INDEX usergroup_parent_idx ON userstore.usergroup (parent_);
+
+CREATE TABLE userstore.group_member (
+    group_ INTEGER NOT NULL REFERENCES userstore.usergroup (id) ON DELETE CASCADE,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    PRIMARY KEY (group_, user_)
+);
+
+CREATE INDEX group_member_user_idx ON userstore.group_member (user_);
+
+CREATE TABLE userstore.group_role (
+    group_ INTEGER NOT NULL REFERENCES userstore.usergroup (id) ON DELETE CASCADE,
+    role_ INTEGER NOT NULL REFERENCES userstore.role (id) ON DELETE CASCADE,
+    PRIMARY KEY (group_, role_)
+);
+
+CREATE INDEX group_role_role_idx ON userstore.group_role (role_);
@@ -0,0 +1,65 @@
+-- Organizations scope persons, users and everything attached to them.
+-- Existing rows move to the default organization, identified by the nil uuid.
+CREATE TABLE userstore.organization (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    created_on TIMESTAMPTZ,
+    updated_on TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX organization_name_unique_idx ON userstore.organization (lower(name));
+
+INSERT INTO userstore.organization (uuid, name, created_on)
+VALUES ('00000000-0000-0000-0000-000000000000', 'default', now());
+
+ALTER TABLE userstore.person
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.applicationuser
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.login_counter
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.login_event
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.session
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.refresh_token
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.role
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+ALTER TABLE userstore.usergroup
+    ADD COLUMN organization_ INTEGER REFERENCES userstore.organization (id);
+
+UPDATE userstore.person SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.applicationuser SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.login_counter SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.login_event SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.session SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.refresh_token SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.role SET organization_ = (SELECT id FROM userstore.organization);
+UPDATE userstore.usergroup SET organization_ = (SELECT id FROM userstore.organization);
+
+ALTER TABLE userstore.person ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.applicationuser ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.login_counter ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.login_event ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.session ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.refresh_token ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.role ALTER COLUMN organization_ SET NOT NULL;
+ALTER TABLE userstore.usergroup ALTER COLUMN organization_ SET NOT NULL;
+
+-- Counters are kept per organization.
+ALTER TABLE userstore.login_counter DROP CONSTRAINT login_counter_pkey;
+ALTER TABLE userstore.login_counter ADD PRIMARY KEY (organization_, scope, key);
+
+-- Logins, emails, role and group names are unique within their organization.
+DROP INDEX userstore.applicationuser_login_unique_idx;
+DROP INDEX userstore.person_email_unique_idx;
+DROP INDEX userstore.role_name_unique_idx;
+DROP INDEX userstore.usergroup_name_unique_idx;
+
+CREATE UNIQUE INDEX applicationuser_login_unique_idx
+    ON userstore.applicationuser (organization_, lower(login));
+CREATE UNIQUE INDEX person_email_unique_idx ON userstore.person (organization_, lower(email));
+CREATE UNIQUE INDEX role_name_unique_idx ON userstore.role (organization_, lower(name));
+CREATE UNIQUE INDEX usergroup_name_unique_idx ON userstore.usergroup (organization_, lower(name));
@@ -0,0 +1,34 @@
+-- TOTP second factor, at most one per user, pending until confirmed.
+CREATE TABLE userstore.two_factor (
+    user_ INTEGER PRIMARY KEY REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    secret VARCHAR(64) NOT NULL,
+    created_on TIMESTAMPTZ NOT NULL,
+    confirmed_on TIMESTAMPTZ,
+    last_used_step BIGINT
+);
+
+-- Single use recovery codes, stored as SHA-256 digests.
+CREATE TABLE userstore.recovery_code (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    code_hash VARCHAR(64) NOT NULL,
+    used_on TIMESTAMPTZ
+);
+
+CREATE INDEX recovery_code_user_idx ON userstore.recovery_code (user_);
+
+-- Logins waiting for their second factor, stored as SHA-256 digests.
+CREATE TABLE userstore.login_challenge (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    token_hash VARCHAR(64) NOT NULL,
+    device VARCHAR(100),
+    created_on TIMESTAMPTZ NOT NULL,
+    expires_on TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX login_challenge_hash_idx ON userstore.login_challenge (token_hash);
+CREATE INDEX login_challenge_expires_idx ON userstore.login_challenge (expires_on);
@@ -0,0 +1,14 @@
+-- Password reset tokens, single use, stored as SHA-256 digests.
+CREATE TABLE userstore.password_reset (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    token_hash VARCHAR(64) NOT NULL,
+    created_on TIMESTAMPTZ NOT NULL,
+    expires_on TIMESTAMPTZ NOT NULL,
+    used_on TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX password_reset_hash_idx ON userstore.password_reset (token_hash);
+CREATE INDEX password_reset_user_idx ON userstore.password_reset (user_);
+CREATE INDEX password_reset_expires_idx ON userstore.password_reset (expires_on);
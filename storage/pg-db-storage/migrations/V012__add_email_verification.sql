@@ -0,0 +1,19 @@
+-- Existing addresses were never checked.
+ALTER TABLE userstore.person
+    ADD COLUMN email_status VARCHAR(16) NOT NULL DEFAULT 'unverified',
+    ADD COLUMN email_verified_on TIMESTAMPTZ;
+
+-- Email verification tokens, stored as SHA-256 digests with the address they verify.
+CREATE TABLE userstore.email_verification (
+    id SERIAL PRIMARY KEY,
+    person_ INTEGER NOT NULL REFERENCES userstore.person (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    email VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL,
+    created_on TIMESTAMPTZ NOT NULL,
+    expires_on TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX email_verification_hash_idx ON userstore.email_verification (token_hash);
+CREATE INDEX email_verification_person_idx ON userstore.email_verification (person_);
+CREATE INDEX email_verification_expires_idx ON userstore.email_verification (expires_on);
@@ -0,0 +1,21 @@
+-- Existing users stay active.
+ALTER TABLE userstore.applicationuser
+    ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'active',
+    ADD COLUMN status_reason TEXT,
+    ADD COLUMN suspended_until TIMESTAMPTZ;
+
+-- Who changed the status of a user, changed_by is the uuid of the acting user
+-- and is kept when that user is deleted.
+CREATE TABLE userstore.user_status_change (
+    id SERIAL PRIMARY KEY,
+    user_ INTEGER NOT NULL REFERENCES userstore.applicationuser (id) ON DELETE CASCADE,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    previous_status VARCHAR(16) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    reason TEXT,
+    suspended_until TIMESTAMPTZ,
+    changed_by UUID,
+    changed_on TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX user_status_change_user_idx ON userstore.user_status_change (user_, changed_on);
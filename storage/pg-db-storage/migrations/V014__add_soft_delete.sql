@@ -0,0 +1,14 @@
+-- Deleted users and persons are kept until purged, deleted_by is the uuid of the acting user.
+ALTER TABLE userstore.applicationuser
+    ADD COLUMN deleted_on TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID;
+
+ALTER TABLE userstore.person
+    ADD COLUMN deleted_on TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID;
+
+-- Only deleted rows are indexed, for the purge.
+CREATE INDEX applicationuser_deleted_idx ON userstore.applicationuser (deleted_on)
+    WHERE deleted_on IS NOT NULL;
+CREATE INDEX person_deleted_idx ON userstore.person (deleted_on)
+    WHERE deleted_on IS NOT NULL;
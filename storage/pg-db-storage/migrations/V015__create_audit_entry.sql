@@ -0,0 +1,15 @@
+-- Append only, actor and target are uuids so entries outlive the rows they describe.
+CREATE TABLE userstore.audit_entry (
+    id SERIAL PRIMARY KEY,
+    organization_ INTEGER NOT NULL REFERENCES userstore.organization (id),
+    actor UUID,
+    action VARCHAR(32) NOT NULL,
+    target VARCHAR(16) NOT NULL,
+    target_uuid UUID,
+    changes JSONB NOT NULL,
+    request_id VARCHAR(128),
+    occurred_on TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX audit_entry_occurred_idx ON userstore.audit_entry (organization_, occurred_on);
+CREATE INDEX audit_entry_target_idx ON userstore.audit_entry (target_uuid);
@@ -0,0 +1,91 @@
+use chrono::prelude::*;
+use helix_user_domain::core::query::SortDirection;
+use tokio_postgres::types::ToSql;
+
+//Accumulate where clauses and their positional parameters.
+pub struct SqlFilter {
+    clauses: Vec<String>,
+    params: Vec<Box<dyn ToSql + Sync + Send>>,
+}
+
+impl SqlFilter {
+    pub fn new() -> SqlFilter {
+        SqlFilter {
+            clauses: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    //Register a parameter and return its placeholder.
+    pub fn add_param<T: ToSql + Sync + Send + 'static>(&mut self, value: T) -> String {
+        self.params.push(Box::new(value));
+        format!("${}", self.params.len())
+    }
+
+    pub fn add_clause(&mut self, clause: String) {
+        self.clauses.push(clause);
+    }
+
+    pub fn add_equal<T: ToSql + Sync + Send + 'static>(&mut self, column: &str, value: T) {
+        let placeholder = self.add_param(value);
+        self.add_clause(format!("{} = {}", column, placeholder));
+    }
+
+    //Case insensitive prefix match on any of the columns.
+    pub fn add_prefix(&mut self, columns: &[&str], value: &Option<String>) {
+        if let Some(value) = value {
+            let placeholder = self.add_param(format!("{}%", escape_like(value)));
+            let conditions: Vec<String> = columns
+                .iter()
+                .map(|column| format!("{} ILIKE {}", column, placeholder))
+                .collect();
+            self.add_clause(format!("({})", conditions.join(" OR ")));
+        }
+    }
+
+    pub fn add_range(
+        &mut self,
+        column: &str,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+    ) {
+        if let Some(from) = from {
+            let placeholder = self.add_param(*from);
+            self.add_clause(format!("{} >= {}", column, placeholder));
+        }
+        if let Some(to) = to {
+            let placeholder = self.add_param(*to);
+            self.add_clause(format!("{} <= {}", column, placeholder));
+        }
+    }
+
+    pub fn get_where_clause(&self) -> String {
+        let mut where_clause = "where 1=1".to_string();
+        for clause in &self.clauses {
+            where_clause.push_str(" and ");
+            where_clause.push_str(clause);
+        }
+        where_clause
+    }
+
+    pub fn get_params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.params
+            .iter()
+            .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+}
+
+pub fn get_direction(direction: SortDirection) -> &'static str {
+    match direction {
+        SortDirection::Asc => "asc",
+        SortDirection::Desc => "desc",
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -1,16 +1,65 @@
 use async_trait::async_trait;
 use chrono::prelude::*;
-use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod};
-use helix_user_domain::core::app_user::AppUser;
-use helix_user_domain::core::person::Person;
+use deadpool_postgres::{Client, Config, ManagerConfig, Pool, RecyclingMethod};
+use filter::*;
+use helix_user_domain::core::app_user::*;
+use helix_user_domain::core::audit::*;
+use helix_user_domain::core::group::*;
+use helix_user_domain::core::login::*;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::password_reset::*;
+use helix_user_domain::core::person::*;
+use helix_user_domain::core::query::*;
+use helix_user_domain::core::role::*;
+use helix_user_domain::core::session::*;
+use helix_user_domain::core::token::*;
+use helix_user_domain::core::two_factor::*;
 use helix_user_domain::storage::error::*;
-use helix_user_domain::storage::traits::StorageTrait;
+use helix_user_domain::storage::traits::{StorageTrait, StorageTransaction};
+use row::{
+    AUDIT_ENTRY_COLUMNS, GROUP_COLUMNS, GROUP_FROM, PERSON_COLUMNS, ROLE_COLUMNS, SESSION_COLUMNS,
+    STATUS_CHANGE_COLUMNS, USER_COLUMNS, USER_FROM,
+};
+use std::ops::Deref;
+use tokio_postgres::error::{DbError, SqlState};
 use tokio_postgres::tls::NoTls;
+use tokio_postgres::types::Json;
 
 use uuid;
 
+mod filter;
+pub mod migration;
+mod row;
+
+//Arbitrary key letting a single service purge at a time.
+const PURGE_LOCK_KEY: i64 = 0x4845_4c49_5850_5247;
+
 pub struct PgDbUserStorage {
     pub pool: Pool,
+    //Connection of the transaction the storage belongs to, calls run on the pool outside of one.
+    transaction: Option<Client>,
+}
+
+//Transaction opened by begin, it owns the connection until it ends.
+pub struct PgDbUserTransaction {
+    storage: PgDbUserStorage,
+}
+
+//Connection a call runs on.
+enum PgClient<'a> {
+    Pooled(Box<Client>),
+    Transaction(&'a Client),
+}
+
+impl<'a> Deref for PgClient<'a> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            PgClient::Pooled(client) => client,
+            PgClient::Transaction(client) => client,
+        }
+    }
 }
 
 impl PgDbUserStorage {
@@ -33,132 +82,431 @@ impl PgDbUserStorage {
 
         Ok(PgDbUserStorage {
             pool: cfg.create_pool(NoTls).unwrap(),
+            transaction: None,
         })
     }
-}
 
-#[async_trait]
-impl StorageTrait for PgDbUserStorage {
-    async fn login(&self, key: String) -> StorageResult<Option<AppUser>> {
+    async fn get_pooled_client(&self) -> StorageResult<Client> {
+        self.pool
+            .get()
+            .await
+            .map_err(|_| StorageError::BackendUnavailable)
+    }
+
+    async fn get_client(&self) -> StorageResult<PgClient<'_>> {
+        match &self.transaction {
+            Some(client) => Ok(PgClient::Transaction(client)),
+            None => Ok(PgClient::Pooled(Box::new(self.get_pooled_client().await?))),
+        }
+    }
+
+    async fn find_user(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+        with_password: bool,
+    ) -> StorageResult<Option<AppUser>> {
         let mut result: Option<AppUser> = None;
-        let query = "select * from userstore.applicationuser where password=$1;";
+        let query = format!(
+            "
+        select {}, {}
+        {}
+        where 1=1
+        and u.organization_=$1
+        and u.uuid=$2
+        and ($3 or u.deleted_on is null);",
+            USER_COLUMNS, PERSON_COLUMNS, USER_FROM
+        );
 
-        let client = &self.pool.get().await.unwrap();
-        client.query(query, &[&key]).await?;
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &uuid, &include_deleted])
+            .await?
+        {
+            result = Some(row::get_user(&row, with_password));
+        }
 
-        for row in client.query(query, &[&key]).await? {
-            match self.get_person_by_id(row.get("person_")).await? {
-                None => {
-                    result = None;
-                }
-                Some(person) => {
-                    result = Some(AppUser::new(
-                        row.get("id"),
-                        row.get("uuid"),
-                        row.get("login"),
-                        //Do not restitute password
-                        "".to_string(),
-                        row.get("photo"),
-                        row.get("created_on"),
-                        row.get("updated_on"),
-                        row.get("lastlogin_on"),
-                        person,
-                    ));
+        Ok(result)
+    }
+}
+
+impl PgDbUserTransaction {
+    //The connection goes back to the pool once the statement succeeds,
+    //it is closed otherwise as its state is unknown.
+    async fn end(&mut self, statement: &str) -> StorageResult<()> {
+        let client = match self.storage.transaction.take() {
+            Some(client) => client,
+            None => return Err(StorageError::NoTransaction),
+        };
+        if let Err(error) = client.batch_execute(statement).await {
+            drop(Client::take(client));
+            return Err(error.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PgDbUserTransaction {
+    //Rolled back in the background, the connection then goes back to the pool.
+    //Without a runtime to do it, the connection is closed and the server rolls back.
+    fn drop(&mut self) {
+        if let Some(client) = self.storage.transaction.take() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn(async move {
+                        if client.batch_execute("ROLLBACK;").await.is_err() {
+                            drop(Client::take(client));
+                        }
+                    });
                 }
+                Err(_) => drop(Client::take(client)),
             }
         }
+    }
+}
+
+#[async_trait]
+impl StorageTransaction for PgDbUserTransaction {
+    fn as_storage(&self) -> &dyn StorageTrait {
+        &self.storage
+    }
+
+    async fn commit(mut self: Box<Self>) -> StorageResult<()> {
+        self.end("COMMIT;").await
+    }
+
+    async fn rollback(mut self: Box<Self>) -> StorageResult<()> {
+        self.end("ROLLBACK;").await
+    }
+}
+
+//Translate unique index violations into the field that collided.
+fn get_write_error(error: tokio_postgres::Error) -> StorageError {
+    let field = std::error::Error::source(&error)
+        .and_then(|source| source.downcast_ref::<DbError>())
+        .filter(|db_error| db_error.code() == &SqlState::UNIQUE_VIOLATION)
+        .and_then(|db_error| match db_error.constraint() {
+            Some("organization_name_unique_idx") => Some("name"),
+            Some("applicationuser_login_unique_idx") => Some("login"),
+            Some("person_email_unique_idx") => Some("email"),
+            Some("role_name_unique_idx") => Some("name"),
+            Some("usergroup_name_unique_idx") => Some("name"),
+            _ => None,
+        });
+
+    match field {
+        Some(field) => StorageError::Conflict {
+            field: field.to_string(),
+        },
+        None => error.into(),
+    }
+}
+
+#[async_trait]
+impl StorageTrait for PgDbUserStorage {
+    async fn get_all_organizations(&self) -> StorageResult<Vec<Organization>> {
+        let mut result: Vec<Organization> = Vec::new();
+        let query = "
+        select id, uuid, name, created_on, updated_on
+        from userstore.organization
+        order by name;";
+
+        let client = &self.get_client().await?;
+        for row in client.query(query, &[]).await? {
+            result.push(row::get_organization(&row));
+        }
 
-        //return
         Ok(result)
     }
 
-    async fn get_user(&self, uuid: &uuid::Uuid) -> StorageResult<Option<AppUser>> {
-        let mut result: Option<AppUser> = None;
+    async fn get_organization_by_uuid(
+        &self,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Organization>> {
+        let mut result: Option<Organization> = None;
+        let query = "
+        select id, uuid, name, created_on, updated_on
+        from userstore.organization
+        where uuid = $1;";
+
+        let client = &self.get_client().await?;
+        for row in client.query(query, &[uuid]).await? {
+            result = Some(row::get_organization(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_organization_by_id(&self, id: i32) -> StorageResult<Option<Organization>> {
+        let mut result: Option<Organization> = None;
+        let query = "
+        select id, uuid, name, created_on, updated_on
+        from userstore.organization
+        where id = $1;";
+
+        let client = &self.get_client().await?;
+        for row in client.query(query, &[&id]).await? {
+            result = Some(row::get_organization(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn create_organization(
+        &self,
+        mut organization: Organization,
+    ) -> StorageResult<Organization> {
+        organization.created_on = Some(Utc::now());
+        let query = "
+        INSERT INTO userstore.ORGANIZATION (name, created_on)
+        VALUES ($1,$2)
+        RETURNING id, uuid;";
 
+        let client = &self.get_client().await?;
+        let row_inserted = client
+            .query_one(query, &[&organization.name, &organization.created_on])
+            .await
+            .map_err(get_write_error)?;
+
+        organization.id = row_inserted.get("id");
+        organization.uuid = row_inserted.get("uuid");
+        Ok(organization)
+    }
+
+    async fn update_organization(
+        &self,
+        mut organization: Organization,
+    ) -> StorageResult<Organization> {
+        organization.updated_on = Some(Utc::now());
         let query = "
-        select *
-        from userstore.applicationuser
+        UPDATE userstore.ORGANIZATION SET name = $2, updated_on = $3
+        WHERE id = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization.id,
+                    &organization.name,
+                    &organization.updated_on,
+                ],
+            )
+            .await
+            .map_err(get_write_error)?;
+        Ok(organization)
+    }
+
+    async fn get_user_by_login(
+        &self,
+        organization_id: i32,
+        login: &str,
+    ) -> StorageResult<Option<AppUser>> {
+        let mut result: Option<AppUser> = None;
+        let query = format!(
+            "
+        select {}, u.password, {}
+        {}
         where 1=1
-        and uuid=$1;";
+        and u.organization_=$1
+        and u.deleted_on is null
+        and lower(u.login)=lower($2);",
+            USER_COLUMNS, PERSON_COLUMNS, USER_FROM
+        );
 
-        let client = &self.pool.get().await.unwrap();
-        for row in client.query(query, &[&uuid]).await? {
-            match self.get_person_by_id(row.get("person_")).await? {
-                None => {
-                    result = None;
-                }
-                Some(person) => {
-                    result = Some(AppUser::new(
-                        row.get("id"),
-                        row.get("uuid"),
-                        row.get("login"),
-                        //Do not restitute password
-                        "".to_string(),
-                        row.get("photo"),
-                        row.get("created_on"),
-                        row.get("updated_on"),
-                        row.get("lastlogin_on"),
-                        person,
-                    ));
-                }
-            }
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &login])
+            .await?
+        {
+            //Needed by the domain to verify the password
+            result = Some(row::get_user(&row, true));
         }
 
+        //return
         Ok(result)
     }
-    async fn get_all_users(&self) -> StorageResult<Vec<AppUser>> {
-        let mut result: Vec<AppUser> = Vec::new();
 
+    async fn update_user_password(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        password: String,
+    ) -> StorageResult<()> {
         let query = "
-        select *
-        from userstore.applicationuser
-        where 1=1 and person_ is not null
-        order by login;";
+        UPDATE userstore.APPLICATIONUSER SET password = $3
+        WHERE ID = $2 AND organization_ = $1;";
 
-        let client = &self.pool.get().await.unwrap();
-        for row in client.query(query, &[]).await? {
-            match self.get_person_by_id(row.get("person_")).await? {
-                None => {}
-                Some(person) => {
-                    result.push(AppUser::new(
-                        row.get("id"),
-                        row.get("uuid"),
-                        row.get("login"),
-                        row.get("password"),
-                        row.get("photo"),
-                        row.get("created_on"),
-                        row.get("updated_on"),
-                        row.get("lastlogin_on"),
-                        person,
-                    ));
-                }
-            }
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &user_id, &password])
+            .await?;
+        Ok(())
+    }
+
+    async fn update_last_login(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let query = "
+        UPDATE userstore.APPLICATIONUSER SET lastlogin_on = $3
+        WHERE ID = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &user_id, &on])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<AppUser>> {
+        //Do not restitute password
+        self.find_user(organization_id, uuid, include_deleted, false)
+            .await
+    }
+
+    async fn get_user_with_password(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<AppUser>> {
+        self.find_user(organization_id, uuid, false, true).await
+    }
+
+    async fn get_user_by_id(
+        &self,
+        organization_id: i32,
+        id: i32,
+    ) -> StorageResult<Option<AppUser>> {
+        let mut result: Option<AppUser> = None;
+        let query = format!(
+            "
+        select {}, {}
+        {}
+        where 1=1
+        and u.organization_=$1
+        and u.deleted_on is null
+        and u.id=$2;",
+            USER_COLUMNS, PERSON_COLUMNS, USER_FROM
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &id])
+            .await?
+        {
+            //Do not restitute password
+            result = Some(row::get_user(&row, false));
         }
 
         Ok(result)
     }
 
-    async fn create_user(&self, mut user: AppUser) -> StorageResult<AppUser> {
+    async fn get_all_users(
+        &self,
+        organization_id: i32,
+        query: &UserQuery,
+    ) -> StorageResult<Page<AppUser>> {
+        let mut result: Vec<AppUser> = Vec::new();
+        let pagination = query.get_pagination();
+        let offset = pagination.get_offset().unwrap_or(0);
+        let limit = pagination.get_limit();
+
+        let mut filter = SqlFilter::new();
+        filter.add_equal("u.organization_", organization_id);
+        filter.add_prefix(&["u.login"], &query.login);
+        filter.add_prefix(&["pe.email"], &query.email);
+        filter.add_prefix(&["pe.firstname", "pe.lastname"], &query.name);
+        filter.add_range("u.created_on", &query.created_from, &query.created_to);
+        if !query.include_deleted {
+            filter.add_clause("u.deleted_on is null".to_string());
+        }
+
+        let sort_column = match query.sort {
+            UserSortField::Login => "u.login",
+            UserSortField::Email => "pe.email",
+            UserSortField::Firstname => "pe.firstname",
+            UserSortField::Lastname => "pe.lastname",
+            UserSortField::CreatedOn => "u.created_on",
+        };
+        let direction = get_direction(query.direction);
+
+        let count_query = format!(
+            "
+        select count(*) as total
+        {}
+        {};",
+            USER_FROM,
+            filter.get_where_clause()
+        );
+
+        let client = &self.get_client().await?;
+        let total: i64 = client
+            .query_one(count_query.as_str(), &filter.get_params())
+            .await?
+            .get("total");
+
+        let limit_placeholder = filter.add_param(limit as i64);
+        let offset_placeholder = filter.add_param(offset as i64);
+        let query = format!(
+            "
+        select {}, {}
+        {}
+        {}
+        order by {} {}, u.id {}
+        limit {} offset {};",
+            USER_COLUMNS,
+            PERSON_COLUMNS,
+            USER_FROM,
+            filter.get_where_clause(),
+            sort_column,
+            direction,
+            direction,
+            limit_placeholder,
+            offset_placeholder
+        );
+
+        for row in client.query(query.as_str(), &filter.get_params()).await? {
+            result.push(row::get_user(&row, false));
+        }
+
+        Ok(Page::new(result, total, offset, limit))
+    }
+
+    async fn create_user(&self, organization_id: i32, mut user: AppUser) -> StorageResult<AppUser> {
         user.created_on = Some(Utc::now());
 
         let query = "
-        INSERT INTO userstore.APPLICATIONUSER
-        VALUES (DEFAULT,DEFAULT,$1,$2,$3,$4,NULL,NULL,$5) 
+        INSERT INTO userstore.APPLICATIONUSER (organization_, login, password, photo, created_on, person_, status, status_reason, suspended_until)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9) 
         RETURNING id, uuid;";
 
-        let client = &self.pool.get().await.unwrap();
+        let client = &self.get_client().await?;
         let row_inserted = client
             .query(
                 query,
                 &[
+                    &organization_id,
                     &user.login,
                     &user.password,
                     &user.photo,
                     &user.created_on,
                     &user.person.id,
+                    &user.status.as_str(),
+                    &user.status_reason,
+                    &user.suspended_until,
                 ],
             )
-            .await?;
+            .await
+            .map_err(get_write_error)?;
 
         let row_data = row_inserted.iter().next().unwrap();
         user.id = row_data.get("id");
@@ -166,19 +514,21 @@ impl StorageTrait for PgDbUserStorage {
 
         Ok(user)
     }
-    async fn update_user(&self, mut user: AppUser) -> StorageResult<AppUser> {
+    async fn update_user(&self, organization_id: i32, mut user: AppUser) -> StorageResult<AppUser> {
         user.updated_on = Some(Utc::now());
 
-        let query = "UPDATE userstore.APPLICATIONUSER SET (uuid, login, password, photo, created_on, updated_on, lastlogin_on, person_) 
-        = ($2,$3,$4,$5,$6,$7,$8,$9)
-        WHERE ID = $1;";
+        //An empty password keeps the stored hash.
+        let query = "UPDATE userstore.APPLICATIONUSER SET (uuid, login, password, photo, created_on, updated_on, lastlogin_on, person_, status, status_reason, suspended_until) 
+        = ($3,$4,COALESCE(NULLIF($5,''),password),$6,$7,$8,$9,$10,$11,$12,$13)
+        WHERE ID = $2 AND organization_ = $1;";
 
-        let client = &self.pool.get().await.unwrap();
+        let client = &self.get_client().await?;
 
         client
             .execute(
                 query,
                 &[
+                    &organization_id,
                     &user.id,
                     &user.uuid,
                     &user.login,
@@ -188,41 +538,146 @@ impl StorageTrait for PgDbUserStorage {
                     &user.updated_on,
                     &user.last_login_on,
                     &user.person.id,
+                    &user.status.as_str(),
+                    &user.status_reason,
+                    &user.suspended_until,
                 ],
             )
-            .await?;
+            .await
+            .map_err(get_write_error)?;
 
         Ok(user)
     }
-    async fn delete_user(&self, user: AppUser) -> StorageResult<()> {
+    async fn delete_user(&self, organization_id: i32, user: AppUser) -> StorageResult<()> {
         let query = "
-        DELETE FROM userstore.APPLICATIONUSER WHERE ID = $1;";
+        DELETE FROM userstore.APPLICATIONUSER WHERE ID = $2 AND organization_ = $1;";
 
-        let client = &self.pool.get().await.unwrap();
-        client.execute(query, &[&user.id]).await?;
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &user.id]).await?;
         Ok(())
     }
 
-    async fn create_person(&self, mut person: Person) -> StorageResult<Person> {
+    async fn update_user_deletion(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()> {
+        let query = "
+        UPDATE userstore.APPLICATIONUSER SET deleted_on = $3, deleted_by = $4
+        WHERE ID = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[&organization_id, &user_id, &deleted_on, &deleted_by],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_deleted_users(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64> {
+        let query = "
+        DELETE FROM userstore.APPLICATIONUSER WHERE organization_ = $1 AND deleted_on <= $2;";
+
+        let client = &self.get_client().await?;
+        Ok(client.execute(query, &[&organization_id, &before]).await?)
+    }
+
+    async fn change_user_status(
+        &self,
+        organization_id: i32,
+        change: StatusChange,
+    ) -> StorageResult<()> {
+        //A single statement, the status is never saved without its change.
+        let query = "
+        WITH updated AS (
+            UPDATE userstore.APPLICATIONUSER SET status = $3, status_reason = $4, suspended_until = $5
+            WHERE ID = $2 AND organization_ = $1
+            RETURNING id
+        )
+        INSERT INTO userstore.USER_STATUS_CHANGE (organization_, user_, previous_status, status, reason, suspended_until, changed_by, changed_on)
+        SELECT $1, id, $6, $3, $4, $5, $7, $8 FROM updated;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &change.user_id,
+                    &change.status.as_str(),
+                    &change.reason,
+                    &change.suspended_until,
+                    &change.previous_status.as_str(),
+                    &change.changed_by,
+                    &change.changed_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_status_changes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<StatusChange>> {
+        let mut result: Vec<StatusChange> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        from userstore.user_status_change
+        where organization_ = $1 and user_ = $2
+        order by changed_on desc, id desc;",
+            STATUS_CHANGE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &user_id])
+            .await?
+        {
+            result.push(row::get_status_change(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn create_person(
+        &self,
+        organization_id: i32,
+        mut person: Person,
+    ) -> StorageResult<Person> {
         person.created_on = Some(Utc::now());
         let query = "
-        INSERT INTO userstore.PERSON
-        VALUES (DEFAULT,DEFAULT,$1,$2,$3,$4,$5,NULL) 
+        INSERT INTO userstore.PERSON (organization_, firstname, lastname, email, phone, created_on, email_status, email_verified_on)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8) 
         RETURNING id, uuid;";
 
-        let client = &self.pool.get().await.unwrap();
+        let client = &self.get_client().await?;
         let row_inserted = client
             .query(
                 query,
                 &[
+                    &organization_id,
                     &person.firstname,
                     &person.lastname,
                     &person.email,
                     &person.phone,
                     &person.created_on,
+                    &person.email_status.as_str(),
+                    &person.email_verified_on,
                 ],
             )
-            .await?;
+            .await
+            .map_err(get_write_error)?;
 
         let row_data = row_inserted.iter().next().unwrap();
         person.id = row_data.get("id");
@@ -231,116 +686,1597 @@ impl StorageTrait for PgDbUserStorage {
         Ok(person)
     }
 
-    async fn update_person(&self, mut person: Person) -> StorageResult<Person> {
+    async fn update_person(
+        &self,
+        organization_id: i32,
+        mut person: Person,
+    ) -> StorageResult<Person> {
         person.updated_on = Some(Utc::now());
         let query = "
-        UPDATE userstore.PERSON SET (firstname, lastname, email, phone, updated_on) 
-        = ($2,$3,$4,$5,$6)
-        WHERE ID = $1;";
+        UPDATE userstore.PERSON SET (firstname, lastname, email, phone, updated_on, email_status, email_verified_on) 
+        = ($3,$4,$5,$6,$7,$8,$9)
+        WHERE ID = $2 AND organization_ = $1;";
 
-        let client = &self.pool.get().await.unwrap();
+        let client = &self.get_client().await?;
         client
             .execute(
                 query,
                 &[
+                    &organization_id,
                     &person.id,
                     &person.firstname,
                     &person.lastname,
                     &person.email,
                     &person.phone,
                     &person.updated_on,
+                    &person.email_status.as_str(),
+                    &person.email_verified_on,
                 ],
             )
-            .await?;
+            .await
+            .map_err(get_write_error)?;
 
         Ok(person)
     }
 
-    async fn delete_person(&self, person: Person) -> StorageResult<()> {
-        let query = "DELETE FROM userstore.PERSON WHERE ID = $1;";
+    async fn delete_person(&self, organization_id: i32, person: Person) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.PERSON WHERE ID = $2 AND organization_ = $1;";
 
-        let client = &self.pool.get().await.unwrap();
-        client.execute(query, &[&person.id]).await?;
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &person.id])
+            .await?;
         Ok(())
     }
 
-    async fn get_person_by_uuid(&self, uuid: &uuid::Uuid) -> StorageResult<Option<Person>> {
-        let mut result: Option<Person> = None;
+    async fn update_person_deletion(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        deleted_on: Option<DateTime<Utc>>,
+        deleted_by: Option<uuid::Uuid>,
+    ) -> StorageResult<()> {
+        let query = "
+        UPDATE userstore.PERSON SET deleted_on = $3, deleted_by = $4
+        WHERE ID = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[&organization_id, &person_id, &deleted_on, &deleted_by],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_deleted_persons(
+        &self,
+        organization_id: i32,
+        before: DateTime<Utc>,
+    ) -> StorageResult<u64> {
         let query = "
-        select *
+        DELETE FROM userstore.PERSON as pe
+        WHERE pe.organization_ = $1 AND pe.deleted_on <= $2
+        AND NOT EXISTS (SELECT 1 FROM userstore.APPLICATIONUSER as u WHERE u.person_ = pe.id);";
+
+        let client = &self.get_client().await?;
+        Ok(client.execute(query, &[&organization_id, &before]).await?)
+    }
+
+    async fn try_lock_purge(&self) -> StorageResult<bool> {
+        let query = "select pg_try_advisory_xact_lock($1);";
+
+        //Outside of a transaction the lock would be released right away.
+        let client = match &self.transaction {
+            Some(client) => client,
+            None => return Err(StorageError::NoTransaction),
+        };
+        let row = client.query_one(query, &[&PURGE_LOCK_KEY]).await?;
+        Ok(row.get(0))
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+        include_deleted: bool,
+    ) -> StorageResult<Option<Person>> {
+        let mut result: Option<Person> = None;
+        let query = format!(
+            "
+        select {}
         from userstore.person as pe
         where 1=1
-        and uuid=$1;";
-
-        let client = &self.pool.get().await.unwrap();
-        for row in client.query(query, &[&uuid]).await? {
-            result = Some(Person::new(
-                row.get("id"),
-                row.get("uuid"),
-                row.get("firstname"),
-                row.get("lastname"),
-                row.get("email"),
-                row.get("phone"),
-                row.get("created_on"),
-                row.get("updated_on"),
-            ));
+        and pe.organization_=$1
+        and pe.uuid=$2
+        and ($3 or pe.deleted_on is null);",
+            PERSON_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &uuid, &include_deleted])
+            .await?
+        {
+            result = Some(row::get_person(&row));
         }
 
         Ok(result)
     }
 
-    async fn get_person_by_id(&self, id: i32) -> StorageResult<Option<Person>> {
+    async fn get_person_by_id(
+        &self,
+        organization_id: i32,
+        id: i32,
+    ) -> StorageResult<Option<Person>> {
         let mut result: Option<Person> = None;
-        let query = "
-        select *
+        let query = format!(
+            "
+        select {}
         from userstore.person as pe
         where 1=1
-        and id=$1;
-        ";
+        and pe.organization_=$1
+        and pe.deleted_on is null
+        and pe.id=$2;",
+            PERSON_COLUMNS
+        );
 
-        let client = &self.pool.get().await.unwrap();
-        for row in client.query(query, &[&id]).await? {
-            result = Some(Person::new(
-                row.get("id"),
-                row.get("uuid"),
-                row.get("firstname"),
-                row.get("lastname"),
-                row.get("email"),
-                row.get("phone"),
-                row.get("created_on"),
-                row.get("updated_on"),
-            ));
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &id])
+            .await?
+        {
+            result = Some(row::get_person(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_person_by_email(
+        &self,
+        organization_id: i32,
+        email: &str,
+    ) -> StorageResult<Option<Person>> {
+        let mut result: Option<Person> = None;
+        let query = format!(
+            "
+        select {}
+        from userstore.person as pe
+        where 1=1
+        and pe.organization_=$1
+        and pe.deleted_on is null
+        and lower(pe.email)=lower($2);",
+            PERSON_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &email])
+            .await?
+        {
+            result = Some(row::get_person(&row));
         }
 
         Ok(result)
     }
 
-    async fn get_all_person(&self) -> StorageResult<Vec<Person>> {
+    async fn get_all_person(
+        &self,
+        organization_id: i32,
+        query: &PersonQuery,
+    ) -> StorageResult<Page<Person>> {
         let mut result: Vec<Person> = Vec::new();
+        let pagination = query.get_pagination();
+        let offset = pagination.get_offset().unwrap_or(0);
+        let limit = pagination.get_limit();
 
-        let query = "
-        select *
+        let mut filter = SqlFilter::new();
+        filter.add_equal("pe.organization_", organization_id);
+        filter.add_prefix(&["pe.email"], &query.email);
+        filter.add_prefix(&["pe.firstname", "pe.lastname"], &query.name);
+        filter.add_range("pe.created_on", &query.created_from, &query.created_to);
+        if !query.include_deleted {
+            filter.add_clause("pe.deleted_on is null".to_string());
+        }
+
+        let sort_column = match query.sort {
+            PersonSortField::Firstname => "pe.firstname",
+            PersonSortField::Lastname => "pe.lastname",
+            PersonSortField::Email => "pe.email",
+            PersonSortField::CreatedOn => "pe.created_on",
+        };
+        let direction = get_direction(query.direction);
+
+        let count_query = format!(
+            "
+        select count(*) as total
         from userstore.person as pe
-        where 1=1
-        order by firstname;
-        ";
+        {};",
+            filter.get_where_clause()
+        );
 
-        let client = &self.pool.get().await.unwrap();
-        for row in client.query(query, &[]).await? {
-            let person: Person = Person::new(
-                row.get("id"),
-                row.get("uuid"),
-                row.get("firstname"),
-                row.get("lastname"),
-                row.get("email"),
-                row.get("phone"),
-                row.get("created_on"),
-                row.get("updated_on"),
-            );
+        let client = &self.get_client().await?;
+        let total: i64 = client
+            .query_one(count_query.as_str(), &filter.get_params())
+            .await?
+            .get("total");
+
+        let limit_placeholder = filter.add_param(limit as i64);
+        let offset_placeholder = filter.add_param(offset as i64);
+        let query = format!(
+            "
+        select {}
+        from userstore.person as pe
+        {}
+        order by {} {}, pe.id {}
+        limit {} offset {};",
+            PERSON_COLUMNS,
+            filter.get_where_clause(),
+            sort_column,
+            direction,
+            direction,
+            limit_placeholder,
+            offset_placeholder
+        );
+
+        for row in client.query(query.as_str(), &filter.get_params()).await? {
+            result.push(row::get_person(&row));
+        }
+
+        Ok(Page::new(result, total, offset, limit))
+    }
+
+    async fn update_email_status(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+        status: EmailStatus,
+        verified_on: Option<DateTime<Utc>>,
+    ) -> StorageResult<()> {
+        let query = "
+        UPDATE userstore.PERSON SET email_status = $3, email_verified_on = $4
+        WHERE ID = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[&organization_id, &person_id, &status.as_str(), &verified_on],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn create_email_verification(
+        &self,
+        organization_id: i32,
+        verification: EmailVerification,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.EMAIL_VERIFICATION (organization_, person_, email, token_hash, created_on, expires_on)
+        VALUES ($1,$2,$3,$4,$5,$6);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &verification.person_id,
+                    &verification.email,
+                    &verification.token_hash,
+                    &verification.created_on,
+                    &verification.expires_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_email_verification(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<EmailVerification>> {
+        let mut result: Option<EmailVerification> = None;
+        let query = "
+        select id, person_, email, token_hash, created_on, expires_on
+        from userstore.email_verification
+        where organization_ = $1 and token_hash = $2;";
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query, &[&organization_id, &token_hash])
+            .await?
+        {
+            result = Some(EmailVerification {
+                id: row.get("id"),
+                person_id: row.get("person_"),
+                email: row.get("email"),
+                token_hash: row.get("token_hash"),
+                created_on: row.get("created_on"),
+                expires_on: row.get("expires_on"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn delete_person_email_verifications(
+        &self,
+        organization_id: i32,
+        person_id: i32,
+    ) -> StorageResult<()> {
+        let query =
+            "DELETE FROM userstore.EMAIL_VERIFICATION WHERE organization_ = $1 AND person_ = $2;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &person_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_email_verifications(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let query =
+            "DELETE FROM userstore.EMAIL_VERIFICATION WHERE organization_ = $1 AND expires_on <= $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &now]).await?;
+        Ok(())
+    }
+
+    async fn get_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<Option<LoginCounter>> {
+        let mut result: Option<LoginCounter> = None;
+        let query = "
+        select scope, key, failures, last_failure_on, locked_until
+        from userstore.login_counter
+        where 1=1
+        and organization_=$1
+        and scope=$2
+        and key=$3;";
 
-            result.push(person);
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query, &[&organization_id, &scope.as_str(), &key])
+            .await?
+        {
+            result = Some(LoginCounter {
+                scope,
+                key: row.get("key"),
+                failures: row.get("failures"),
+                last_failure_on: row.get("last_failure_on"),
+                locked_until: row.get("locked_until"),
+            });
         }
 
         Ok(result)
     }
+
+    async fn save_login_counter(
+        &self,
+        organization_id: i32,
+        counter: LoginCounter,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.LOGIN_COUNTER (organization_, scope, key, failures, last_failure_on, locked_until)
+        VALUES ($1,$2,$3,$4,$5,$6)
+        ON CONFLICT (organization_, scope, key) DO UPDATE SET
+        (failures, last_failure_on, locked_until) = ($4,$5,$6);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &counter.scope.as_str(),
+                    &counter.key,
+                    &counter.failures,
+                    &counter.last_failure_on,
+                    &counter.locked_until,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_login_counter(
+        &self,
+        organization_id: i32,
+        scope: CounterScope,
+        key: &str,
+    ) -> StorageResult<()> {
+        let query = "
+        DELETE FROM userstore.LOGIN_COUNTER WHERE organization_ = $1 AND scope = $2 AND key = $3;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &scope.as_str(), &key])
+            .await?;
+        Ok(())
+    }
+
+    async fn add_login_event(&self, organization_id: i32, event: LoginEvent) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.LOGIN_EVENT (organization_, user_, login, occurred_on, ip, user_agent, success, channel)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &event.user_id,
+                    &event.login,
+                    &event.occurred_on,
+                    &event.ip,
+                    &event.user_agent,
+                    &event.success,
+                    &event.channel.as_str(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_login_events(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        pagination: &Pagination,
+    ) -> StorageResult<Page<LoginEvent>> {
+        let mut result: Vec<LoginEvent> = Vec::new();
+        let offset = pagination.get_offset().unwrap_or(0);
+        let limit = pagination.get_limit();
+
+        let count_query = "
+        select count(*) as total
+        from userstore.login_event
+        where organization_ = $1 and user_ = $2;";
+
+        let query = "
+        select id, user_, login, occurred_on, ip, user_agent, success, channel
+        from userstore.login_event
+        where organization_ = $1 and user_ = $2
+        order by occurred_on desc, id desc
+        limit $3 offset $4;";
+
+        let client = &self.get_client().await?;
+        let total: i64 = client
+            .query_one(count_query, &[&organization_id, &user_id])
+            .await?
+            .get("total");
+
+        for row in client
+            .query(
+                query,
+                &[
+                    &organization_id,
+                    &user_id,
+                    &(limit as i64),
+                    &(offset as i64),
+                ],
+            )
+            .await?
+        {
+            let channel: String = row.get("channel");
+            result.push(LoginEvent {
+                id: row.get("id"),
+                user_id: row.get("user_"),
+                login: row.get("login"),
+                occurred_on: row.get("occurred_on"),
+                ip: row.get("ip"),
+                user_agent: row.get("user_agent"),
+                success: row.get("success"),
+                channel: LoginChannel::from_name(&channel).unwrap_or(LoginChannel::Rest),
+            });
+        }
+
+        Ok(Page::new(result, total, offset, limit))
+    }
+
+    async fn create_session(
+        &self,
+        organization_id: i32,
+        mut session: Session,
+    ) -> StorageResult<Session> {
+        let query = "
+        INSERT INTO userstore.SESSION (organization_, user_, device, ip, user_agent, created_on, expires_on,
+            access_token_hash, access_expires_on)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
+        RETURNING id, uuid;";
+
+        let client = &self.get_client().await?;
+        let row_inserted = client
+            .query_one(
+                query,
+                &[
+                    &organization_id,
+                    &session.user_id,
+                    &session.device,
+                    &session.ip,
+                    &session.user_agent,
+                    &session.created_on,
+                    &session.expires_on,
+                    &session.access_token_hash,
+                    &session.access_expires_on,
+                ],
+            )
+            .await?;
+
+        session.id = row_inserted.get("id");
+        session.uuid = row_inserted.get("uuid");
+        Ok(session)
+    }
+
+    async fn get_session(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Session>> {
+        let mut result: Option<Session> = None;
+        let query = format!(
+            "
+        select {}
+        from userstore.session
+        where organization_ = $1 and uuid = $2;",
+            SESSION_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, uuid])
+            .await?
+        {
+            result = Some(row::get_session(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_session_by_access_token(
+        &self,
+        access_token_hash: &str,
+    ) -> StorageResult<Option<Session>> {
+        let mut result: Option<Session> = None;
+        let query = format!(
+            "
+        select {}
+        from userstore.session
+        where access_token_hash = $1;",
+            SESSION_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client.query(query.as_str(), &[&access_token_hash]).await? {
+            result = Some(row::get_session(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_user_sessions(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<Vec<Session>> {
+        let mut result: Vec<Session> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        from userstore.session
+        where organization_ = $1 and user_ = $2
+        and expires_on > $3
+        order by coalesce(refreshed_on, created_on) desc, id desc;",
+            SESSION_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &user_id, &now])
+            .await?
+        {
+            result.push(row::get_session(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn update_session(&self, organization_id: i32, session: Session) -> StorageResult<()> {
+        let query = "
+        UPDATE userstore.SESSION SET refreshed_on = $3, expires_on = $4,
+            access_token_hash = $5, access_expires_on = $6
+        WHERE id = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &session.id,
+                    &session.refreshed_on,
+                    &session.expires_on,
+                    &session.access_token_hash,
+                    &session.access_expires_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_session(&self, organization_id: i32, uuid: &uuid::Uuid) -> StorageResult<()> {
+        //Refresh tokens are removed by cascade.
+        let query = "DELETE FROM userstore.SESSION WHERE organization_ = $1 AND uuid = $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, uuid]).await?;
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, organization_id: i32, user_id: i32) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.SESSION WHERE organization_ = $1 AND user_ = $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &user_id]).await?;
+        Ok(())
+    }
+
+    async fn delete_expired_sessions(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.SESSION WHERE organization_ = $1 AND expires_on <= $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &now]).await?;
+        Ok(())
+    }
+
+    async fn add_refresh_token(
+        &self,
+        organization_id: i32,
+        token: RefreshToken,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.REFRESH_TOKEN (organization_, user_, family, token_hash, created_on, expires_on)
+        VALUES ($1,$2,$3,$4,$5,$6);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &token.user_id,
+                    &token.family,
+                    &token.token_hash,
+                    &token.created_on,
+                    &token.expires_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_refresh_token(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<RefreshToken>> {
+        let mut result: Option<RefreshToken> = None;
+        let query = "
+        select id, user_, family, token_hash, created_on, expires_on, rotated_on
+        from userstore.refresh_token
+        where organization_ = $1 and token_hash = $2;";
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query, &[&organization_id, &token_hash])
+            .await?
+        {
+            result = Some(RefreshToken {
+                id: row.get("id"),
+                user_id: row.get("user_"),
+                family: row.get("family"),
+                token_hash: row.get("token_hash"),
+                created_on: row.get("created_on"),
+                expires_on: row.get("expires_on"),
+                rotated_on: row.get("rotated_on"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        //Only the first of concurrent exchanges wins.
+        let query = "
+        UPDATE userstore.REFRESH_TOKEN SET rotated_on = $3
+        WHERE id = $2 AND organization_ = $1 AND rotated_on IS NULL;";
+
+        let client = &self.get_client().await?;
+        let updated = client.execute(query, &[&organization_id, &id, &on]).await?;
+        Ok(updated == 1)
+    }
+
+    async fn get_two_factor(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Option<TwoFactor>> {
+        let mut result: Option<TwoFactor> = None;
+        let query = "
+        select user_, secret, created_on, confirmed_on, last_used_step
+        from userstore.two_factor
+        where organization_ = $1 and user_ = $2;";
+
+        let client = &self.get_client().await?;
+        for row in client.query(query, &[&organization_id, &user_id]).await? {
+            result = Some(TwoFactor {
+                user_id: row.get("user_"),
+                secret: row.get("secret"),
+                created_on: row.get("created_on"),
+                confirmed_on: row.get("confirmed_on"),
+                last_used_step: row.get("last_used_step"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn save_two_factor(
+        &self,
+        organization_id: i32,
+        two_factor: TwoFactor,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.TWO_FACTOR (organization_, user_, secret, created_on, confirmed_on, last_used_step)
+        SELECT $1, u.id, $3, $4, $5, $6
+        FROM userstore.applicationuser as u
+        WHERE u.id = $2 AND u.organization_ = $1
+        ON CONFLICT (user_) DO UPDATE
+        SET secret = $3, created_on = $4, confirmed_on = $5, last_used_step = $6;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &two_factor.user_id,
+                    &two_factor.secret,
+                    &two_factor.created_on,
+                    &two_factor.confirmed_on,
+                    &two_factor.last_used_step,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_two_factor(&self, organization_id: i32, user_id: i32) -> StorageResult<()> {
+        let query = "
+        WITH codes AS (
+            DELETE FROM userstore.RECOVERY_CODE WHERE organization_ = $1 AND user_ = $2
+        )
+        DELETE FROM userstore.TWO_FACTOR WHERE organization_ = $1 AND user_ = $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &user_id]).await?;
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hashes: Vec<String>,
+    ) -> StorageResult<()> {
+        //A single statement, the previous codes are never left alongside the new ones.
+        let query = "
+        WITH previous AS (
+            DELETE FROM userstore.RECOVERY_CODE WHERE organization_ = $1 AND user_ = $2
+        )
+        INSERT INTO userstore.RECOVERY_CODE (organization_, user_, code_hash)
+        SELECT $1, u.id, unnest($3::varchar[])
+        FROM userstore.applicationuser as u
+        WHERE u.id = $2 AND u.organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &user_id, &code_hashes])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_recovery_codes(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<RecoveryCode>> {
+        let mut result: Vec<RecoveryCode> = Vec::new();
+        let query = "
+        select id, user_, code_hash, used_on
+        from userstore.recovery_code
+        where organization_ = $1 and user_ = $2
+        order by id;";
+
+        let client = &self.get_client().await?;
+        for row in client.query(query, &[&organization_id, &user_id]).await? {
+            result.push(RecoveryCode {
+                id: row.get("id"),
+                user_id: row.get("user_"),
+                code_hash: row.get("code_hash"),
+                used_on: row.get("used_on"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn use_recovery_code(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        code_hash: &str,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        //Only the first of concurrent uses wins.
+        let query = "
+        UPDATE userstore.RECOVERY_CODE SET used_on = $4
+        WHERE id = (
+            SELECT id FROM userstore.recovery_code
+            WHERE organization_ = $1 AND user_ = $2 AND code_hash = $3 AND used_on IS NULL
+            LIMIT 1
+        ) AND used_on IS NULL;";
+
+        let client = &self.get_client().await?;
+        let updated = client
+            .execute(query, &[&organization_id, &user_id, &code_hash, &on])
+            .await?;
+        Ok(updated == 1)
+    }
+
+    async fn create_login_challenge(
+        &self,
+        organization_id: i32,
+        challenge: LoginChallenge,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.LOGIN_CHALLENGE (organization_, user_, token_hash, device, created_on, expires_on)
+        VALUES ($1,$2,$3,$4,$5,$6);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &challenge.user_id,
+                    &challenge.token_hash,
+                    &challenge.device,
+                    &challenge.created_on,
+                    &challenge.expires_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_login_challenge(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<LoginChallenge>> {
+        let mut result: Option<LoginChallenge> = None;
+        let query = "
+        select id, user_, token_hash, device, created_on, expires_on
+        from userstore.login_challenge
+        where organization_ = $1 and token_hash = $2;";
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query, &[&organization_id, &token_hash])
+            .await?
+        {
+            result = Some(LoginChallenge {
+                id: row.get("id"),
+                user_id: row.get("user_"),
+                token_hash: row.get("token_hash"),
+                device: row.get("device"),
+                created_on: row.get("created_on"),
+                expires_on: row.get("expires_on"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn delete_login_challenge(&self, organization_id: i32, id: i32) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.LOGIN_CHALLENGE WHERE organization_ = $1 AND id = $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &id]).await?;
+        Ok(())
+    }
+
+    async fn delete_expired_login_challenges(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let query =
+            "DELETE FROM userstore.LOGIN_CHALLENGE WHERE organization_ = $1 AND expires_on <= $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &now]).await?;
+        Ok(())
+    }
+
+    async fn create_password_reset(
+        &self,
+        organization_id: i32,
+        reset: PasswordReset,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.PASSWORD_RESET (organization_, user_, token_hash, created_on, expires_on)
+        VALUES ($1,$2,$3,$4,$5);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &reset.user_id,
+                    &reset.token_hash,
+                    &reset.created_on,
+                    &reset.expires_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_password_reset(
+        &self,
+        organization_id: i32,
+        token_hash: &str,
+    ) -> StorageResult<Option<PasswordReset>> {
+        let mut result: Option<PasswordReset> = None;
+        let query = "
+        select id, user_, token_hash, created_on, expires_on, used_on
+        from userstore.password_reset
+        where organization_ = $1 and token_hash = $2;";
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query, &[&organization_id, &token_hash])
+            .await?
+        {
+            result = Some(PasswordReset {
+                id: row.get("id"),
+                user_id: row.get("user_"),
+                token_hash: row.get("token_hash"),
+                created_on: row.get("created_on"),
+                expires_on: row.get("expires_on"),
+                used_on: row.get("used_on"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn use_password_reset(
+        &self,
+        organization_id: i32,
+        id: i32,
+        on: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        //Only the first of concurrent uses wins.
+        let query = "
+        UPDATE userstore.PASSWORD_RESET SET used_on = $3
+        WHERE id = $2 AND organization_ = $1 AND used_on IS NULL;";
+
+        let client = &self.get_client().await?;
+        let updated = client.execute(query, &[&organization_id, &id, &on]).await?;
+        Ok(updated == 1)
+    }
+
+    async fn delete_user_password_resets(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.PASSWORD_RESET WHERE organization_ = $1 AND user_ = $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &user_id]).await?;
+        Ok(())
+    }
+
+    async fn delete_expired_password_resets(
+        &self,
+        organization_id: i32,
+        now: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let query =
+            "DELETE FROM userstore.PASSWORD_RESET WHERE organization_ = $1 AND expires_on <= $2;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &now]).await?;
+        Ok(())
+    }
+
+    async fn get_all_roles(&self, organization_id: i32) -> StorageResult<Vec<Role>> {
+        let mut result: Vec<Role> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        from userstore.role as r
+        where r.organization_ = $1
+        order by r.name;",
+            ROLE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client.query(query.as_str(), &[&organization_id]).await? {
+            result.push(row::get_role(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_role_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Role>> {
+        let mut result: Option<Role> = None;
+        let query = format!(
+            "
+        select {}
+        from userstore.role as r
+        where r.organization_ = $1 and r.uuid = $2;",
+            ROLE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, uuid])
+            .await?
+        {
+            result = Some(row::get_role(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_role_by_name(
+        &self,
+        organization_id: i32,
+        name: &str,
+    ) -> StorageResult<Option<Role>> {
+        let mut result: Option<Role> = None;
+        let query = format!(
+            "
+        select {}
+        from userstore.role as r
+        where r.organization_ = $1 and lower(r.name) = lower($2);",
+            ROLE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &name])
+            .await?
+        {
+            result = Some(row::get_role(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn create_role(&self, organization_id: i32, mut role: Role) -> StorageResult<Role> {
+        role.created_on = Some(Utc::now());
+        let query = "
+        INSERT INTO userstore.ROLE (organization_, name, description, permissions, created_on)
+        VALUES ($1,$2,$3,$4,$5)
+        RETURNING id, uuid;";
+
+        let client = &self.get_client().await?;
+        let row_inserted = client
+            .query_one(
+                query,
+                &[
+                    &organization_id,
+                    &role.name,
+                    &role.description,
+                    &row::get_permission_names(&role.permissions),
+                    &role.created_on,
+                ],
+            )
+            .await
+            .map_err(get_write_error)?;
+
+        role.id = row_inserted.get("id");
+        role.uuid = row_inserted.get("uuid");
+        Ok(role)
+    }
+
+    async fn update_role(&self, organization_id: i32, mut role: Role) -> StorageResult<Role> {
+        role.updated_on = Some(Utc::now());
+        let query = "
+        UPDATE userstore.ROLE SET name = $3, description = $4, permissions = $5, updated_on = $6
+        WHERE id = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &role.id,
+                    &role.name,
+                    &role.description,
+                    &row::get_permission_names(&role.permissions),
+                    &role.updated_on,
+                ],
+            )
+            .await
+            .map_err(get_write_error)?;
+        Ok(role)
+    }
+
+    async fn delete_role(&self, organization_id: i32, role: Role) -> StorageResult<()> {
+        //Assignments are removed by cascade.
+        let query = "DELETE FROM userstore.ROLE WHERE id = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client.execute(query, &[&organization_id, &role.id]).await?;
+        Ok(())
+    }
+
+    async fn get_user_roles(&self, organization_id: i32, user_id: i32) -> StorageResult<Vec<Role>> {
+        let mut result: Vec<Role> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        from userstore.role as r
+        join userstore.user_role as ur on ur.role_ = r.id
+        where r.organization_ = $1 and ur.user_ = $2
+        order by r.name;",
+            ROLE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &user_id])
+            .await?
+        {
+            result.push(row::get_role(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn add_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.USER_ROLE (user_, role_)
+        SELECT u.id, r.id
+        FROM userstore.applicationuser as u, userstore.role as r
+        WHERE u.id = $2 AND r.id = $3 AND u.organization_ = $1 AND r.organization_ = $1
+        ON CONFLICT DO NOTHING;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &user_id, &role_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_user_role(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.USER_ROLE WHERE user_ = $2 AND role_ = $3
+        AND role_ IN (SELECT id FROM userstore.role WHERE organization_ = $1);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &user_id, &role_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_all_groups(&self, organization_id: i32) -> StorageResult<Vec<Group>> {
+        let mut result: Vec<Group> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        {}
+        where g.organization_ = $1
+        order by g.name;",
+            GROUP_COLUMNS, GROUP_FROM
+        );
+
+        let client = &self.get_client().await?;
+        for row in client.query(query.as_str(), &[&organization_id]).await? {
+            result.push(row::get_group(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_group_by_uuid(
+        &self,
+        organization_id: i32,
+        uuid: &uuid::Uuid,
+    ) -> StorageResult<Option<Group>> {
+        let mut result: Option<Group> = None;
+        let query = format!(
+            "
+        select {}
+        {}
+        where g.organization_ = $1 and g.uuid = $2;",
+            GROUP_COLUMNS, GROUP_FROM
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, uuid])
+            .await?
+        {
+            result = Some(row::get_group(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn create_group(&self, organization_id: i32, mut group: Group) -> StorageResult<Group> {
+        group.created_on = Some(Utc::now());
+        let query = "
+        INSERT INTO userstore.USERGROUP (organization_, name, description, parent_, created_on)
+        VALUES ($1,$2,$3,$4,$5)
+        RETURNING id, uuid;";
+
+        let client = &self.get_client().await?;
+        let row_inserted = client
+            .query_one(
+                query,
+                &[
+                    &organization_id,
+                    &group.name,
+                    &group.description,
+                    &group.parent_id,
+                    &group.created_on,
+                ],
+            )
+            .await
+            .map_err(get_write_error)?;
+
+        group.id = row_inserted.get("id");
+        group.uuid = row_inserted.get("uuid");
+        Ok(group)
+    }
+
+    async fn update_group(&self, organization_id: i32, mut group: Group) -> StorageResult<Group> {
+        group.updated_on = Some(Utc::now());
+        let query = "
+        UPDATE userstore.USERGROUP SET name = $3, description = $4, parent_ = $5, updated_on = $6
+        WHERE id = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &group.id,
+                    &group.name,
+                    &group.description,
+                    &group.parent_id,
+                    &group.updated_on,
+                ],
+            )
+            .await
+            .map_err(get_write_error)?;
+        Ok(group)
+    }
+
+    async fn delete_group(&self, organization_id: i32, group: Group) -> StorageResult<()> {
+        //Subgroups are detached, memberships and roles removed by the foreign keys.
+        let query = "DELETE FROM userstore.USERGROUP WHERE id = $2 AND organization_ = $1;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &group.id])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_group_members(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<AppUser>> {
+        let mut result: Vec<AppUser> = Vec::new();
+        let query = format!(
+            "
+        select {}, {}
+        {}
+        join userstore.group_member as gm on gm.user_ = u.id
+        where u.organization_ = $1 and gm.group_ = $2 and u.deleted_on is null
+        order by u.login;",
+            USER_COLUMNS, PERSON_COLUMNS, USER_FROM
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &group_id])
+            .await?
+        {
+            //Do not restitute password
+            result.push(row::get_user(&row, false));
+        }
+
+        Ok(result)
+    }
+
+    async fn get_user_groups(
+        &self,
+        organization_id: i32,
+        user_id: i32,
+    ) -> StorageResult<Vec<Group>> {
+        let mut result: Vec<Group> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        {}
+        join userstore.group_member as gm on gm.group_ = g.id
+        where g.organization_ = $1 and gm.user_ = $2
+        order by g.name;",
+            GROUP_COLUMNS, GROUP_FROM
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &user_id])
+            .await?
+        {
+            result.push(row::get_group(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn add_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.GROUP_MEMBER (group_, user_)
+        SELECT g.id, u.id
+        FROM userstore.usergroup as g, userstore.applicationuser as u
+        WHERE g.id = $2 AND u.id = $3 AND g.organization_ = $1 AND u.organization_ = $1
+        ON CONFLICT DO NOTHING;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &group_id, &user_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_group_member(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        user_id: i32,
+    ) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.GROUP_MEMBER WHERE group_ = $2 AND user_ = $3
+        AND group_ IN (SELECT id FROM userstore.usergroup WHERE organization_ = $1);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &group_id, &user_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn get_group_roles(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+    ) -> StorageResult<Vec<Role>> {
+        let mut result: Vec<Role> = Vec::new();
+        let query = format!(
+            "
+        select {}
+        from userstore.role as r
+        join userstore.group_role as gr on gr.role_ = r.id
+        where r.organization_ = $1 and gr.group_ = $2
+        order by r.name;",
+            ROLE_COLUMNS
+        );
+
+        let client = &self.get_client().await?;
+        for row in client
+            .query(query.as_str(), &[&organization_id, &group_id])
+            .await?
+        {
+            result.push(row::get_role(&row));
+        }
+
+        Ok(result)
+    }
+
+    async fn add_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.GROUP_ROLE (group_, role_)
+        SELECT g.id, r.id
+        FROM userstore.usergroup as g, userstore.role as r
+        WHERE g.id = $2 AND r.id = $3 AND g.organization_ = $1 AND r.organization_ = $1
+        ON CONFLICT DO NOTHING;";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &group_id, &role_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_group_role(
+        &self,
+        organization_id: i32,
+        group_id: i32,
+        role_id: i32,
+    ) -> StorageResult<()> {
+        let query = "DELETE FROM userstore.GROUP_ROLE WHERE group_ = $2 AND role_ = $3
+        AND group_ IN (SELECT id FROM userstore.usergroup WHERE organization_ = $1);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(query, &[&organization_id, &group_id, &role_id])
+            .await?;
+        Ok(())
+    }
+
+    async fn add_audit_entry(&self, organization_id: i32, entry: AuditEntry) -> StorageResult<()> {
+        let query = "
+        INSERT INTO userstore.AUDIT_ENTRY (organization_, actor, action, target, target_uuid, changes, request_id, occurred_on)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8);";
+
+        let client = &self.get_client().await?;
+        client
+            .execute(
+                query,
+                &[
+                    &organization_id,
+                    &entry.actor,
+                    &entry.action.as_str(),
+                    &entry.target.as_str(),
+                    &entry.target_uuid,
+                    &Json(&entry.changes),
+                    &entry.request_id,
+                    &entry.occurred_on,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        organization_id: i32,
+        query: &AuditQuery,
+    ) -> StorageResult<Page<AuditEntry>> {
+        let mut result: Vec<AuditEntry> = Vec::new();
+        let pagination = query.get_pagination();
+        let offset = pagination.get_offset().unwrap_or(0);
+        let limit = pagination.get_limit();
+
+        let mut filter = SqlFilter::new();
+        filter.add_equal("organization_", organization_id);
+        if let Some(actor) = query.actor {
+            filter.add_equal("actor", actor);
+        }
+        if let Some(action) = query.action {
+            filter.add_equal("action", action.as_str());
+        }
+        if let Some(target) = query.target {
+            filter.add_equal("target", target.as_str());
+        }
+        if let Some(target_uuid) = query.target_uuid {
+            filter.add_equal("target_uuid", target_uuid);
+        }
+        if let Some(request_id) = &query.request_id {
+            filter.add_equal("request_id", request_id.clone());
+        }
+        filter.add_range("occurred_on", &query.from, &query.to);
+
+        let count_query = format!(
+            "
+        select count(*) as total
+        from userstore.audit_entry
+        {};",
+            filter.get_where_clause()
+        );
+
+        let client = &self.get_client().await?;
+        let total: i64 = client
+            .query_one(count_query.as_str(), &filter.get_params())
+            .await?
+            .get("total");
+
+        let limit_placeholder = filter.add_param(limit as i64);
+        let offset_placeholder = filter.add_param(offset as i64);
+        let query = format!(
+            "
+        select {}
+        from userstore.audit_entry
+        {}
+        order by occurred_on desc, id desc
+        limit {} offset {};",
+            AUDIT_ENTRY_COLUMNS,
+            filter.get_where_clause(),
+            limit_placeholder,
+            offset_placeholder
+        );
+
+        for row in client.query(query.as_str(), &filter.get_params()).await? {
+            result.push(row::get_audit_entry(&row));
+        }
+
+        Ok(Page::new(result, total, offset, limit))
+    }
+
+    async fn begin(&self) -> StorageResult<Box<dyn StorageTransaction>> {
+        if self.transaction.is_some() {
+            return Err(StorageError::TransactionAlreadyOpen);
+        }
+
+        let client = self.get_pooled_client().await?;
+        client.batch_execute("BEGIN;").await?;
+        Ok(Box::new(PgDbUserTransaction {
+            storage: PgDbUserStorage {
+                pool: self.pool.clone(),
+                transaction: Some(client),
+            },
+        }))
+    }
 }
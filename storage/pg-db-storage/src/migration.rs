@@ -0,0 +1,286 @@
+use crate::PgDbUserStorage;
+use chrono::prelude::*;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use helix_user_domain::storage::error::*;
+use std::collections::HashMap;
+
+//Arbitrary key serializing concurrent migration runs.
+const MIGRATION_LOCK_KEY: i64 = 0x4845_4c49_5855_5352;
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input_str(self.sql);
+        hasher.result_str()
+    }
+}
+
+//Embedded migrations, ordered by version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_userstore",
+        sql: include_str!("../migrations/V001__create_userstore.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "unique_login_email",
+        sql: include_str!("../migrations/V002__unique_login_email.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_login_counter",
+        sql: include_str!("../migrations/V003__create_login_counter.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "create_login_event",
+        sql: include_str!("../migrations/V004__create_login_event.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "create_refresh_token",
+        sql: include_str!("../migrations/V005__create_refresh_token.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_session",
+        sql: include_str!("../migrations/V006__create_session.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "create_role",
+        sql: include_str!("../migrations/V007__create_role.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "create_group",
+        sql: include_str!("../migrations/V008__create_group.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "create_organization",
+        sql: include_str!("../migrations/V009__create_organization.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "create_two_factor",
+        sql: include_str!("../migrations/V010__create_two_factor.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "create_password_reset",
+        sql: include_str!("../migrations/V011__create_password_reset.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "add_email_verification",
+        sql: include_str!("../migrations/V012__add_email_verification.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "add_account_status",
+        sql: include_str!("../migrations/V013__add_account_status.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "add_soft_delete",
+        sql: include_str!("../migrations/V014__add_soft_delete.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "create_audit_entry",
+        sql: include_str!("../migrations/V015__create_audit_entry.sql"),
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationMode {
+    //Do not touch the schema.
+    None,
+    //Report applied and pending migrations.
+    Status,
+    //Run the pending migrations in a transaction rolled back at the end.
+    DryRun,
+    //Apply pending migrations.
+    Apply,
+}
+
+impl MigrationMode {
+    pub fn from_name(name: &str) -> MigrationMode {
+        match name.to_lowercase().as_str() {
+            "status" => MigrationMode::Status,
+            "dry-run" | "dry_run" => MigrationMode::DryRun,
+            "apply" => MigrationMode::Apply,
+            _ => MigrationMode::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i32,
+    pub name: String,
+    pub applied_on: Option<DateTime<Utc>>,
+}
+
+impl MigrationStatus {
+    pub fn is_pending(&self) -> bool {
+        self.applied_on.is_none()
+    }
+}
+
+impl PgDbUserStorage {
+    //Run the migrations according to the mode and return the resulting status.
+    pub async fn migrate(&self, mode: MigrationMode) -> StorageResult<Vec<MigrationStatus>> {
+        match mode {
+            MigrationMode::None => Ok(Vec::new()),
+            MigrationMode::Status => self.get_migration_status().await,
+            MigrationMode::DryRun => self.check_pending_migrations().await,
+            MigrationMode::Apply => self.apply_pending_migrations().await,
+        }
+    }
+
+    //Read only, a database without the history table has nothing applied yet.
+    pub async fn get_migration_status(&self) -> StorageResult<Vec<MigrationStatus>> {
+        let client = &self.get_client().await?;
+
+        let mut applied: HashMap<i32, (String, DateTime<Utc>)> = HashMap::new();
+        let history = client
+            .query_one(
+                "select to_regclass('userstore.schema_migration') is not null as present;",
+                &[],
+            )
+            .await?;
+        if history.get("present") {
+            let query = "select version, checksum, applied_on from userstore.schema_migration;";
+            for row in client.query(query, &[]).await? {
+                applied.insert(
+                    row.get("version"),
+                    (row.get("checksum"), row.get("applied_on")),
+                );
+            }
+        }
+
+        let mut result: Vec<MigrationStatus> = Vec::new();
+        for migration in MIGRATIONS {
+            let applied_on = match applied.get(&migration.version) {
+                None => None,
+                Some((checksum, applied_on)) => {
+                    if checksum != &migration.checksum() {
+                        return Err(StorageError::Migration(format!(
+                            "V{:03} {} was modified after being applied",
+                            migration.version, migration.name
+                        )));
+                    }
+                    Some(*applied_on)
+                }
+            };
+
+            result.push(MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                applied_on,
+            });
+        }
+
+        Ok(result)
+    }
+
+    //The scripts meet the real schema and data, so a broken one fails here
+    //with its version instead of halfway through an apply.
+    pub async fn check_pending_migrations(&self) -> StorageResult<Vec<MigrationStatus>> {
+        let status = self.get_migration_status().await?;
+        let mut client = self.get_pooled_client().await?;
+
+        let transaction = client.transaction().await?;
+        transaction
+            .execute("select pg_advisory_xact_lock($1);", &[&MIGRATION_LOCK_KEY])
+            .await?;
+        for pending in status.iter().filter(|status| status.is_pending()) {
+            let migration = MIGRATIONS
+                .iter()
+                .find(|migration| migration.version == pending.version)
+                .unwrap();
+            //Dropping the transaction rolls back what ran before.
+            transaction
+                .batch_execute(migration.sql)
+                .await
+                .map_err(|error| {
+                    StorageError::Migration(format!(
+                        "V{:03} {} failed: {}",
+                        migration.version, migration.name, error
+                    ))
+                })?;
+        }
+        transaction.rollback().await?;
+
+        Ok(status)
+    }
+
+    pub async fn apply_pending_migrations(&self) -> StorageResult<Vec<MigrationStatus>> {
+        let mut client = self.get_pooled_client().await?;
+        client.batch_execute(HISTORY_TABLE_DDL).await?;
+
+        //Each migration runs in its own transaction.
+        for status in self.get_migration_status().await? {
+            if !status.is_pending() {
+                continue;
+            }
+            let migration = MIGRATIONS
+                .iter()
+                .find(|migration| migration.version == status.version)
+                .unwrap();
+
+            let transaction = client.transaction().await?;
+            transaction
+                .execute("select pg_advisory_xact_lock($1);", &[&MIGRATION_LOCK_KEY])
+                .await?;
+
+            //Another instance may have applied it while waiting for the lock.
+            let already_applied = transaction
+                .query(
+                    "select 1 from userstore.schema_migration where version = $1;",
+                    &[&migration.version],
+                )
+                .await?;
+            if !already_applied.is_empty() {
+                transaction.rollback().await?;
+                continue;
+            }
+
+            transaction.batch_execute(migration.sql).await?;
+            transaction
+                .execute(
+                    "INSERT INTO userstore.schema_migration (version, name, checksum, applied_on)
+                    VALUES ($1, $2, $3, $4);",
+                    &[
+                        &migration.version,
+                        &migration.name,
+                        &migration.checksum(),
+                        &Utc::now(),
+                    ],
+                )
+                .await?;
+            transaction.commit().await?;
+        }
+
+        self.get_migration_status().await
+    }
+}
+
+const HISTORY_TABLE_DDL: &str = "
+CREATE SCHEMA IF NOT EXISTS userstore;
+CREATE TABLE IF NOT EXISTS userstore.schema_migration (
+    version INTEGER PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    checksum VARCHAR(64) NOT NULL,
+    applied_on TIMESTAMPTZ NOT NULL
+);";
@@ -0,0 +1,188 @@
+use helix_user_domain::core::app_user::{AccountStatus, AppUser, StatusChange};
+use helix_user_domain::core::audit::{AuditAction, AuditEntry, AuditTarget};
+use helix_user_domain::core::group::Group;
+use helix_user_domain::core::organization::Organization;
+use helix_user_domain::core::person::{EmailStatus, Person};
+use helix_user_domain::core::role::{Permission, Role};
+use helix_user_domain::core::session::Session;
+use tokio_postgres::types::Json;
+use tokio_postgres::Row;
+
+//Explicit column lists, person columns are prefixed to stay unique in joins.
+pub const PERSON_COLUMNS: &str = "
+        pe.id as person_id, pe.uuid as person_uuid, pe.firstname as person_firstname,
+        pe.lastname as person_lastname, pe.email as person_email, pe.phone as person_phone,
+        pe.created_on as person_created_on, pe.updated_on as person_updated_on,
+        pe.email_status as person_email_status, pe.email_verified_on as person_email_verified_on,
+        pe.deleted_on as person_deleted_on, pe.deleted_by as person_deleted_by";
+
+pub const SESSION_COLUMNS: &str = "
+        id, uuid, organization_, user_, device, ip, user_agent, created_on, refreshed_on, expires_on,
+        access_token_hash, access_expires_on";
+
+//Groups are read with the uuid of their parent.
+pub const GROUP_COLUMNS: &str = "
+        g.id, g.uuid, g.name, g.description, g.parent_, pg.uuid as parent_uuid,
+        g.created_on, g.updated_on";
+
+pub const GROUP_FROM: &str = "
+        from userstore.usergroup as g
+        left join userstore.usergroup as pg on pg.id = g.parent_";
+
+pub const ROLE_COLUMNS: &str = "
+        r.id, r.uuid, r.name, r.description, r.permissions, r.created_on, r.updated_on";
+
+pub const USER_COLUMNS: &str = "
+        u.id, u.uuid, u.login, u.photo, u.created_on, u.updated_on, u.lastlogin_on,
+        u.status, u.status_reason, u.suspended_until, u.deleted_on, u.deleted_by";
+
+pub const STATUS_CHANGE_COLUMNS: &str = "
+        id, user_, previous_status, status, reason, suspended_until, changed_by, changed_on";
+
+pub const AUDIT_ENTRY_COLUMNS: &str = "
+        id, actor, action, target, target_uuid, changes, request_id, occurred_on";
+
+//Users are always loaded with their person in a single round trip.
+pub const USER_FROM: &str = "
+        from userstore.applicationuser as u
+        join userstore.person as pe on pe.id = u.person_";
+
+pub fn get_person(row: &Row) -> Person {
+    let mut person = Person::new(
+        row.get("person_id"),
+        row.get("person_uuid"),
+        row.get("person_firstname"),
+        row.get("person_lastname"),
+        row.get("person_email"),
+        row.get("person_phone"),
+        row.get("person_created_on"),
+        row.get("person_updated_on"),
+    );
+    let email_status: String = row.get("person_email_status");
+    person.email_status = EmailStatus::from_name(&email_status).unwrap_or_default();
+    person.email_verified_on = row.get("person_email_verified_on");
+    person.deleted_on = row.get("person_deleted_on");
+    person.deleted_by = row.get("person_deleted_by");
+    person
+}
+
+//The password column is only read when selected, otherwise left empty.
+pub fn get_user(row: &Row, with_password: bool) -> AppUser {
+    let mut user = AppUser::new(
+        row.get("id"),
+        row.get("uuid"),
+        row.get("login"),
+        match with_password {
+            true => row.get("password"),
+            false => "".to_string(),
+        },
+        row.get("photo"),
+        row.get("created_on"),
+        row.get("updated_on"),
+        row.get("lastlogin_on"),
+        get_person(row),
+    );
+    let status: String = row.get("status");
+    user.status = AccountStatus::from_name(&status).unwrap_or_default();
+    user.status_reason = row.get("status_reason");
+    user.suspended_until = row.get("suspended_until");
+    user.deleted_on = row.get("deleted_on");
+    user.deleted_by = row.get("deleted_by");
+    user
+}
+
+pub fn get_status_change(row: &Row) -> StatusChange {
+    let previous_status: String = row.get("previous_status");
+    let status: String = row.get("status");
+    StatusChange {
+        id: row.get("id"),
+        user_id: row.get("user_"),
+        previous_status: AccountStatus::from_name(&previous_status).unwrap_or_default(),
+        status: AccountStatus::from_name(&status).unwrap_or_default(),
+        reason: row.get("reason"),
+        suspended_until: row.get("suspended_until"),
+        changed_by: row.get("changed_by"),
+        changed_on: row.get("changed_on"),
+    }
+}
+
+//Unknown names come from a newer version of the service, they are read as updates.
+pub fn get_audit_entry(row: &Row) -> AuditEntry {
+    let action: String = row.get("action");
+    let target: String = row.get("target");
+    let changes: Json<_> = row.get("changes");
+    AuditEntry {
+        id: row.get("id"),
+        actor: row.get("actor"),
+        action: AuditAction::from_name(&action).unwrap_or(AuditAction::Update),
+        target: AuditTarget::from_name(&target).unwrap_or(AuditTarget::User),
+        target_uuid: row.get("target_uuid"),
+        changes: changes.0,
+        request_id: row.get("request_id"),
+        occurred_on: row.get("occurred_on"),
+    }
+}
+
+pub fn get_session(row: &Row) -> Session {
+    Session {
+        id: row.get("id"),
+        uuid: row.get("uuid"),
+        organization_id: row.get("organization_"),
+        user_id: row.get("user_"),
+        device: row.get("device"),
+        ip: row.get("ip"),
+        user_agent: row.get("user_agent"),
+        created_on: row.get("created_on"),
+        refreshed_on: row.get("refreshed_on"),
+        expires_on: row.get("expires_on"),
+        access_token_hash: row.get("access_token_hash"),
+        access_expires_on: row.get("access_expires_on"),
+    }
+}
+
+pub fn get_organization(row: &Row) -> Organization {
+    Organization::new(
+        row.get("id"),
+        row.get("uuid"),
+        row.get("name"),
+        row.get("created_on"),
+        row.get("updated_on"),
+    )
+}
+
+pub fn get_group(row: &Row) -> Group {
+    Group::new(
+        row.get("id"),
+        row.get("uuid"),
+        row.get("name"),
+        row.get("description"),
+        row.get("parent_"),
+        row.get("parent_uuid"),
+        row.get("created_on"),
+        row.get("updated_on"),
+    )
+}
+
+//Unknown permission names, left by a newer version, are ignored.
+pub fn get_role(row: &Row) -> Role {
+    let permissions: Vec<String> = row.get("permissions");
+    Role::new(
+        row.get("id"),
+        row.get("uuid"),
+        row.get("name"),
+        row.get("description"),
+        permissions
+            .iter()
+            .filter_map(|name| Permission::from_name(name))
+            .collect(),
+        row.get("created_on"),
+        row.get("updated_on"),
+    )
+}
+
+pub fn get_permission_names(permissions: &[Permission]) -> Vec<String> {
+    permissions
+        .iter()
+        .map(|permission| permission.as_str().to_string())
+        .collect()
+}
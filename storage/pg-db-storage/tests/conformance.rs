@@ -0,0 +1,163 @@
+//Needs a migrated database, configured with the HELIX_DB_* variables:
+//cargo test -p pg-db-storage -- --ignored
+use helix_user_domain::storage::conformance;
+use helix_user_domain::storage::traits::StorageTrait;
+use pg_db_storage::migration::{MigrationMode, MigrationStatus};
+use pg_db_storage::PgDbUserStorage;
+use std::env;
+
+fn get_storage() -> PgDbUserStorage {
+    PgDbUserStorage::new(
+        env::var("HELIX_DB_NAME").expect("HELIX_DB_NAME not found."),
+        env::var("HELIX_DB_HOST").expect("HELIX_DB_HOST not found."),
+        env::var("HELIX_DB_PORT")
+            .expect("HELIX_DB_PORT not found.")
+            .parse()
+            .unwrap(),
+        env::var("HELIX_DB_USER").expect("HELIX_DB_USER not found."),
+        env::var("HELIX_DB_PASSWORD").expect("HELIX_DB_PASSWORD not found."),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore]
+async fn person_lifecycle() {
+    conformance::check_person_lifecycle(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn user_lifecycle() {
+    conformance::check_user_lifecycle(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn login_lookup() {
+    conformance::check_login_lookup(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn user_listing() {
+    conformance::check_user_listing(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn uniqueness() {
+    conformance::check_uniqueness(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn login_counters() {
+    conformance::check_login_counters(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn login_history() {
+    conformance::check_login_history(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn sessions() {
+    conformance::check_sessions(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn email_verification() {
+    conformance::check_email_verification(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn user_status() {
+    conformance::check_user_status(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn soft_delete() {
+    conformance::check_soft_delete(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn two_factor() {
+    conformance::check_two_factor(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn password_resets() {
+    conformance::check_password_resets(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn roles() {
+    conformance::check_roles(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn groups() {
+    conformance::check_groups(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn organizations() {
+    conformance::check_organizations(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn audit_log() {
+    conformance::check_audit_log(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn transactions() {
+    conformance::check_transactions(&get_storage()).await;
+}
+
+#[tokio::test]
+#[ignore]
+async fn migration_dry_run_applies_nothing() {
+    let storage = get_storage();
+    let before = storage.get_migration_status().await.unwrap();
+    let checked = storage.migrate(MigrationMode::DryRun).await.unwrap();
+    let after = storage.get_migration_status().await.unwrap();
+
+    let pending = |status: &Vec<MigrationStatus>| {
+        status
+            .iter()
+            .filter(|migration| migration.is_pending())
+            .map(|migration| migration.version)
+            .collect::<Vec<i32>>()
+    };
+    assert_eq!(pending(&checked), pending(&before));
+    assert_eq!(pending(&after), pending(&before));
+}
+
+#[tokio::test]
+#[ignore]
+async fn purge_lock_is_held_once() {
+    let storage = get_storage();
+    let first = storage.begin().await.unwrap();
+    let second = storage.begin().await.unwrap();
+    assert!(first.as_storage().try_lock_purge().await.unwrap());
+    assert!(!second.as_storage().try_lock_purge().await.unwrap());
+
+    //Released with the transaction holding it.
+    first.rollback().await.unwrap();
+    assert!(second.as_storage().try_lock_purge().await.unwrap());
+    second.rollback().await.unwrap();
+}